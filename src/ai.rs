@@ -0,0 +1,245 @@
+// The computer opponent. It spends its energy on weapons first, closes with
+// the nearest enemy while its heavy weapons are armed, and otherwise turns
+// to keep its strongest shield toward the enemy. How well it does that
+// depends on its difficulty. Anything it leaves to chance comes from its own
+// seeded dice, so the same seed always plays the same way.
+
+use crate::controller::{combat_allocation, nearest_enemy, side_toward, Controller};
+use crate::dice::{Dice, SeededDice};
+use crate::energy::EnergyAllocation;
+use crate::game::{Game, Order};
+use crate::impulse::MAX_SPEED;
+use crate::movement::Side;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+// Hard opponents slow to this speed once within range and put the energy
+// saved into their shields.
+const HARD_CLOSING_RANGE: u8 = 12;
+const HARD_SPEED: u8 = 12;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Difficulty {
+    // Wanders in at an uneven speed, fires at anything in range and now and
+    // then forgets to give orders at all.
+    Easy,
+    // Closes at full speed, fires at short range and turns its strongest
+    // shield toward the enemy while rearming.
+    Normal,
+    // Reinforces the shield facing the enemy, holds fire for point blank
+    // range unless the target's shield facing it is down.
+    Hard,
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difficulty::Easy => write!(f, "easy"),
+            Difficulty::Normal => write!(f, "normal"),
+            Difficulty::Hard => write!(f, "hard"),
+        }
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Difficulty, String> {
+        match s {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "hard" => Ok(Difficulty::Hard),
+            _ => Err(format!("Unknown difficulty {}", s)),
+        }
+    }
+}
+
+pub struct Ai {
+    pub difficulty: Difficulty,
+    dice: SeededDice,
+    // The range it opens fire at this turn.
+    fire_range: u8,
+}
+
+impl Ai {
+    pub fn new(difficulty: Difficulty, seed: u64) -> Ai {
+        Ai {
+            difficulty,
+            dice: SeededDice::new(seed),
+            fire_range: 8,
+        }
+    }
+
+    fn should_fire(&self, game: &Game, ship: usize, target: usize, range: u8) -> bool {
+        match self.difficulty {
+            Difficulty::Easy => true,
+            Difficulty::Normal => range <= self.fire_range,
+            Difficulty::Hard => range <= 4 || (range <= self.fire_range && shield_is_down(game, target, ship)),
+        }
+    }
+}
+
+// Whether the target's shield facing the attacker is at half or less of its
+// strongest.
+fn shield_is_down(game: &Game, target: usize, attacker: usize) -> bool {
+    let t = &game.ships[target];
+    let shields = t.shields();
+    let facing = shields.facing_shield(&t.position, &game.ships[attacker].position.hex);
+    let strongest = (0..6).map(|s| shields.strength(s)).max().unwrap_or(0);
+    shields.strength(facing) * 2 <= strongest
+}
+
+// Whether any of the ship's heavy weapons could fire now.
+fn heavy_weapons_ready(game: &Game, ship: usize) -> bool {
+    let s = &game.ships[ship];
+    s.weapons_ready(game.turn(), game.impulse())
+        .iter()
+        .any(|i| s.weapon_kind(*i).phaser_energy().is_none() && s.weapon_kind(*i).launches().is_none())
+}
+
+// Which way to turn to bring the strongest shield around toward the enemy,
+// or None if it's already facing them.
+pub fn side_to_show_strongest_shield(game: &Game, ship: usize, enemy: usize) -> Option<Side> {
+    let position = game.ships[ship].position;
+    let hex = game.ships[enemy].position.hex;
+    if position.hex == hex {
+        return None;
+    }
+    let shields = game.ships[ship].shields();
+    let facing = shields.facing_shield(&position, &hex);
+    let strongest = (0..6).max_by_key(|s| (shields.strength(*s), 6 - s)).unwrap_or(0);
+    if shields.strength(facing) >= shields.strength(strongest) {
+        return None;
+    }
+    // Turning right swings the enemy one shield to the left, and the other
+    // way around.
+    match (facing + 6 - strongest) % 6 {
+        1..=3 => Some(Side::Right),
+        _ => Some(Side::Left),
+    }
+}
+
+impl Controller for Ai {
+    fn allocate(&mut self, game: &Game, ship: usize) -> EnergyAllocation {
+        let me = &game.ships[ship];
+        let enemy = nearest_enemy(game, ship);
+        let range = enemy.map_or(u8::MAX, |e| me.position.hex.range_to(&game.ships[e].position.hex));
+        self.fire_range = 6 + self.dice.d6() / 2;
+        let speed = match self.difficulty {
+            Difficulty::Easy => 8 + self.dice.d6(),
+            Difficulty::Normal => MAX_SPEED,
+            Difficulty::Hard if range <= HARD_CLOSING_RANGE => HARD_SPEED,
+            Difficulty::Hard => MAX_SPEED,
+        };
+        let mut e = combat_allocation(me, speed);
+        if let (Difficulty::Hard, Some(enemy)) = (self.difficulty, enemy) {
+            let shield = me
+                .shields()
+                .facing_shield(&me.position, &game.ships[enemy].position.hex);
+            e.specific_reinforcement[shield] = e.remaining().clamp(0, u8::MAX as i32) as u8;
+        }
+        e
+    }
+
+    fn orders(&mut self, game: &Game, ship: usize) -> Vec<Order> {
+        let target = match nearest_enemy(game, ship) {
+            Some(target) => target,
+            None => return Vec::new(),
+        };
+        if self.difficulty == Difficulty::Easy && self.dice.d6() <= 2 {
+            return Vec::new();
+        }
+        let mut orders = Vec::new();
+        let range = game.ships[ship].position.hex.range_to(&game.ships[target].position.hex);
+        if !game.weapons_to_fire(ship, target).is_empty() && self.should_fire(game, ship, target, range) {
+            orders.push(Order::Fire { target });
+        }
+        // Close in to use the heavy weapons, and keep the best shield toward
+        // the enemy while they're rearming.
+        let side = if self.difficulty == Difficulty::Easy || heavy_weapons_ready(game, ship) {
+            side_toward(game, ship, target)
+        } else {
+            side_to_show_strongest_shield(game, ship, target)
+        };
+        if let Some(side) = side {
+            orders.push(Order::Turn(side));
+        }
+        orders
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combat::Target;
+    use crate::hex::Hex;
+    use crate::scenario::Scenario;
+    use crate::ship::Position;
+    use crate::sim;
+
+    fn duel(seed: u64) -> Game {
+        Scenario::load("the_duel.toml").unwrap().start(seed).unwrap()
+    }
+
+    fn ais(a: Difficulty, b: Difficulty, seed: u64) -> Vec<Box<dyn Controller>> {
+        vec![Box::new(Ai::new(a, seed)), Box::new(Ai::new(b, seed + 1))]
+    }
+
+    #[test]
+    fn same_seed_same_game() {
+        let play = |seed| sim::play(duel(seed), seed, &mut ais(Difficulty::Easy, Difficulty::Hard, seed));
+        assert_eq!(play(4), play(4));
+        assert_ne!(play(4).ships, play(5).ships);
+    }
+
+    #[test]
+    fn harder_wins_more() {
+        let wins = |a, b| {
+            (1..=10)
+                .map(|seed| sim::play(duel(seed), seed, &mut ais(a, b, seed)))
+                .filter(|r| r.winner.as_deref() == Some("Federation"))
+                .count()
+        };
+        assert!(wins(Difficulty::Hard, Difficulty::Easy) > wins(Difficulty::Easy, Difficulty::Hard));
+    }
+
+    #[test]
+    fn turns_its_strongest_shield_toward_the_enemy() {
+        let mut game = duel(1);
+        let enemy = game.ships[0].position.hex;
+        let position = game.ships[1].position;
+        let facing = game.ships[1].shields().facing_shield(&position, &enemy);
+        game.ships[1].take_hit(&enemy, 25);
+
+        let side = side_to_show_strongest_shield(&game, 1, 0).unwrap();
+        let turned = match side {
+            Side::Left => position.facing.turn_left(),
+            Side::Right => position.facing.turn_right(),
+        };
+        game.ships[1].rotate_to(turned);
+        let shields = game.ships[1].shields();
+        let now_facing = shields.facing_shield(&game.ships[1].position, &enemy);
+        assert!(shields.strength(now_facing) > shields.strength(facing));
+    }
+
+    #[test]
+    fn hard_reinforces_the_shield_facing_the_enemy() {
+        let mut game = duel(1);
+        let far = Ai::new(Difficulty::Hard, 1).allocate(&game, 0);
+        assert!(far.validate().is_ok());
+        assert!(far.speed > HARD_SPEED);
+
+        // Once in range it trades speed for shields.
+        let facing = game.ships[1].position.facing;
+        game.ships[1].move_to(Position {
+            hex: Hex::new(8, 24),
+            facing,
+        });
+        let e = Ai::new(Difficulty::Hard, 1).allocate(&game, 0);
+        assert_eq!(e.speed, HARD_SPEED);
+        assert!(e.specific_reinforcement.iter().any(|r| *r > 0));
+        assert!(e.validate().is_ok());
+    }
+}
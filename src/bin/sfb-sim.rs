@@ -0,0 +1,157 @@
+// Plays a batch of games between computer-controlled ships with no window,
+// and reports how each ship fared. Each game gets its own seed, so any one
+// of them can be played again exactly.
+
+use sfb::ai::{Ai, Difficulty};
+use sfb::controller::{Brawler, Controller, Scripted};
+use sfb::hex::MapEdges;
+use sfb::scenario::Scenario;
+use sfb::sim::{self, GameResult, Summary};
+use std::env;
+use std::process;
+
+const USAGE: &str = "Usage: sfb-sim [options]
+  --games N              games to play (default 100)
+  --seed N               seed for the first game; each game after adds one (default 1)
+  --scenario FILE        scenario, relative to resources/scenarios
+                         (default the_duel.toml)
+  --turns N              turn limit, in place of the scenario's (default 20
+                         if the scenario has none)
+  --controllers A,B,...  brawler, idle, easy, normal or hard for each ship
+                         (default brawler for all)
+  --fire-range N         range at which brawlers open fire (default 8)
+  --map COLSxROWS        map size, in place of the scenario's
+  --floating             play on a floating map that keeps the ships on it
+  --json                 print each game and the summary as JSON lines";
+
+struct Options {
+    games: u32,
+    seed: u64,
+    scenario: String,
+    turns: Option<u16>,
+    controllers: Vec<String>,
+    fire_range: u8,
+    map: Option<(i16, i16)>,
+    floating: bool,
+    json: bool,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        games: 100,
+        seed: 1,
+        scenario: String::from("the_duel.toml"),
+        turns: None,
+        controllers: Vec::new(),
+        fire_range: 8,
+        map: None,
+        floating: false,
+        json: false,
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--json" {
+            options.json = true;
+            continue;
+        }
+        if arg == "--floating" {
+            options.floating = true;
+            continue;
+        }
+        let value = args.next().ok_or_else(|| format!("{} needs a value", arg))?;
+        let number = || format!("{} needs a number, not {}", arg, value);
+        match arg.as_str() {
+            "--games" => options.games = value.parse().map_err(|_| number())?,
+            "--seed" => options.seed = value.parse().map_err(|_| number())?,
+            "--turns" => options.turns = Some(value.parse().map_err(|_| number())?),
+            "--fire-range" => options.fire_range = value.parse().map_err(|_| number())?,
+            "--scenario" => options.scenario = value.clone(),
+            "--map" => options.map = Some(parse_map_size(value).ok_or_else(|| format!("--map needs COLSxROWS, not {}", value))?),
+            "--controllers" => options.controllers = value.split(',').map(String::from).collect(),
+            _ => return Err(format!("Unknown option {}", arg)),
+        }
+    }
+    Ok(options)
+}
+
+fn parse_map_size(value: &str) -> Option<(i16, i16)> {
+    let (cols, rows) = value.split_once('x')?;
+    let (cols, rows) = (cols.parse().ok()?, rows.parse().ok()?);
+    if cols < 1 || rows < 1 {
+        return None;
+    }
+    Some((cols, rows))
+}
+
+// Computer opponents are seeded from the game's seed and their ship, so each
+// game plays out the same every time.
+fn controller(name: &str, options: &Options, seed: u64) -> Result<Box<dyn Controller>, String> {
+    if let Ok(difficulty) = name.parse::<Difficulty>() {
+        return Ok(Box::new(Ai::new(difficulty, seed)));
+    }
+    match name {
+        "brawler" => Ok(Box::new(Brawler {
+            fire_range: options.fire_range,
+        })),
+        // Sits still with its weapons armed.
+        "idle" => Ok(Box::new(Scripted::new(0, Vec::new()))),
+        _ => Err(format!("Unknown controller {}", name)),
+    }
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let scenario = Scenario::load(&options.scenario).map_err(|e| e.to_string())?;
+    let mut results: Vec<GameResult> = Vec::new();
+    for n in 0..options.games {
+        let seed = options.seed + n as u64;
+        let mut game = scenario.start(seed).map_err(|e| e.to_string())?;
+        game.rules.turn_limit = options.turns.or(game.rules.turn_limit).or(Some(20));
+        if let Some((cols, rows)) = options.map {
+            game.rules.map.cols = cols;
+            game.rules.map.rows = rows;
+        }
+        if options.floating {
+            game.rules.map.edges = MapEdges::Floating;
+        }
+        if let Some(hex) = game.off_map() {
+            return Err(format!("Hex {} is off the {}x{} map", hex, game.rules.map.cols, game.rules.map.rows));
+        }
+        if !options.controllers.is_empty() && options.controllers.len() != game.ships.len() {
+            return Err(format!(
+                "--controllers needs one controller for each of the scenario's {} ships",
+                game.ships.len()
+            ));
+        }
+        let mut controllers = (0..game.ships.len())
+            .map(|i| {
+                let name = options.controllers.get(i).map_or("brawler", |c| c.as_str());
+                controller(name, options, seed.wrapping_mul(31).wrapping_add(i as u64))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let result = sim::play(game, seed, &mut controllers);
+        if options.json {
+            println!("{}", serde_json::to_string(&result).map_err(|e| e.to_string())?);
+        }
+        results.push(result);
+    }
+    let summary = Summary::new(&results);
+    if options.json {
+        println!("{}", serde_json::to_string(&summary).map_err(|e| e.to_string())?);
+    } else {
+        print!("{}", summary);
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        println!("{}", USAGE);
+        return;
+    }
+    let result = parse_args(&args).and_then(|options| run(&options));
+    if let Err(e) = result {
+        eprintln!("{}\n\n{}", e, USAGE);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,612 @@
+// Direct-fire combat: range, the weapon hit and damage tables, and applying
+// the results to the target's shields and internals.
+
+use crate::damage::DamageAllocation;
+use crate::dice::Dice;
+use crate::hex::Hex;
+use crate::shields::ShieldDamage;
+use crate::ship::Position;
+use crate::weapons::{Arming, ArmingMode, CapacitorError, Mount, PhaserCapacitor, WeaponKind};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Anything that can be hit by direct-fire weapons.
+pub trait Target {
+    fn name(&self) -> &str;
+    fn position(&self) -> Position;
+    // Applies one shot's damage to the shield facing the given hex.
+    fn take_hit(&mut self, from: &Hex, damage: u8) -> ShieldDamage;
+    // Resolves the internal damage from a volley.
+    fn take_internal_damage(&mut self, hits: u8, dice: &mut dyn Dice) -> Vec<DamageAllocation>;
+    fn is_destroyed(&self) -> bool;
+}
+
+// Anything that can fire direct-fire weapons.
+pub trait Combatant: Target {
+    fn weapons(&self) -> &[Mount];
+    fn weapons_mut(&mut self) -> &mut [Mount];
+    fn capacitor_mut(&mut self) -> &mut PhaserCapacitor;
+}
+
+// Damage by die roll (1 through 6) for targets out to the given range.
+struct Bracket(u8, [u8; 6]);
+
+// A weapon's combined hit and damage table. A damage of 0 is a miss.
+struct Table {
+    min_range: u8,
+    brackets: &'static [Bracket],
+}
+
+const PHASER_1: Table = Table {
+    min_range: 0,
+    brackets: &[
+        Bracket(0, [9, 8, 7, 6, 5, 4]),
+        Bracket(1, [8, 7, 6, 5, 4, 4]),
+        Bracket(2, [7, 6, 5, 4, 4, 3]),
+        Bracket(3, [6, 5, 4, 4, 4, 3]),
+        Bracket(4, [5, 5, 4, 4, 3, 3]),
+        Bracket(5, [5, 4, 4, 4, 3, 3]),
+        Bracket(8, [4, 4, 4, 3, 3, 2]),
+        Bracket(15, [4, 3, 3, 2, 1, 1]),
+        Bracket(25, [3, 2, 1, 1, 0, 0]),
+        Bracket(50, [2, 1, 0, 0, 0, 0]),
+        Bracket(75, [1, 0, 0, 0, 0, 0]),
+    ],
+};
+
+const PHASER_2: Table = Table {
+    min_range: 0,
+    brackets: &[
+        Bracket(0, [6, 5, 5, 4, 4, 3]),
+        Bracket(1, [5, 5, 4, 4, 3, 3]),
+        Bracket(2, [5, 4, 4, 4, 3, 3]),
+        Bracket(3, [4, 4, 4, 3, 3, 2]),
+        Bracket(8, [4, 3, 3, 2, 2, 1]),
+        Bracket(15, [3, 2, 2, 1, 1, 0]),
+        Bracket(30, [2, 1, 1, 0, 0, 0]),
+        Bracket(50, [1, 0, 0, 0, 0, 0]),
+    ],
+};
+
+const PHASER_3: Table = Table {
+    min_range: 0,
+    brackets: &[
+        Bracket(0, [4, 4, 4, 3, 3, 2]),
+        Bracket(1, [4, 4, 3, 2, 1, 1]),
+        Bracket(2, [4, 4, 3, 1, 0, 0]),
+        Bracket(3, [3, 2, 1, 0, 0, 0]),
+        Bracket(8, [1, 1, 0, 0, 0, 0]),
+        Bracket(15, [1, 0, 0, 0, 0, 0]),
+    ],
+};
+
+const DISRUPTOR: Table = Table {
+    min_range: 0,
+    brackets: &[
+        Bracket(0, [5, 5, 5, 5, 5, 5]),
+        Bracket(1, [5, 5, 5, 5, 5, 0]),
+        Bracket(2, [4, 4, 4, 4, 4, 0]),
+        Bracket(4, [4, 4, 4, 4, 0, 0]),
+        Bracket(8, [3, 3, 3, 3, 0, 0]),
+        Bracket(15, [3, 3, 3, 0, 0, 0]),
+        Bracket(22, [2, 2, 2, 0, 0, 0]),
+        Bracket(30, [2, 2, 0, 0, 0, 0]),
+    ],
+};
+
+const PHOTON_TORPEDO: Table = Table {
+    min_range: 2,
+    brackets: &[
+        Bracket(2, [8, 8, 8, 8, 8, 0]),
+        Bracket(4, [8, 8, 8, 8, 0, 0]),
+        Bracket(8, [8, 8, 8, 0, 0, 0]),
+        Bracket(12, [8, 8, 0, 0, 0, 0]),
+        Bracket(30, [8, 0, 0, 0, 0, 0]),
+    ],
+};
+
+// Launchers have no table; what they launch does its own damage.
+fn table(kind: WeaponKind) -> Option<&'static Table> {
+    use WeaponKind::*;
+    match kind {
+        Phaser1 => Some(&PHASER_1),
+        Phaser2 => Some(&PHASER_2),
+        Phaser3 => Some(&PHASER_3),
+        Disruptor => Some(&DISRUPTOR),
+        PhotonTorpedo => Some(&PHOTON_TORPEDO),
+        DroneRack | PlasmaTorpedo => None,
+    }
+}
+
+// Overloaded weapons only fire at short range, proximity torpedoes only at long.
+pub const OVERLOAD_RANGE: u8 = 8;
+pub const PROXIMITY_RANGE: u8 = 9;
+
+pub fn in_range(kind: WeaponKind, range: u8) -> bool {
+    table(kind).is_some_and(|t| range >= t.min_range && t.brackets.iter().any(|b| range <= b.0))
+}
+
+// The damage a weapon does for a given range and die roll, or None if the
+// target is out of range.
+pub fn damage(kind: WeaponKind, range: u8, roll: u8) -> Option<u8> {
+    let t = table(kind)?;
+    if range < t.min_range {
+        return None;
+    }
+    t.brackets.iter().find(|b| range <= b.0).map(|b| b.1[roll as usize - 1])
+}
+
+// A heavy weapon's damage depends on how it was armed: overloads scale with the
+// extra energy put in, and a proximity fuse halves the warhead.
+fn armed_damage(kind: WeaponKind, arming: Arming, damage: u8) -> u8 {
+    match (kind.arming_cycle(), arming) {
+        (Some(cycle), Arming::Armed { mode, energy }) => match mode {
+            ArmingMode::Standard => damage,
+            ArmingMode::Overload => (damage as u16 * energy as u16 / cycle.standard_energy() as u16) as u8,
+            ArmingMode::Proximity => damage / 2,
+        },
+        _ => damage,
+    }
+}
+
+// Overloads only reach short range, and a proximity fuse can't be used up close.
+pub fn armed_in_range(kind: WeaponKind, arming: Arming, range: u8) -> bool {
+    match arming {
+        Arming::Armed {
+            mode: ArmingMode::Overload,
+            ..
+        } => range <= OVERLOAD_RANGE && in_range(kind, range),
+        Arming::Armed {
+            mode: ArmingMode::Proximity,
+            ..
+        } => range >= PROXIMITY_RANGE && in_range(kind, range),
+        _ => in_range(kind, range),
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum FireError {
+    NoSuchMount(usize),
+    MountDestroyed(String),
+    OutOfArc(String),
+    OutOfRange { mount: String, range: u8 },
+    // Unarmed, or fired too recently.
+    NotReady(String),
+    // Not enough charge in the phaser capacitor for the volley's phasers.
+    Capacitor(CapacitorError),
+    // Nothing in arc to fire at.
+    NoTarget,
+    TargetDestroyed,
+    // A ship can't be its own target.
+    SelfTarget,
+}
+
+impl fmt::Display for FireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FireError::NoSuchMount(i) => write!(f, "No weapon mount {}", i),
+            FireError::MountDestroyed(mount) => write!(f, "Weapon {} is destroyed", mount),
+            FireError::OutOfArc(mount) => write!(f, "The target is outside weapon {}'s arc", mount),
+            FireError::OutOfRange { mount, range } => {
+                write!(f, "The target is out of range of weapon {} at range {}", mount, range)
+            }
+            FireError::NotReady(mount) => write!(f, "Weapon {} isn't ready to fire", mount),
+            FireError::Capacitor(e) => write!(f, "{}", e),
+            FireError::NoTarget => write!(f, "Nothing in arc to fire at"),
+            FireError::TargetDestroyed => write!(f, "The target is already destroyed"),
+            FireError::SelfTarget => write!(f, "A ship can't fire at itself"),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Shot {
+    pub mount: String,
+    pub weapon: WeaponKind,
+    // None for weapons that don't need arming.
+    pub mode: Option<ArmingMode>,
+    pub roll: u8,
+    pub damage: u8,
+    // None on a miss.
+    pub shield: Option<ShieldDamage>,
+}
+
+// Everything that happened when one unit fired a volley at another.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct FireReport {
+    pub attacker: String,
+    pub target: String,
+    pub range: u8,
+    pub shots: Vec<Shot>,
+    pub internals: Vec<DamageAllocation>,
+    pub target_destroyed: bool,
+}
+
+impl FireReport {
+    pub fn total_damage(&self) -> u16 {
+        self.shots.iter().map(|s| s.damage as u16).sum()
+    }
+}
+
+impl fmt::Display for FireReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} fires at {} at range {}", self.attacker, self.target, self.range)?;
+        for shot in &self.shots {
+            match &shot.shield {
+                Some(shield) => writeln!(f, "  {} {} rolled {}: {}", shot.weapon, shot.mount, shot.roll, shield)?,
+                None => writeln!(f, "  {} {} rolled {}: miss", shot.weapon, shot.mount, shot.roll)?,
+            }
+        }
+        if !self.internals.is_empty() {
+            let internals: Vec<String> = self.internals.iter().map(|i| i.to_string()).collect();
+            writeln!(f, "  Internals: {}", internals.join(", "))?;
+        }
+        if self.target_destroyed {
+            writeln!(f, "  {} is destroyed!", self.target)?;
+        }
+        Ok(())
+    }
+}
+
+// Fires the attacker's weapon mounts (by index) at the target as one volley
+// on the given turn and impulse. Every shot is checked before any is fired.
+// Each hit is applied to the target's shields, then everything that got
+// through is resolved together on the Damage Allocation Chart.
+pub fn fire(
+    attacker: &mut dyn Combatant,
+    mounts: &[usize],
+    target: &mut dyn Target,
+    turn: u16,
+    impulse: u8,
+    dice: &mut dyn Dice,
+) -> Result<FireReport, FireError> {
+    if target.is_destroyed() {
+        return Err(FireError::TargetDestroyed);
+    }
+    let from = attacker.position();
+    let target_hex = target.position().hex;
+    let range = from.hex.range_to(&target_hex);
+
+    let mut firing = Vec::new();
+    for (n, i) in mounts.iter().enumerate() {
+        let mount = attacker.weapons().get(*i).ok_or(FireError::NoSuchMount(*i))?;
+        let name = mount.weapon.mount.clone();
+        if mount.destroyed {
+            return Err(FireError::MountDestroyed(name));
+        }
+        if !mount.weapon.arc.contains(&from, &target_hex) {
+            return Err(FireError::OutOfArc(name));
+        }
+        if !armed_in_range(mount.weapon.kind, mount.arming, range) {
+            return Err(FireError::OutOfRange { mount: name, range });
+        }
+        if !mount.ready(turn, impulse) || mounts[..n].contains(i) {
+            return Err(FireError::NotReady(name));
+        }
+        firing.push((*i, name, mount.weapon.kind, mount.arming));
+    }
+    let phaser_energy = firing.iter().filter_map(|f| f.2.phaser_energy()).sum();
+    attacker
+        .capacitor_mut()
+        .discharge(phaser_energy)
+        .map_err(FireError::Capacitor)?;
+
+    let mut shots = Vec::new();
+    let mut internal = 0_u8;
+    for (i, mount, weapon, arming) in firing {
+        attacker.weapons_mut()[i].fire(turn, impulse);
+        let roll = dice.d6();
+        let damage = armed_damage(weapon, arming, damage(weapon, range, roll).unwrap_or(0));
+        let mode = match arming {
+            Arming::Armed { mode, .. } => Some(mode),
+            _ => None,
+        };
+        let shield = if damage > 0 {
+            let hit = target.take_hit(&from.hex, damage);
+            internal = internal.saturating_add(hit.internal);
+            Some(hit)
+        } else {
+            None
+        };
+        shots.push(Shot {
+            mount,
+            weapon,
+            mode,
+            roll,
+            damage,
+            shield,
+        });
+    }
+    let internals = target.take_internal_damage(internal, dice);
+
+    Ok(FireReport {
+        attacker: attacker.name().to_string(),
+        target: target.name().to_string(),
+        range,
+        shots,
+        internals,
+        target_destroyed: target.is_destroyed(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::damage::Systems;
+    use crate::dice::{LoadedDice, SeededDice};
+    use crate::hex::{Facing, STANDARD_MAP};
+    use crate::seeking::{SeekerMove, SeekingKind, SeekingWeapon};
+    use crate::shields::Shields;
+    use crate::ship_spec::ShipSpec;
+
+    struct TestShip {
+        name: String,
+        position: Position,
+        shields: Shields,
+        systems: Systems,
+        weapons: Vec<Mount>,
+        capacitor: PhaserCapacitor,
+    }
+
+    impl TestShip {
+        fn new(spec_file: &str, col: i16, row: i16, facing: Facing) -> TestShip {
+            let spec = ShipSpec::new(spec_file);
+            TestShip {
+                name: spec.ship.name.clone(),
+                position: Position {
+                    hex: Hex::new(col, row),
+                    facing,
+                },
+                shields: Shields::new(&spec.defenses),
+                systems: Systems::new(&spec),
+                weapons: spec.weapons.iter().map(Mount::new).collect(),
+                capacitor: PhaserCapacitor::new(&spec.weapons),
+            }
+        }
+
+        // Runs every heavy weapon through its standard arming cycle and
+        // fills the phaser capacitor.
+        fn armed(mut self) -> TestShip {
+            self.capacitor.allocate(self.capacitor.capacity());
+            for m in self.weapons.iter_mut() {
+                if let Some(cycle) = m.arming_cycle() {
+                    loop {
+                        m.arm(cycle.required(m.carried()), false);
+                        if let Arming::Armed { .. } = m.arming {
+                            break;
+                        }
+                        m.end_turn();
+                    }
+                }
+            }
+            self
+        }
+    }
+
+    impl Combatant for TestShip {
+        fn weapons(&self) -> &[Mount] {
+            &self.weapons
+        }
+        fn weapons_mut(&mut self) -> &mut [Mount] {
+            &mut self.weapons
+        }
+        fn capacitor_mut(&mut self) -> &mut PhaserCapacitor {
+            &mut self.capacitor
+        }
+    }
+
+    impl Target for TestShip {
+        fn name(&self) -> &str {
+            &self.name
+        }
+        fn position(&self) -> Position {
+            self.position
+        }
+        fn take_hit(&mut self, from: &Hex, damage: u8) -> ShieldDamage {
+            let shield = self.shields.facing_shield(&self.position, from);
+            self.shields.absorb(shield, damage)
+        }
+        fn take_internal_damage(&mut self, hits: u8, dice: &mut dyn Dice) -> Vec<DamageAllocation> {
+            self.systems.allocate(hits, dice)
+        }
+        fn is_destroyed(&self) -> bool {
+            self.systems.is_destroyed()
+        }
+    }
+
+    fn mount_index(ship: &TestShip, mount: &str) -> usize {
+        ship.weapons.iter().position(|m| m.weapon.mount == mount).unwrap()
+    }
+
+    #[test]
+    fn tables() {
+        assert_eq!(damage(WeaponKind::Phaser1, 0, 1), Some(9));
+        assert_eq!(damage(WeaponKind::Phaser1, 7, 6), Some(2));
+        assert_eq!(damage(WeaponKind::Phaser1, 76, 1), None);
+        assert_eq!(damage(WeaponKind::PhotonTorpedo, 1, 1), None);
+        assert_eq!(damage(WeaponKind::PhotonTorpedo, 10, 2), Some(8));
+        assert_eq!(damage(WeaponKind::PhotonTorpedo, 10, 3), Some(0));
+        assert_eq!(damage(WeaponKind::Disruptor, 30, 2), Some(2));
+        assert!(!in_range(WeaponKind::Disruptor, 31));
+        assert!(!in_range(WeaponKind::Phaser3, 16));
+    }
+
+    #[test]
+    fn volley_through_shields() {
+        // The D7 sits three hexes straight ahead of the CA, facing it.
+        let mut ca = TestShip::new("federation/ca.toml", 10, 10, Facing::A).armed();
+        let mut d7 = TestShip::new("klingon/d7.toml", 10, 7, Facing::D);
+        let torps: Vec<usize> = ["A", "B", "C", "D"].iter().map(|m| mount_index(&ca, m)).collect();
+
+        // All four photons hit on rolls of 1-4 at range 3: 32 damage on the
+        // D7's 30 point #1 shield leaves 2 internals, rolled as two 7s.
+        let mut dice = LoadedDice::new(vec![1, 2, 3, 4, 3, 4, 2, 5]);
+        let report = fire(&mut ca, &torps, &mut d7, 1, 1, &mut dice).unwrap();
+        assert_eq!(report.range, 3);
+        assert_eq!(report.total_damage(), 32);
+        assert!(report.shots.iter().all(|s| s.shield.unwrap().shield == 0));
+        assert_eq!(report.shots[3].shield.unwrap().internal, 2);
+        assert_eq!(report.internals.len(), 2);
+        assert_eq!(d7.shields.boxes()[0], 0);
+        assert!(!report.target_destroyed);
+        assert!(report.to_string().contains("Photon Torpedo A rolled 1"));
+
+        // Fired torpedoes are empty.
+        assert_eq!(
+            fire(&mut ca, &torps, &mut d7, 1, 20, &mut dice),
+            Err(FireError::NotReady(String::from("A")))
+        );
+    }
+
+    #[test]
+    fn unarmed_weapons_cant_fire() {
+        let mut ca = TestShip::new("federation/ca.toml", 10, 10, Facing::A);
+        let mut d7 = TestShip::new("klingon/d7.toml", 10, 7, Facing::D);
+        let a = mount_index(&ca, "A");
+        let mut dice = LoadedDice::new(vec![]);
+        assert_eq!(
+            fire(&mut ca, &[a], &mut d7, 1, 1, &mut dice),
+            Err(FireError::NotReady(String::from("A")))
+        );
+    }
+
+    #[test]
+    fn phasers_draw_on_the_capacitor() {
+        let mut ca = TestShip::new("federation/ca.toml", 10, 10, Facing::A);
+        let mut d7 = TestShip::new("klingon/d7.toml", 10, 7, Facing::D);
+        let phasers: Vec<usize> = ["1", "2"].iter().map(|m| mount_index(&ca, m)).collect();
+        ca.capacitor.allocate(1);
+        assert_eq!(
+            fire(&mut ca, &phasers, &mut d7, 1, 1, &mut LoadedDice::new(vec![])),
+            Err(FireError::Capacitor(CapacitorError { needed: 2, charge: 1 }))
+        );
+        assert_eq!(ca.capacitor.charge(), 1);
+
+        ca.capacitor.allocate(4);
+        fire(&mut ca, &phasers, &mut d7, 1, 1, &mut LoadedDice::new(vec![1, 1])).unwrap();
+        assert_eq!(ca.capacitor.charge(), 2);
+    }
+
+    #[test]
+    fn phasers_shoot_down_drones() {
+        let mut ca = TestShip::new("federation/ca.toml", 10, 10, Facing::A).armed();
+        let position = Position {
+            hex: Hex::new(10, 7),
+            facing: Facing::D,
+        };
+        let mut drone = SeekingWeapon::launch(String::from("Drone 1"), SeekingKind::Drone, position, 1, 0);
+        let (one, two) = (mount_index(&ca, "1"), mount_index(&ca, "2"));
+        let report = fire(&mut ca, &[one], &mut drone, 1, 1, &mut LoadedDice::new(vec![1])).unwrap();
+        assert_eq!(report.total_damage(), 6);
+        assert!(report.target_destroyed);
+        assert_eq!(
+            fire(&mut ca, &[two], &mut drone, 1, 1, &mut LoadedDice::new(vec![])),
+            Err(FireError::TargetDestroyed)
+        );
+    }
+
+    #[test]
+    fn drones_strike_the_facing_shield() {
+        let mut ca = TestShip::new("federation/ca.toml", 10, 10, Facing::A);
+        let position = Position {
+            hex: Hex::new(10, 12),
+            facing: Facing::A,
+        };
+        let mut drone = SeekingWeapon::launch(String::from("Drone 1"), SeekingKind::Drone, position, 1, 0);
+        let mut result = SeekerMove::Waiting;
+        for impulse in 1..=32 {
+            result = drone.on_impulse(impulse, &ca.position.hex, &STANDARD_MAP);
+            if result == SeekerMove::Hit {
+                break;
+            }
+        }
+        assert_eq!(result, SeekerMove::Hit);
+        let hit = drone.strike(&mut ca, &mut LoadedDice::new(vec![]));
+        // From directly behind, on the CA's 20 point #4 shield.
+        assert_eq!(hit.shield.shield, 3);
+        assert_eq!(hit.shield.shield_absorbed, 12);
+        assert_eq!(ca.shields.boxes()[3], 8);
+        assert!(hit.to_string().starts_with("Drone 1 hits Federation CA"));
+    }
+
+    #[test]
+    fn overloads_and_proximity() {
+        let mut ca = TestShip::new("federation/ca.toml", 10, 10, Facing::A);
+        let mut d7 = TestShip::new("klingon/d7.toml", 10, 7, Facing::D);
+        let (a, b) = (mount_index(&ca, "A"), mount_index(&ca, "B"));
+        for m in ca.weapons.iter_mut().take(2) {
+            m.arm(2, false);
+            m.end_turn();
+        }
+        ca.weapons[a].arm(6, false);
+        ca.weapons[b].arm(2, true);
+
+        // The overload does double damage; the proximity torpedo can't fire this close.
+        assert_eq!(
+            fire(&mut ca, &[b], &mut d7, 1, 1, &mut LoadedDice::new(vec![])),
+            Err(FireError::OutOfRange {
+                mount: String::from("B"),
+                range: 3
+            })
+        );
+        let report = fire(&mut ca, &[a], &mut d7, 1, 1, &mut LoadedDice::new(vec![1])).unwrap();
+        assert_eq!(report.shots[0].mode, Some(ArmingMode::Overload));
+        assert_eq!(report.total_damage(), 16);
+
+        let mut far = TestShip::new("klingon/d7.toml", 10, 0, Facing::D);
+        let report = fire(&mut ca, &[b], &mut far, 1, 1, &mut LoadedDice::new(vec![1])).unwrap();
+        assert_eq!(report.total_damage(), 4);
+    }
+
+    #[test]
+    fn misses() {
+        let mut ca = TestShip::new("federation/ca.toml", 10, 10, Facing::A).armed();
+        let mut d7 = TestShip::new("klingon/d7.toml", 10, 7, Facing::D);
+        let mut dice = LoadedDice::new(vec![5]);
+        let a = mount_index(&ca, "A");
+        let report = fire(&mut ca, &[a], &mut d7, 1, 1, &mut dice).unwrap();
+        assert_eq!(report.shots[0].damage, 0);
+        assert_eq!(report.shots[0].shield, None);
+        assert_eq!(d7.shields.boxes()[0], 30);
+    }
+
+    #[test]
+    fn arcs_and_ranges_checked() {
+        let mut ca = TestShip::new("federation/ca.toml", 10, 10, Facing::A).armed();
+        let mut behind = TestShip::new("klingon/d7.toml", 10, 15, Facing::A);
+        let mut dice = LoadedDice::new(vec![]);
+        let a = mount_index(&ca, "A");
+        assert_eq!(
+            fire(&mut ca, &[a], &mut behind, 1, 1, &mut dice),
+            Err(FireError::OutOfArc(String::from("A")))
+        );
+
+        let mut adjacent = TestShip::new("klingon/d7.toml", 10, 9, Facing::A);
+        assert_eq!(
+            fire(&mut ca, &[a], &mut adjacent, 1, 1, &mut dice),
+            Err(FireError::OutOfRange {
+                mount: String::from("A"),
+                range: 1
+            })
+        );
+        assert_eq!(
+            fire(&mut ca, &[99], &mut adjacent, 1, 1, &mut dice),
+            Err(FireError::NoSuchMount(99))
+        );
+    }
+
+    #[test]
+    fn seeded_fire_replays() {
+        let run = || {
+            let mut d7 = TestShip::new("klingon/d7.toml", 20, 10, Facing::F).armed();
+            let mut ca = TestShip::new("federation/ca.toml", 16, 8, Facing::B);
+            let mounts: Vec<usize> = (0..d7.weapons.len())
+                .filter(|i| d7.weapons[*i].bears_on(&d7.position, &ca.position.hex))
+                .collect();
+            fire(&mut d7, &mounts, &mut ca, 1, 1, &mut SeededDice::new(99)).unwrap()
+        };
+        let report = run();
+        assert!(!report.shots.is_empty());
+        assert_eq!(report, run());
+    }
+}
@@ -0,0 +1,192 @@
+// Controllers decide what a ship does: how it spends its energy each turn and
+// what it orders between impulses. A game runs the same whether its ships
+// answer to a player, a script or the computer.
+
+use crate::combat::Target;
+use crate::energy::EnergyAllocation;
+use crate::game::{Game, Order};
+use crate::hex::Facing;
+use crate::impulse::MAX_SPEED;
+use crate::movement::Side;
+use crate::ship::Ship;
+
+pub trait Controller {
+    // The ship's energy allocation for the coming turn.
+    fn allocate(&mut self, game: &Game, ship: usize) -> EnergyAllocation;
+    // Orders to carry out before the next impulse runs.
+    fn orders(&mut self, game: &Game, ship: usize) -> Vec<Order>;
+}
+
+// Keeps every heavy weapon arming and tops up the phaser capacitor, then puts
+// what's left into movement, up to the given speed. Weapons are dropped from
+// the end of the list if the ship can't afford them all, and a ship with
+// too little power left goes without fire control and then life support.
+pub fn combat_allocation(ship: &Ship, max_speed: u8) -> EnergyAllocation {
+    let mut e = ship.get_energy_allocation();
+    e.speed = 0;
+    e.phaser_capacitor = e.capacitor_capacity.saturating_sub(e.capacitor_charge);
+    for w in e.heavy_weapons.iter_mut() {
+        w.energy = w.required;
+    }
+    while e.remaining() < 0 {
+        if let Some(w) = e.heavy_weapons.iter_mut().rev().find(|w| w.energy > 0) {
+            w.energy = 0;
+        } else if e.phaser_capacitor > 0 {
+            e.phaser_capacitor = 0;
+        } else if e.fire_control > 0 {
+            e.fire_control = 0;
+        } else if e.life_support > 0 {
+            e.life_support = 0;
+        } else {
+            break;
+        }
+    }
+    let hexes = e.remaining().max(0) as u16 / e.movement_cost.max(1) as u16;
+    e.speed = hexes.min(max_speed as u16) as u8;
+    e
+}
+
+// The closest enemy ship that's still in the fight.
+pub fn nearest_enemy(game: &Game, ship: usize) -> Option<usize> {
+    let from = game.ships[ship].position.hex;
+    (0..game.ships.len())
+        .filter(|i| game.are_enemies(ship, *i) && !game.ships[*i].is_destroyed())
+        .min_by_key(|i| from.distance_to(&game.ships[*i].position.hex))
+}
+
+// Which way to turn to bring the target toward the ship's nose, or None if
+// it's already dead ahead.
+pub fn side_toward(game: &Game, ship: usize, target: usize) -> Option<Side> {
+    let position = game.ships[ship].position;
+    let hex = game.ships[target].position.hex;
+    if position.hex == hex {
+        return None;
+    }
+    let (a, b) = position.hex.bearing_to(&hex).relative_to(position.facing).sides();
+    if a == Facing::A || b == Facing::A {
+        return None;
+    }
+    match a {
+        Facing::B | Facing::C | Facing::D => Some(Side::Right),
+        _ => Some(Side::Left),
+    }
+}
+
+// Follows a fixed list of orders, each given before a particular turn and
+// impulse, and otherwise keeps its weapons armed at a set speed.
+pub struct Scripted {
+    speed: u8,
+    script: Vec<(u16, u8, Order)>,
+}
+
+impl Scripted {
+    pub fn new(speed: u8, script: Vec<(u16, u8, Order)>) -> Scripted {
+        Scripted { speed, script }
+    }
+}
+
+impl Controller for Scripted {
+    fn allocate(&mut self, game: &Game, ship: usize) -> EnergyAllocation {
+        combat_allocation(&game.ships[ship], self.speed)
+    }
+
+    fn orders(&mut self, game: &Game, _ship: usize) -> Vec<Order> {
+        let next = game.next_impulse();
+        self.script
+            .iter()
+            .filter(|(turn, impulse, _)| (*turn, *impulse) == next)
+            .map(|(_, _, order)| order.clone())
+            .collect()
+    }
+}
+
+// Heads straight for the nearest enemy as fast as it can and fires
+// everything once it's within the given range.
+pub struct Brawler {
+    pub fire_range: u8,
+}
+
+impl Controller for Brawler {
+    fn allocate(&mut self, game: &Game, ship: usize) -> EnergyAllocation {
+        combat_allocation(&game.ships[ship], MAX_SPEED)
+    }
+
+    fn orders(&mut self, game: &Game, ship: usize) -> Vec<Order> {
+        let target = match nearest_enemy(game, ship) {
+            Some(target) => target,
+            None => return Vec::new(),
+        };
+        let mut orders = Vec::new();
+        if let Some(side) = side_toward(game, ship, target) {
+            orders.push(Order::Turn(side));
+        }
+        let range = game.ships[ship].position.hex.range_to(&game.ships[target].position.hex);
+        if range <= self.fire_range && !game.weapons_to_fire(ship, target).is_empty() {
+            orders.push(Order::Fire { target });
+        }
+        orders
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::Scenario;
+
+    fn duel() -> Game {
+        Scenario::load("the_duel.toml").unwrap().start(1).unwrap()
+    }
+
+    #[test]
+    fn allocation_arms_weapons_first() {
+        let game = duel();
+        let e = combat_allocation(&game.ships[0], MAX_SPEED);
+        assert!(e.validate().is_ok());
+        assert_eq!(e.remaining(), 0);
+        assert!(e.heavy_weapons.iter().all(|w| w.energy == w.required));
+        assert_eq!(e.capacitor_after(), e.capacitor_capacity);
+
+        let slow = combat_allocation(&game.ships[0], 4);
+        assert_eq!(slow.speed, 4);
+        assert!(slow.remaining() > 0);
+    }
+
+    #[test]
+    fn crippled_ships_go_without() {
+        let game = duel();
+        let mut spec = game.ships[0].spec().clone();
+        spec.power.left_warp = 0;
+        spec.power.right_warp = 0;
+        spec.power.battery = 0;
+        spec.power.impulse = 1;
+        let ship = Ship::from_spec(spec.clone(), game.ships[0].position, 0);
+        let e = combat_allocation(&ship, MAX_SPEED);
+        assert!(e.validate().is_ok());
+        assert_eq!((e.life_support, e.fire_control, e.speed), (1, 0, 0));
+
+        spec.power.impulse = 0;
+        let ship = Ship::from_spec(spec, game.ships[0].position, 0);
+        let e = combat_allocation(&ship, MAX_SPEED);
+        assert!(e.validate().is_ok());
+        assert_eq!((e.life_support, e.fire_control), (0, 0));
+    }
+
+    #[test]
+    fn scripted_orders_come_on_time() {
+        let game = duel();
+        let mut script = Scripted::new(
+            10,
+            vec![(1, 1, Order::Turn(Side::Left)), (1, 2, Order::Turn(Side::Right))],
+        );
+        assert_eq!(script.orders(&game, 0), vec![Order::Turn(Side::Left)]);
+    }
+
+    #[test]
+    fn brawler_turns_toward_the_enemy() {
+        let game = duel();
+        let mut brawler = Brawler { fire_range: 8 };
+        // The CA faces A from the lower left corner; the D7 is up and to its right.
+        assert_eq!(brawler.orders(&game, 0), vec![Order::Turn(Side::Right)]);
+        assert_eq!(nearest_enemy(&game, 1), Some(0));
+    }
+}
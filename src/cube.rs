@@ -0,0 +1,305 @@
+// Cube coordinates for hex arithmetic.
+// https://www.redblobgames.com/grids/hexagons/#coordinates-cube
+// The map's odd-q offset coordinates are good for labelling hexes but awkward
+// to do sums with, since what's adjacent depends on whether the column is odd.
+// In cube coordinates every direction is the same step from any hex, so
+// moves, rotations and distances are plain arithmetic. x runs with the
+// columns, and z down the map; axial coordinates are just x and z.
+
+use crate::hex::{Facing, Hex};
+use std::ops::{Add, Mul, Neg, Sub};
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Cube {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+pub const ORIGIN: Cube = Cube { x: 0, y: 0, z: 0 };
+
+// One step in each direction, in Facing order.
+const DIRECTIONS: [Cube; 6] = [
+    Cube { x: 0, y: 1, z: -1 },
+    Cube { x: 1, y: 0, z: -1 },
+    Cube { x: 1, y: -1, z: 0 },
+    Cube { x: 0, y: -1, z: 1 },
+    Cube { x: -1, y: 0, z: 1 },
+    Cube { x: -1, y: 1, z: 0 },
+];
+
+impl Cube {
+    pub fn from_axial(q: i32, r: i32) -> Cube {
+        Cube { x: q, y: -q - r, z: r }
+    }
+
+    pub fn to_axial(&self) -> (i32, i32) {
+        (self.x, self.z)
+    }
+
+    // Any column and row, on the map or not.
+    pub fn from_offset(col: i32, row: i32) -> Cube {
+        Cube::from_axial(col, row - (col - (col & 1)) / 2)
+    }
+
+    pub fn to_offset(&self) -> (i32, i32) {
+        (self.x, self.z + (self.x - (self.x & 1)) / 2)
+    }
+
+    pub fn to_hex(&self) -> Hex {
+        let (col, row) = self.to_offset();
+        Hex::new(col as i16, row as i16)
+    }
+
+    pub fn direction(f: Facing) -> Cube {
+        DIRECTIONS[f as usize]
+    }
+
+    pub fn neighbor(&self, f: Facing) -> Cube {
+        *self + Cube::direction(f)
+    }
+
+    // Distance from the origin.
+    pub fn length(&self) -> i32 {
+        (self.x.abs() + self.y.abs() + self.z.abs()) / 2
+    }
+
+    pub fn distance_to(&self, other: &Cube) -> i32 {
+        (*self - *other).length()
+    }
+
+    // Turned clockwise about the origin by as many hexsides as the facing is
+    // from A, so a vector pointing A ends up pointing `by`.
+    pub fn rotate(&self, by: Facing) -> Cube {
+        let mut c = *self;
+        for _ in 0..by as u8 {
+            c = Cube {
+                x: -c.z,
+                y: -c.x,
+                z: -c.y,
+            };
+        }
+        c
+    }
+
+    pub fn rotate_about(&self, center: &Cube, by: Facing) -> Cube {
+        (*self - *center).rotate(by) + *center
+    }
+
+    // Mirrored across the line through the origin running toward `across`
+    // and its opposite.
+    pub fn reflect(&self, across: Facing) -> Cube {
+        // Reflecting across the A-D line swaps y and z and negates them all;
+        // other lines are that, rotated.
+        let back = Facing::from_index(6 - across as u8);
+        let c = self.rotate(back);
+        Cube {
+            x: -c.x,
+            y: -c.z,
+            z: -c.y,
+        }
+        .rotate(across)
+    }
+
+    pub fn reflect_about(&self, center: &Cube, across: Facing) -> Cube {
+        (*self - *center).reflect(across) + *center
+    }
+
+    // The hexes exactly `radius` away, clockwise from the one straight up.
+    pub fn ring(&self, radius: u32) -> Vec<Cube> {
+        if radius == 0 {
+            return vec![*self];
+        }
+        let mut hexes = Vec::with_capacity(6 * radius as usize);
+        let mut c = *self + Cube::direction(Facing::A) * radius as i32;
+        for i in 0..6 {
+            let f = Facing::from_index(i + 2);
+            for _ in 0..radius {
+                hexes.push(c);
+                c = c.neighbor(f);
+            }
+        }
+        hexes
+    }
+
+    // Every hex within `radius`, from the center outward ring by ring.
+    pub fn spiral(&self, radius: u32) -> Vec<Cube> {
+        (0..=radius).flat_map(|r| self.ring(r)).collect()
+    }
+
+    // The hexes a straight line between the two centers passes through, both
+    // ends included. Where the line runs exactly along a hexside, it takes
+    // the same side every time.
+    pub fn line_to(&self, other: &Cube) -> Vec<Cube> {
+        let n = self.distance_to(other);
+        if n == 0 {
+            return vec![*self];
+        }
+        // Nudged off the hexsides so ties round the same way.
+        let (ax, ay, az) = (self.x as f64 + 1e-6, self.y as f64 + 2e-6, self.z as f64 - 3e-6);
+        let (bx, by, bz) = (other.x as f64 + 1e-6, other.y as f64 + 2e-6, other.z as f64 - 3e-6);
+        (0..=n)
+            .map(|i| {
+                let t = i as f64 / n as f64;
+                Cube::round(ax + (bx - ax) * t, ay + (by - ay) * t, az + (bz - az) * t)
+            })
+            .collect()
+    }
+
+    fn round(x: f64, y: f64, z: f64) -> Cube {
+        let (mut rx, mut ry, mut rz) = (x.round(), y.round(), z.round());
+        let (dx, dy, dz) = ((rx - x).abs(), (ry - y).abs(), (rz - z).abs());
+        if dx > dy && dx > dz {
+            rx = -ry - rz;
+        } else if dy > dz {
+            ry = -rx - rz;
+        } else {
+            rz = -rx - ry;
+        }
+        Cube {
+            x: rx as i32,
+            y: ry as i32,
+            z: rz as i32,
+        }
+    }
+}
+
+impl From<Hex> for Cube {
+    fn from(hex: Hex) -> Cube {
+        Cube::from_offset(hex.col as i32, hex.row as i32)
+    }
+}
+
+impl Add for Cube {
+    type Output = Cube;
+
+    fn add(self, other: Cube) -> Cube {
+        Cube {
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        }
+    }
+}
+
+impl Sub for Cube {
+    type Output = Cube;
+
+    fn sub(self, other: Cube) -> Cube {
+        Cube {
+            x: self.x - other.x,
+            y: self.y - other.y,
+            z: self.z - other.z,
+        }
+    }
+}
+
+impl Neg for Cube {
+    type Output = Cube;
+
+    fn neg(self) -> Cube {
+        Cube {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+}
+
+impl Mul<i32> for Cube {
+    type Output = Cube;
+
+    fn mul(self, k: i32) -> Cube {
+        Cube {
+            x: self.x * k,
+            y: self.y * k,
+            z: self.z * k,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex::STANDARD_MAP;
+    use proptest::prelude::*;
+
+    fn hex() -> impl Strategy<Value = Hex> {
+        (0..STANDARD_MAP.cols, 0..STANDARD_MAP.rows).prop_map(|(col, row)| Hex::new(col, row))
+    }
+
+    fn facing() -> impl Strategy<Value = Facing> {
+        (0..6u8).prop_map(Facing::from_index)
+    }
+
+    #[test]
+    fn directions_match_the_map() {
+        for col in [10, 11] {
+            let hex = Hex::new(col, 10);
+            for i in 0..6 {
+                let f = Facing::from_index(i);
+                assert_eq!(Cube::from(hex).neighbor(f).to_hex(), hex.neighbor(f));
+            }
+        }
+        assert_eq!(Cube::direction(Facing::A).rotate(Facing::C), Cube::direction(Facing::C));
+        assert_eq!(Cube::direction(Facing::B).reflect(Facing::A), Cube::direction(Facing::F));
+        assert_eq!(ORIGIN.ring(0), vec![ORIGIN]);
+        assert_eq!(ORIGIN.spiral(2).len(), 19);
+    }
+
+    proptest! {
+        #[test]
+        fn round_trips(hex in hex()) {
+            let c = Cube::from(hex);
+            prop_assert_eq!(c.x + c.y + c.z, 0);
+            prop_assert_eq!(c.to_hex(), hex);
+            let (q, r) = c.to_axial();
+            prop_assert_eq!(Cube::from_axial(q, r), c);
+        }
+
+        #[test]
+        fn distance_matches_the_map(a in hex(), b in hex()) {
+            let d = a.distance_to(&b);
+            prop_assert_eq!(Cube::from(a).distance_to(&Cube::from(b)), d);
+            prop_assert_eq!((Cube::from(b) - Cube::from(a)).length(), d);
+            prop_assert_eq!(Cube::from(a) + (Cube::from(b) - Cube::from(a)), Cube::from(b));
+        }
+
+        #[test]
+        fn rotation_and_reflection_keep_distances(a in hex(), b in hex(), center in hex(), f in facing()) {
+            let (a, b, center) = (Cube::from(a), Cube::from(b), Cube::from(center));
+            let d = a.distance_to(&b);
+            prop_assert_eq!(a.rotate_about(&center, f).distance_to(&b.rotate_about(&center, f)), d);
+            prop_assert_eq!(a.reflect_about(&center, f).distance_to(&b.reflect_about(&center, f)), d);
+            prop_assert_eq!(a.rotate_about(&center, f).distance_to(&center), a.distance_to(&center));
+            prop_assert_eq!(a.reflect(f).reflect(f), a);
+            let back = Facing::from_index(6 - f as u8);
+            prop_assert_eq!(a.rotate(f).rotate(back), a);
+        }
+
+        #[test]
+        fn rings_are_at_their_radius(center in hex(), radius in 0..8u32) {
+            let c = Cube::from(center);
+            let ring = c.ring(radius);
+            prop_assert_eq!(ring.len(), if radius == 0 { 1 } else { 6 * radius as usize });
+            for h in &ring {
+                prop_assert_eq!(h.distance_to(&c), radius as i32);
+                prop_assert_eq!(h.to_hex().distance_to(&center) as u32, radius);
+            }
+            let spiral = c.spiral(radius);
+            let r = radius as usize;
+            prop_assert_eq!(spiral.len(), 1 + 3 * r * (r + 1));
+        }
+
+        #[test]
+        fn lines_step_one_hex_at_a_time(a in hex(), b in hex()) {
+            let line = Cube::from(a).line_to(&Cube::from(b));
+            prop_assert_eq!(line.len() as i32, a.distance_to(&b) + 1);
+            prop_assert_eq!(line[0], Cube::from(a));
+            prop_assert_eq!(*line.last().unwrap(), Cube::from(b));
+            for pair in line.windows(2) {
+                prop_assert_eq!(pair[0].distance_to(&pair[1]), 1);
+            }
+        }
+    }
+}
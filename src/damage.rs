@@ -0,0 +1,417 @@
+// Internal damage: the ship's systems and the Damage Allocation Chart that
+// decides which of them each internal hit destroys.
+
+use crate::dice::Dice;
+use crate::ship_spec::ShipSpec;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum System {
+    Bridge,
+    ForwardHull,
+    AftHull,
+    LeftWarp,
+    CenterWarp,
+    RightWarp,
+    Impulse,
+    Reactor,
+    Battery,
+    Phaser,
+    Torpedo,
+    Drone,
+    Tractor,
+    Transporter,
+    Shuttle,
+    ExcessDamage,
+}
+
+const SYSTEM_COUNT: usize = System::ExcessDamage as usize + 1;
+
+const ALL_SYSTEMS: [System; SYSTEM_COUNT] = [
+    System::Bridge,
+    System::ForwardHull,
+    System::AftHull,
+    System::LeftWarp,
+    System::CenterWarp,
+    System::RightWarp,
+    System::Impulse,
+    System::Reactor,
+    System::Battery,
+    System::Phaser,
+    System::Torpedo,
+    System::Drone,
+    System::Tractor,
+    System::Transporter,
+    System::Shuttle,
+    System::ExcessDamage,
+];
+
+impl fmt::Display for System {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use System::*;
+        let name = match self {
+            Bridge => "Bridge",
+            ForwardHull => "Forward Hull",
+            AftHull => "Aft Hull",
+            LeftWarp => "Left Warp",
+            CenterWarp => "Center Warp",
+            RightWarp => "Right Warp",
+            Impulse => "Impulse",
+            Reactor => "Reactor",
+            Battery => "Battery",
+            Phaser => "Phaser",
+            Torpedo => "Torpedo",
+            Drone => "Drone",
+            Tractor => "Tractor",
+            Transporter => "Transporter",
+            Shuttle => "Shuttle",
+            ExcessDamage => "Excess Damage",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// One box on the chart. Capitalized boxes on the printed chart can only be
+// scored once per volley; further hits on that row pass them by.
+struct Entry(System, bool);
+
+use System::*;
+
+// Rows for 2d6 rolls of 2 through 12, read left to right. A hit goes to the
+// first system on its row that still has boxes left. Every row ends in excess
+// damage, and when that is gone the ship is destroyed.
+const CHART: [&[Entry]; 11] = [
+    // 2
+    &[
+        Entry(Bridge, true),
+        Entry(Tractor, false),
+        Entry(Transporter, false),
+        Entry(Shuttle, false),
+        Entry(AftHull, false),
+        Entry(LeftWarp, false),
+        Entry(Impulse, false),
+        Entry(Battery, false),
+        Entry(ForwardHull, false),
+        Entry(RightWarp, false),
+        Entry(ExcessDamage, false),
+    ],
+    // 3
+    &[
+        Entry(Drone, true),
+        Entry(Phaser, false),
+        Entry(Impulse, false),
+        Entry(LeftWarp, false),
+        Entry(RightWarp, false),
+        Entry(AftHull, false),
+        Entry(Shuttle, false),
+        Entry(Battery, false),
+        Entry(CenterWarp, false),
+        Entry(Tractor, false),
+        Entry(ExcessDamage, false),
+    ],
+    // 4
+    &[
+        Entry(Phaser, true),
+        Entry(Transporter, false),
+        Entry(RightWarp, false),
+        Entry(Impulse, false),
+        Entry(ForwardHull, false),
+        Entry(AftHull, false),
+        Entry(Shuttle, false),
+        Entry(CenterWarp, false),
+        Entry(Battery, false),
+        Entry(Torpedo, false),
+        Entry(ExcessDamage, false),
+    ],
+    // 5
+    &[
+        Entry(RightWarp, true),
+        Entry(AftHull, false),
+        Entry(Battery, false),
+        Entry(Shuttle, false),
+        Entry(Torpedo, false),
+        Entry(LeftWarp, false),
+        Entry(Impulse, false),
+        Entry(Tractor, false),
+        Entry(Phaser, false),
+        Entry(ExcessDamage, false),
+    ],
+    // 6
+    &[
+        Entry(ForwardHull, true),
+        Entry(Impulse, false),
+        Entry(LeftWarp, false),
+        Entry(AftHull, false),
+        Entry(Reactor, false),
+        Entry(Transporter, false),
+        Entry(Battery, false),
+        Entry(Phaser, false),
+        Entry(Shuttle, false),
+        Entry(Torpedo, false),
+        Entry(ExcessDamage, false),
+    ],
+    // 7
+    &[
+        Entry(ForwardHull, false),
+        Entry(AftHull, false),
+        Entry(LeftWarp, false),
+        Entry(RightWarp, false),
+        Entry(CenterWarp, false),
+        Entry(Battery, false),
+        Entry(Phaser, false),
+        Entry(ExcessDamage, false),
+    ],
+    // 8
+    &[
+        Entry(AftHull, true),
+        Entry(Impulse, false),
+        Entry(RightWarp, false),
+        Entry(ForwardHull, false),
+        Entry(Reactor, false),
+        Entry(Transporter, false),
+        Entry(Battery, false),
+        Entry(Phaser, false),
+        Entry(Shuttle, false),
+        Entry(Torpedo, false),
+        Entry(ExcessDamage, false),
+    ],
+    // 9
+    &[
+        Entry(LeftWarp, true),
+        Entry(ForwardHull, false),
+        Entry(Battery, false),
+        Entry(Shuttle, false),
+        Entry(Drone, false),
+        Entry(RightWarp, false),
+        Entry(Impulse, false),
+        Entry(Tractor, false),
+        Entry(Phaser, false),
+        Entry(ExcessDamage, false),
+    ],
+    // 10
+    &[
+        Entry(Phaser, true),
+        Entry(Tractor, false),
+        Entry(LeftWarp, false),
+        Entry(Impulse, false),
+        Entry(AftHull, false),
+        Entry(ForwardHull, false),
+        Entry(Shuttle, false),
+        Entry(CenterWarp, false),
+        Entry(Battery, false),
+        Entry(Torpedo, false),
+        Entry(ExcessDamage, false),
+    ],
+    // 11
+    &[
+        Entry(Torpedo, true),
+        Entry(Phaser, false),
+        Entry(Impulse, false),
+        Entry(RightWarp, false),
+        Entry(LeftWarp, false),
+        Entry(ForwardHull, false),
+        Entry(Shuttle, false),
+        Entry(Battery, false),
+        Entry(CenterWarp, false),
+        Entry(Transporter, false),
+        Entry(ExcessDamage, false),
+    ],
+    // 12
+    &[
+        Entry(Bridge, true),
+        Entry(Tractor, false),
+        Entry(Transporter, false),
+        Entry(Shuttle, false),
+        Entry(ForwardHull, false),
+        Entry(RightWarp, false),
+        Entry(Impulse, false),
+        Entry(Battery, false),
+        Entry(AftHull, false),
+        Entry(LeftWarp, false),
+        Entry(ExcessDamage, false),
+    ],
+];
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct DamageAllocation {
+    pub roll: u8,
+    // None when the hit destroyed the ship outright.
+    pub system: Option<System>,
+}
+
+impl fmt::Display for DamageAllocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.system {
+            Some(system) => write!(f, "{}: {}", self.roll, system),
+            None => write!(f, "{}: ship destroyed", self.roll),
+        }
+    }
+}
+
+// The boxes of every system on a ship, and how many are left.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Systems {
+    total: [u8; SYSTEM_COUNT],
+    remaining: [u8; SYSTEM_COUNT],
+    destroyed: bool,
+}
+
+impl Systems {
+    pub fn new(spec: &ShipSpec) -> Systems {
+        let mut total = [0; SYSTEM_COUNT];
+        total[Bridge as usize] = spec.ship.bridge;
+        total[ForwardHull as usize] = spec.ship.forward_hull;
+        total[AftHull as usize] = spec.ship.aft_hull;
+        total[LeftWarp as usize] = spec.power.left_warp;
+        total[CenterWarp as usize] = spec.power.center_warp;
+        total[RightWarp as usize] = spec.power.right_warp;
+        total[Impulse as usize] = spec.power.impulse;
+        total[Reactor as usize] = spec.power.reactor;
+        total[Battery as usize] = spec.power.battery;
+        total[Tractor as usize] = spec.ship.tractor;
+        total[Transporter as usize] = spec.ship.transporter;
+        total[Shuttle as usize] = spec.ship.shuttle;
+        total[ExcessDamage as usize] = spec.ship.excess_damage;
+        for weapon in &spec.weapons {
+            total[weapon.kind.system() as usize] += 1;
+        }
+        Systems {
+            total,
+            remaining: total,
+            destroyed: false,
+        }
+    }
+
+    pub fn total(&self, system: System) -> u8 {
+        self.total[system as usize]
+    }
+
+    pub fn remaining(&self, system: System) -> u8 {
+        self.remaining[system as usize]
+    }
+
+    pub fn is_destroyed(&self) -> bool {
+        self.destroyed
+    }
+
+    // Systems that have lost boxes, with how many are left of how many.
+    pub fn damaged(&self) -> Vec<(System, u8, u8)> {
+        ALL_SYSTEMS
+            .iter()
+            .filter(|s| self.remaining(**s) < self.total(**s))
+            .map(|s| (*s, self.remaining(*s), self.total(*s)))
+            .collect()
+    }
+
+    // Systems with every box gone.
+    pub fn knocked_out(&self) -> Vec<System> {
+        self.damaged()
+            .into_iter()
+            .filter(|(_, remaining, _)| *remaining == 0)
+            .map(|(system, _, _)| system)
+            .collect()
+    }
+
+    // Resolves one volley's internal damage on the chart, one 2d6 roll per hit.
+    pub fn allocate(&mut self, hits: u8, dice: &mut dyn Dice) -> Vec<DamageAllocation> {
+        let mut once_per_volley: Vec<(usize, usize)> = Vec::new();
+        let mut allocations = Vec::new();
+        for _ in 0..hits {
+            if self.destroyed {
+                break;
+            }
+            let roll = dice.two_d6();
+            let row = roll as usize - 2;
+            let column = CHART[row].iter().enumerate().position(|(column, entry)| {
+                let Entry(system, once) = entry;
+                self.remaining(*system) > 0 && !(*once && once_per_volley.contains(&(row, column)))
+            });
+            let system = match column {
+                Some(column) => {
+                    let Entry(system, once) = &CHART[row][column];
+                    if *once {
+                        once_per_volley.push((row, column));
+                    }
+                    self.remaining[*system as usize] -= 1;
+                    Some(*system)
+                }
+                None => {
+                    // Out of excess damage boxes.
+                    self.destroyed = true;
+                    None
+                }
+            };
+            allocations.push(DamageAllocation { roll, system });
+        }
+        allocations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dice::LoadedDice;
+
+    fn systems() -> Systems {
+        let mut total = [0; SYSTEM_COUNT];
+        total[Bridge as usize] = 2;
+        total[ForwardHull as usize] = 2;
+        total[AftHull as usize] = 1;
+        total[LeftWarp as usize] = 15;
+        total[RightWarp as usize] = 15;
+        total[Impulse as usize] = 4;
+        total[Battery as usize] = 4;
+        total[ExcessDamage as usize] = 2;
+        Systems {
+            total,
+            remaining: total,
+            destroyed: false,
+        }
+    }
+
+    #[test]
+    fn hits_follow_the_row() {
+        let mut s = systems();
+        // Three 7s: the forward hull until it's gone, then the aft hull.
+        let mut dice = LoadedDice::new(vec![3, 4, 4, 3, 1, 6]);
+        let hits = s.allocate(3, &mut dice);
+        let systems: Vec<Option<System>> = hits.iter().map(|h| h.system).collect();
+        assert_eq!(systems, vec![Some(ForwardHull), Some(ForwardHull), Some(AftHull)]);
+        assert_eq!(s.remaining(ForwardHull), 0);
+        assert_eq!(s.remaining(AftHull), 0);
+        assert_eq!(s.knocked_out(), vec![ForwardHull, AftHull]);
+    }
+
+    #[test]
+    fn missing_systems_are_skipped() {
+        let mut s = systems();
+        // Row 3 starts with drone, phaser; this ship has neither.
+        let mut dice = LoadedDice::new(vec![1, 2]);
+        assert_eq!(s.allocate(1, &mut dice)[0].system, Some(Impulse));
+    }
+
+    #[test]
+    fn once_per_volley() {
+        let mut s = systems();
+        // Two 2s in one volley: the bridge, then on past it to the aft hull.
+        let mut dice = LoadedDice::new(vec![1, 1, 1, 1, 1, 1]);
+        let hits = s.allocate(2, &mut dice);
+        assert_eq!(hits[0].system, Some(Bridge));
+        assert_eq!(hits[1].system, Some(AftHull));
+        // A new volley can hit the bridge again.
+        assert_eq!(s.allocate(1, &mut dice)[0].system, Some(Bridge));
+        assert_eq!(s.remaining(Bridge), 0);
+    }
+
+    #[test]
+    fn destroyed_when_excess_damage_runs_out() {
+        let mut s = systems();
+        let mut dice = LoadedDice::new([3, 4].repeat(40));
+        let hits = s.allocate(40, &mut dice);
+        assert!(s.is_destroyed());
+        assert_eq!(hits.last().unwrap().system, None);
+        assert_eq!(s.remaining(ExcessDamage), 0);
+        assert_eq!(s.remaining(LeftWarp), 0);
+    }
+}
@@ -0,0 +1,88 @@
+// Dice for everything the rules leave to chance. Anything that rolls takes a
+// `Dice` so tests and replays can supply exact results.
+
+use serde::{Deserialize, Serialize};
+
+pub trait Dice {
+    // A single six-sided die: 1 through 6.
+    fn d6(&mut self) -> u8;
+
+    fn two_d6(&mut self) -> u8 {
+        self.d6() + self.d6()
+    }
+}
+
+// A small deterministic generator (splitmix64). The same seed always produces
+// the same rolls.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SeededDice {
+    state: u64,
+}
+
+impl SeededDice {
+    pub fn new(seed: u64) -> SeededDice {
+        SeededDice { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+impl Dice for SeededDice {
+    fn d6(&mut self) -> u8 {
+        (self.next_u64() % 6) as u8 + 1
+    }
+}
+
+// Replays a fixed list of rolls, for tests. Panics if it runs out.
+#[cfg(test)]
+pub struct LoadedDice {
+    rolls: std::vec::IntoIter<u8>,
+}
+
+#[cfg(test)]
+impl LoadedDice {
+    pub fn new(rolls: Vec<u8>) -> LoadedDice {
+        LoadedDice {
+            rolls: rolls.into_iter(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Dice for LoadedDice {
+    fn d6(&mut self) -> u8 {
+        self.rolls.next().expect("ran out of loaded dice")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_dice_repeat() {
+        let mut a = SeededDice::new(42);
+        let mut b = SeededDice::new(42);
+        for _ in 0..100 {
+            let roll = a.d6();
+            assert!((1..=6).contains(&roll));
+            assert_eq!(roll, b.d6());
+        }
+    }
+
+    #[test]
+    fn seeded_dice_cover_all_faces() {
+        let mut dice = SeededDice::new(7);
+        let mut seen = [false; 6];
+        for _ in 0..200 {
+            seen[dice.d6() as usize - 1] = true;
+        }
+        assert!(seen.iter().all(|s| *s));
+    }
+}
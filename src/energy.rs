@@ -0,0 +1,445 @@
+// The energy allocation phase: where each ship's power goes for the turn.
+
+use crate::damage::{System, Systems};
+use crate::ship_spec::ShipSpec;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// General shield reinforcement costs 2 energy per point.
+pub const GENERAL_REINFORCEMENT_COST: u8 = 2;
+
+// Power the ship can spend this turn.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct PowerBudget {
+    pub warp: u8,
+    pub impulse: u8,
+    pub reactor: u8,
+    // Charge held in the batteries, which can be discharged to cover a shortfall.
+    pub battery: u8,
+}
+
+impl PowerBudget {
+    // What the ship's surviving engines produce, plus the battery charge.
+    pub fn from_systems(systems: &Systems, battery: u8) -> PowerBudget {
+        PowerBudget {
+            warp: systems.remaining(System::LeftWarp)
+                + systems.remaining(System::CenterWarp)
+                + systems.remaining(System::RightWarp),
+            impulse: systems.remaining(System::Impulse),
+            reactor: systems.remaining(System::Reactor),
+            battery,
+        }
+    }
+
+    // Everything the engines produce this turn, not counting the batteries.
+    pub fn generated(&self) -> u16 {
+        self.warp as u16 + self.impulse as u16 + self.reactor as u16
+    }
+
+    pub fn total(&self) -> u16 {
+        self.generated() + self.battery as u16
+    }
+}
+
+// Energy paid toward one heavy weapon's arming this turn.
+#[derive(Clone, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct WeaponEnergy {
+    // Index of the weapon mount on the ship.
+    pub mount: usize,
+    pub label: String,
+    pub energy: u8,
+    // What keeps the weapon arming or held, and the most it can take.
+    pub required: u8,
+    pub max: u8,
+    // None unless a proximity fuse can be set this turn.
+    pub proximity: Option<bool>,
+}
+
+#[derive(Clone, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct EnergyAllocation {
+    pub available: PowerBudget,
+    pub battery_capacity: u8,
+
+    pub life_support: u8,
+    pub fire_control: u8,
+    // General reinforcement in points; each point costs GENERAL_REINFORCEMENT_COST.
+    pub general_reinforcement: u8,
+    // Specific reinforcement for shields 1 through 6, one energy per point.
+    pub specific_reinforcement: [u8; 6],
+    // Energy put into the phaser capacitor, and its charge and size before that.
+    pub phaser_capacitor: u8,
+    pub capacitor_charge: u8,
+    pub capacitor_capacity: u8,
+    pub heavy_weapons: Vec<WeaponEnergy>,
+    pub speed: u8,
+    pub movement_cost: u8,
+    pub battery_charge: u8,
+    pub high_energy_turns: u8,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum AllocationError {
+    // More energy allocated than the ship has, with the energy on each line.
+    OverAllocated {
+        available: u16,
+        allocated: u16,
+        lines: Vec<(&'static str, u16)>,
+    },
+    // More battery charging than the batteries have room for.
+    BatteryOvercharged {
+        room: u8,
+        requested: u8,
+    },
+    // More energy for the phaser capacitor than it has room for.
+    CapacitorOvercharged {
+        room: u8,
+        requested: u8,
+    },
+    // More energy for a heavy weapon than it can take this turn.
+    WeaponOvercharged {
+        weapon: String,
+        max: u8,
+        requested: u8,
+    },
+    // The ship has already committed its allocation for this turn.
+    AlreadyAllocated,
+    // A limit on the form doesn't match the ship it was submitted for.
+    LimitMismatch(&'static str),
+    // Energy for a mount that isn't a working heavy weapon.
+    NotArmable(usize),
+    // The same mount listed more than once.
+    DuplicateWeapon(usize),
+}
+
+impl fmt::Display for AllocationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AllocationError::OverAllocated {
+                available,
+                allocated,
+                lines,
+            } => {
+                writeln!(f, "Allocated {} energy but only {} is available:", allocated, available)?;
+                for (name, energy) in lines.iter().filter(|(_, energy)| *energy > 0) {
+                    writeln!(f, "  {}: {}", name, energy)?;
+                }
+                Ok(())
+            }
+            AllocationError::BatteryOvercharged { room, requested } => write!(
+                f,
+                "Can't charge {} into the batteries; they only have room for {}",
+                requested, room
+            ),
+            AllocationError::CapacitorOvercharged { room, requested } => write!(
+                f,
+                "Can't put {} into the phaser capacitor; it only has room for {}",
+                requested, room
+            ),
+            AllocationError::WeaponOvercharged { weapon, max, requested } => write!(
+                f,
+                "{} can take at most {} energy this turn, not {}",
+                weapon, max, requested
+            ),
+            AllocationError::AlreadyAllocated => write!(f, "Energy is already allocated for this turn"),
+            AllocationError::LimitMismatch(limit) => {
+                write!(f, "The allocation's {} doesn't match the ship", limit)
+            }
+            AllocationError::NotArmable(mount) => write!(f, "Weapon {} can't be armed", mount),
+            AllocationError::DuplicateWeapon(mount) => write!(f, "Weapon {} is listed twice", mount),
+        }
+    }
+}
+
+impl EnergyAllocation {
+    // A starting allocation for the turn: life support and fire control paid
+    // for, the ship holding its current speed, and nothing else.
+    pub fn new(spec: &ShipSpec, systems: &Systems, battery: u8, speed: u8) -> EnergyAllocation {
+        EnergyAllocation {
+            available: PowerBudget::from_systems(systems, battery),
+            battery_capacity: systems.remaining(System::Battery),
+            life_support: spec.ship.life_support,
+            fire_control: spec.ship.fire_control,
+            speed,
+            movement_cost: spec.movement.movement_cost,
+            ..Default::default()
+        }
+    }
+
+    pub fn movement(&self) -> u16 {
+        self.speed as u16 * self.movement_cost as u16
+    }
+
+    // The energy spent on each line of the form.
+    pub fn lines(&self) -> Vec<(&'static str, u16)> {
+        vec![
+            ("Life support", self.life_support as u16),
+            ("Fire control", self.fire_control as u16),
+            (
+                "General reinforcement",
+                self.general_reinforcement as u16 * GENERAL_REINFORCEMENT_COST as u16,
+            ),
+            (
+                "Specific reinforcement",
+                self.specific_reinforcement.iter().map(|e| *e as u16).sum(),
+            ),
+            ("Phaser capacitor", self.phaser_capacitor as u16),
+            (
+                "Heavy weapons",
+                self.heavy_weapons.iter().map(|w| w.energy as u16).sum(),
+            ),
+            ("Movement", self.movement()),
+            ("Battery charging", self.battery_charge as u16),
+            ("High energy turns", self.high_energy_turns as u16),
+        ]
+    }
+
+    pub fn allocated(&self) -> u16 {
+        self.lines().iter().map(|(_, energy)| energy).sum()
+    }
+
+    // Energy still unspent; negative when over-allocated.
+    pub fn remaining(&self) -> i32 {
+        self.available.total() as i32 - self.allocated() as i32
+    }
+
+    // Battery power discharged to cover what the engines don't.
+    pub fn battery_used(&self) -> u8 {
+        self.allocated().saturating_sub(self.available.generated()) as u8
+    }
+
+    // Battery charge left at the end of the allocation phase.
+    pub fn battery_after(&self) -> u8 {
+        self.available.battery.saturating_sub(self.battery_used()) + self.battery_charge
+    }
+
+    // The capacitor's charge once this allocation is paid.
+    pub fn capacitor_after(&self) -> u8 {
+        self.capacitor_charge + self.phaser_capacitor
+    }
+
+    // Checks that the limits on this form are the ones in the budget, so only
+    // the spending is taken from whoever filled it in.
+    pub fn check_limits(&self, budget: &EnergyAllocation) -> Result<(), AllocationError> {
+        let mismatch = if self.available != budget.available {
+            Some("available power")
+        } else if self.battery_capacity != budget.battery_capacity {
+            Some("battery capacity")
+        } else if (self.capacitor_charge, self.capacitor_capacity)
+            != (budget.capacitor_charge, budget.capacitor_capacity)
+        {
+            Some("phaser capacitor")
+        } else if self.movement_cost != budget.movement_cost {
+            Some("movement cost")
+        } else if !self.heavy_weapons.iter().all(|w| {
+            budget.heavy_weapons.iter().any(|b| {
+                b.mount == w.mount
+                    && b.required == w.required
+                    && b.max == w.max
+                    && b.proximity.is_some() == w.proximity.is_some()
+            })
+        }) {
+            Some("heavy weapons")
+        } else {
+            None
+        };
+        match mismatch {
+            Some(limit) => Err(AllocationError::LimitMismatch(limit)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), AllocationError> {
+        let allocated = self.allocated();
+        let available = self.available.total();
+        if allocated > available {
+            return Err(AllocationError::OverAllocated {
+                available,
+                allocated,
+                lines: self.lines(),
+            });
+        }
+        let capacitor_room = self.capacitor_capacity.saturating_sub(self.capacitor_charge);
+        if self.phaser_capacitor > capacitor_room {
+            return Err(AllocationError::CapacitorOvercharged {
+                room: capacitor_room,
+                requested: self.phaser_capacitor,
+            });
+        }
+        if let Some(w) = self.heavy_weapons.iter().find(|w| w.energy > w.max) {
+            return Err(AllocationError::WeaponOvercharged {
+                weapon: w.label.clone(),
+                max: w.max,
+                requested: w.energy,
+            });
+        }
+        // Charging can refill what's discharged this turn, but no more.
+        let room = self
+            .available
+            .battery
+            .checked_sub(self.battery_used())
+            .and_then(|left| self.battery_capacity.checked_sub(left))
+            .ok_or(AllocationError::LimitMismatch("battery capacity"))?;
+        if self.battery_charge > room {
+            return Err(AllocationError::BatteryOvercharged {
+                room,
+                requested: self.battery_charge,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allocation() -> EnergyAllocation {
+        EnergyAllocation {
+            available: PowerBudget {
+                warp: 30,
+                impulse: 4,
+                reactor: 0,
+                battery: 4,
+            },
+            battery_capacity: 4,
+            life_support: 1,
+            fire_control: 1,
+            movement_cost: 1,
+            capacitor_capacity: 6,
+            ..Default::default()
+        }
+    }
+
+    fn torpedo(energy: u8) -> WeaponEnergy {
+        WeaponEnergy {
+            label: String::from("Photon Torpedo A"),
+            energy,
+            required: 2,
+            max: 6,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn totals() {
+        let mut e = allocation();
+        e.speed = 20;
+        e.general_reinforcement = 2;
+        e.specific_reinforcement[0] = 3;
+        e.phaser_capacitor = 4;
+        assert_eq!(e.allocated(), 1 + 1 + 4 + 3 + 4 + 20);
+        assert_eq!(e.remaining(), 38 - 33);
+        assert_eq!(e.battery_used(), 0);
+        assert_eq!(e.validate(), Ok(()));
+    }
+
+    #[test]
+    fn battery_covers_shortfall() {
+        let mut e = allocation();
+        e.speed = 30;
+        e.heavy_weapons = vec![torpedo(2), torpedo(3)];
+        assert_eq!(e.battery_used(), 3);
+        assert_eq!(e.battery_after(), 1);
+        assert_eq!(e.validate(), Ok(()));
+    }
+
+    #[test]
+    fn over_allocation_is_itemised() {
+        let mut e = allocation();
+        e.speed = 31;
+        e.heavy_weapons = vec![torpedo(6), torpedo(2)];
+        match e.validate() {
+            Err(AllocationError::OverAllocated {
+                available,
+                allocated,
+                lines,
+            }) => {
+                assert_eq!(available, 38);
+                assert_eq!(allocated, 41);
+                assert!(lines.contains(&("Movement", 31)));
+                assert!(lines.contains(&("Heavy weapons", 8)));
+            }
+            other => panic!("expected over-allocation, got {:?}", other),
+        }
+        assert!(e.validate().unwrap_err().to_string().contains("Movement: 31"));
+    }
+
+    #[test]
+    fn battery_charging_limited_to_capacity() {
+        let mut e = allocation();
+        e.battery_charge = 1;
+        assert_eq!(
+            e.validate(),
+            Err(AllocationError::BatteryOvercharged { room: 0, requested: 1 })
+        );
+
+        e.available.battery = 1;
+        e.battery_charge = 3;
+        assert_eq!(e.validate(), Ok(()));
+        assert_eq!(e.battery_after(), 4);
+
+        // A charge bigger than the batteries is refused rather than overflowing.
+        e.available.battery = 6;
+        e.battery_charge = 0;
+        assert_eq!(e.validate(), Err(AllocationError::LimitMismatch("battery capacity")));
+    }
+
+    #[test]
+    fn weapon_energy_limited() {
+        let mut e = allocation();
+        e.heavy_weapons = vec![torpedo(7)];
+        assert_eq!(
+            e.validate(),
+            Err(AllocationError::WeaponOvercharged {
+                weapon: String::from("Photon Torpedo A"),
+                max: 6,
+                requested: 7
+            })
+        );
+    }
+
+    #[test]
+    fn capacitor_limited_to_room() {
+        let mut e = allocation();
+        e.capacitor_charge = 4;
+        e.phaser_capacitor = 2;
+        assert_eq!(e.validate(), Ok(()));
+        assert_eq!(e.capacitor_after(), 6);
+
+        e.phaser_capacitor = 3;
+        assert_eq!(
+            e.validate(),
+            Err(AllocationError::CapacitorOvercharged { room: 2, requested: 3 })
+        );
+    }
+
+    #[test]
+    fn limits_come_from_the_budget() {
+        let mut budget = allocation();
+        budget.heavy_weapons = vec![torpedo(0)];
+        let mut e = budget.clone();
+        e.speed = 10;
+        e.heavy_weapons[0].energy = 2;
+        assert_eq!(e.check_limits(&budget), Ok(()));
+
+        e.available.warp = 40;
+        assert_eq!(
+            e.check_limits(&budget),
+            Err(AllocationError::LimitMismatch("available power"))
+        );
+
+        let mut e = budget.clone();
+        e.heavy_weapons[0].max = 8;
+        assert_eq!(
+            e.check_limits(&budget),
+            Err(AllocationError::LimitMismatch("heavy weapons"))
+        );
+
+        let mut e = budget.clone();
+        e.movement_cost = 0;
+        assert_eq!(
+            e.check_limits(&budget),
+            Err(AllocationError::LimitMismatch("movement cost"))
+        );
+    }
+}
@@ -0,0 +1,846 @@
+// A game in progress: the ships and seeking weapons on the map, the turn
+// sequence and the dice. Everything that changes the state of the game goes
+// through here, so it plays the same with or without a window.
+
+use crate::combat::{self, Combatant, FireError, FireReport, Target};
+use crate::dice::SeededDice;
+use crate::energy::{AllocationError, EnergyAllocation};
+use crate::hex::{Facing, Hex, Map, Shift};
+use crate::impulse::{Sequencer, IMPULSES_PER_TURN};
+use crate::movement::{HighEnergyTurn, MovementError, Side};
+use crate::seeking::{LaunchError, SeekerHit, SeekerMove, SeekingKind, SeekingWeapon};
+use crate::ship::{Position, Ship};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Something that happened while running an impulse.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Event {
+    Moved { ship: usize, to: Position },
+    // The ship's next hex is off the map.
+    BlockedByMapEdge { ship: usize, from: Position },
+    Fired(FireReport),
+    HighEnergyTurn { ship: usize, turn: HighEnergyTurn },
+    SeekerHit(SeekerHit),
+    // Burned out or left the map.
+    SeekerGone(String),
+    // Every unit on a floating map moved together to stay on the board.
+    MapShifted(Shift),
+    TurnEnded(u16),
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::Moved { ship, to } => write!(f, "Ship {} moves to {} facing {:?}", ship, to.hex, to.facing),
+            Event::BlockedByMapEdge { ship, from } => {
+                write!(f, "Ship {} can't move off the map from {}", ship, from.hex)
+            }
+            Event::Fired(report) => write!(f, "{}", report),
+            Event::HighEnergyTurn { ship, turn } if turn.broke_down => {
+                write!(f, "Breakdown! Ship {} spun to facing {:?}", ship, turn.facing)
+            }
+            Event::HighEnergyTurn { ship, turn } => {
+                write!(f, "Ship {} made a high energy turn to facing {:?}", ship, turn.facing)
+            }
+            Event::SeekerHit(hit) => write!(f, "{}", hit),
+            Event::SeekerGone(name) => write!(f, "{} is gone", name),
+            Event::MapShifted(shift) => write!(f, "Everything shifts {} to stay on the map", shift),
+            Event::TurnEnded(turn) => write!(f, "Turn {} is over", turn),
+        }
+    }
+}
+
+// Something a ship's controller tells it to do between impulses.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Order {
+    Allocate(EnergyAllocation),
+    Turn(Side),
+    Sideslip(Side),
+    HighEnergyTurn(Facing),
+    // Fire every ready weapon that bears on the target ship.
+    Fire { target: usize },
+    FireAtSeeker,
+    Launch { kind: SeekingKind, target: usize },
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum OrderError {
+    NoSuchShip(usize),
+    // Energy is only allocated between turns.
+    MidTurn,
+    // Ships don't fire on their own side.
+    NotAnEnemy(usize),
+    Allocation(AllocationError),
+    Movement(MovementError),
+    Fire(FireError),
+    Launch(LaunchError),
+}
+
+impl fmt::Display for OrderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderError::NoSuchShip(i) => write!(f, "No ship {}", i),
+            OrderError::MidTurn => write!(f, "Energy can only be allocated between turns"),
+            OrderError::NotAnEnemy(i) => write!(f, "Ship {} isn't an enemy", i),
+            OrderError::Allocation(e) => write!(f, "{}", e),
+            OrderError::Movement(e) => write!(f, "{}", e),
+            OrderError::Fire(e) => write!(f, "{}", e),
+            OrderError::Launch(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(tag = "condition", rename_all = "kebab-case")]
+pub enum Victory {
+    // The last side with a ship left wins.
+    DestroyEnemy,
+    // The side wins if it still has a ship when the turn limit runs out.
+    Survive { side: String },
+}
+
+// How a game is played out, apart from the ships on the map.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Rules {
+    pub turn_limit: Option<u16>,
+    pub map: Map,
+    pub victory: Vec<Victory>,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            turn_limit: None,
+            map: Map::default(),
+            victory: vec![Victory::DestroyEnemy],
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub enum Outcome {
+    // The name of the winning side.
+    Won(String),
+    Draw,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Game {
+    pub ships: Vec<Ship>,
+    pub seekers: Vec<SeekingWeapon>,
+    pub rules: Rules,
+    // The sides' names, and the side each ship fights for.
+    pub sides: Vec<String>,
+    ship_sides: Vec<usize>,
+    sequencer: Sequencer,
+    dice: SeededDice,
+    seekers_launched: u16,
+}
+
+impl Game {
+    // A free-for-all, every ship on its own side, under the default rules.
+    pub fn new(ships: Vec<Ship>, seed: u64) -> Game {
+        let sides = ships.iter().map(|s| s.spec().ship.name.clone()).collect();
+        let ship_sides = (0..ships.len()).collect();
+        Game {
+            ships,
+            seekers: Vec::new(),
+            rules: Rules::default(),
+            sides,
+            ship_sides,
+            sequencer: Sequencer::new(),
+            dice: SeededDice::new(seed),
+            seekers_launched: 0,
+        }
+    }
+
+    // Ships listed by side. Ships are numbered in the order given.
+    pub fn with_sides(sides: Vec<(String, Vec<Ship>)>, rules: Rules, seed: u64) -> Game {
+        let mut game = Game::new(Vec::new(), seed);
+        game.rules = rules;
+        for (side, (name, ships)) in sides.into_iter().enumerate() {
+            game.sides.push(name);
+            game.ship_sides.extend(ships.iter().map(|_| side));
+            game.ships.extend(ships);
+        }
+        game
+    }
+
+    pub fn side_of(&self, ship: usize) -> usize {
+        self.ship_sides[ship]
+    }
+
+    pub fn are_enemies(&self, a: usize, b: usize) -> bool {
+        self.side_of(a) != self.side_of(b)
+    }
+
+    // The side's ships that are still in the fight.
+    pub fn ships_of(&self, side: usize) -> Vec<usize> {
+        (0..self.ships.len())
+            .filter(|i| self.side_of(*i) == side && !self.ships[*i].is_destroyed())
+            .collect()
+    }
+
+    // The hex of the first ship or seeker that's somewhere the map doesn't
+    // allow, if any.
+    pub fn off_map(&self) -> Option<Hex> {
+        let ships = self.ships.iter().map(|s| s.position.hex);
+        let seekers = self.seekers.iter().map(|s| s.position.hex);
+        ships.chain(seekers).find(|hex| !self.rules.map.contains(hex))
+    }
+
+    // Sides with a ship still in the fight.
+    pub fn sides_standing(&self) -> Vec<usize> {
+        (0..self.sides.len())
+            .filter(|side| !self.ships_of(*side).is_empty())
+            .collect()
+    }
+
+    // The last turn has been played out.
+    pub fn out_of_turns(&self) -> bool {
+        match self.rules.turn_limit {
+            Some(limit) => self.turn() > limit || (self.turn() == limit && self.impulse() == IMPULSES_PER_TURN),
+            None => false,
+        }
+    }
+
+    // How the game came out, or None while it's still going. Victory
+    // conditions are checked in the order given.
+    pub fn outcome(&self) -> Option<Outcome> {
+        let standing = self.sides_standing();
+        for victory in &self.rules.victory {
+            match victory {
+                Victory::DestroyEnemy if standing.len() == 1 => {
+                    return Some(Outcome::Won(self.sides[standing[0]].clone()));
+                }
+                Victory::Survive { side }
+                    if self.out_of_turns() && standing.iter().any(|s| self.sides[*s] == *side) =>
+                {
+                    return Some(Outcome::Won(side.clone()));
+                }
+                _ => (),
+            }
+        }
+        if standing.len() <= 1 || self.out_of_turns() {
+            Some(Outcome::Draw)
+        } else {
+            None
+        }
+    }
+
+    pub fn turn(&self) -> u16 {
+        self.sequencer.turn()
+    }
+
+    pub fn impulse(&self) -> u8 {
+        self.sequencer.impulse()
+    }
+
+    // Whether the last turn is over and the next hasn't started: the time for
+    // energy allocation.
+    pub fn between_turns(&self) -> bool {
+        self.impulse() == 0 || self.impulse() == IMPULSES_PER_TURN
+    }
+
+    // The turn and impulse that run_impulse will run next.
+    pub fn next_impulse(&self) -> (u16, u8) {
+        if self.impulse() == IMPULSES_PER_TURN {
+            (self.turn() + 1, 1)
+        } else {
+            (self.turn(), self.impulse() + 1)
+        }
+    }
+
+    // Carries out an order for one ship.
+    pub fn order(&mut self, ship: usize, order: &Order) -> Result<Option<Event>, OrderError> {
+        if ship >= self.ships.len() {
+            return Err(OrderError::NoSuchShip(ship));
+        }
+        match order {
+            Order::Allocate(_) if !self.between_turns() => return Err(OrderError::MidTurn),
+            Order::Allocate(e) => self.ships[ship]
+                .set_energy_allocation(e.clone())
+                .map_err(OrderError::Allocation)?,
+            Order::Turn(side) => self.turn_ship(ship, *side).map_err(OrderError::Movement)?,
+            Order::Sideslip(side) => self.ships[ship]
+                .sideslip(*side, &self.rules.map)
+                .map_err(OrderError::Movement)?,
+            Order::HighEnergyTurn(facing) => {
+                let turn = self.high_energy_turn(ship, *facing).map_err(OrderError::Movement)?;
+                return Ok(Some(Event::HighEnergyTurn { ship, turn }));
+            }
+            Order::Fire { target } => {
+                if *target >= self.ships.len() {
+                    return Err(OrderError::NoSuchShip(*target));
+                }
+                if !self.are_enemies(ship, *target) {
+                    return Err(OrderError::NotAnEnemy(*target));
+                }
+                let report = self.fire(ship, *target).map_err(OrderError::Fire)?;
+                return Ok(Some(Event::Fired(report)));
+            }
+            Order::FireAtSeeker => {
+                let report = self.fire_at_seeker(ship).map_err(OrderError::Fire)?;
+                return Ok(Some(Event::Fired(report)));
+            }
+            Order::Launch { kind, target } => {
+                if *target >= self.ships.len() {
+                    return Err(OrderError::NoSuchShip(*target));
+                }
+                self.launch(*kind, ship, *target).map_err(OrderError::Launch)?;
+            }
+        }
+        Ok(None)
+    }
+
+    // A copy to show the given side's player in a network game. The dice
+    // stay behind, so nothing in it tells how future rolls will come up, and
+    // so do the other side's energy allocations.
+    pub fn concealed(&self, side: usize) -> Game {
+        let mut game = Game {
+            dice: SeededDice::new(0),
+            ..self.clone()
+        };
+        for ship in 0..game.ships.len() {
+            if game.side_of(ship) != side {
+                game.ships[ship].conceal_allocation();
+            }
+        }
+        game
+    }
+
+    // Runs the next impulse: ships move on the impulse chart, then seeking
+    // weapons chase wherever their targets ended up. The last impulse of a
+    // turn closes the turn out for every ship.
+    pub fn run_impulse(&mut self) -> Vec<Event> {
+        let mut events = Vec::new();
+        let ships: Vec<(Position, u8)> = self.ships.iter().map(|s| (s.position, s.speed)).collect();
+        let impulse = self.sequencer.run_impulse(&ships, &self.rules.map);
+        for m in impulse.moves {
+            match m.to {
+                Some(to) => {
+                    self.ships[m.ship].advance(to);
+                    events.push(Event::Moved { ship: m.ship, to });
+                }
+                None => events.push(Event::BlockedByMapEdge {
+                    ship: m.ship,
+                    from: m.from,
+                }),
+            }
+        }
+        self.move_seekers(impulse.impulse, &mut events);
+        if let Some(shift) = self.recenter() {
+            events.push(Event::MapShifted(shift));
+        }
+        if impulse.impulse == IMPULSES_PER_TURN {
+            for ship in self.ships.iter_mut() {
+                ship.end_turn();
+            }
+            events.push(Event::TurnEnded(impulse.turn));
+        }
+        events
+    }
+
+    // Keeps the action on a floating map's board by moving every unit back
+    // toward the middle once one nears an edge.
+    fn recenter(&mut self) -> Option<Shift> {
+        let hexes: Vec<Hex> = self
+            .ships
+            .iter()
+            .map(|s| s.position.hex)
+            .chain(self.seekers.iter().map(|s| s.position.hex))
+            .collect();
+        let shift = self.rules.map.recenter(&hexes)?;
+        for ship in self.ships.iter_mut() {
+            ship.position.hex = ship.position.hex.shifted(shift);
+        }
+        for seeker in self.seekers.iter_mut() {
+            seeker.shift(shift);
+        }
+        Some(shift)
+    }
+
+    fn move_seekers(&mut self, impulse: u8, events: &mut Vec<Event>) {
+        let mut i = 0;
+        while i < self.seekers.len() {
+            let target = &mut self.ships[self.seekers[i].target];
+            match self.seekers[i].on_impulse(impulse, &target.position.hex, &self.rules.map) {
+                SeekerMove::Waiting | SeekerMove::Moved(_) => {
+                    i += 1;
+                    continue;
+                }
+                SeekerMove::Hit => {
+                    let hit = self.seekers[i].strike(target, &mut self.dice);
+                    events.push(Event::SeekerHit(hit));
+                }
+                SeekerMove::BurnedOut | SeekerMove::OffMap => {
+                    events.push(Event::SeekerGone(self.seekers[i].name.clone()));
+                }
+            }
+            self.seekers.remove(i);
+        }
+    }
+
+    // Stopped ships pivot with tactical maneuvers; moving ones turn under
+    // their turn mode.
+    pub fn turn_ship(&mut self, ship: usize, side: Side) -> Result<(), MovementError> {
+        let (turn, impulse) = (self.turn(), self.impulse());
+        let ship = &mut self.ships[ship];
+        if ship.speed == 0 {
+            ship.tactical_maneuver(side, turn, impulse)
+        } else {
+            ship.turn(side)
+        }
+    }
+
+    pub fn high_energy_turn(&mut self, ship: usize, facing: Facing) -> Result<HighEnergyTurn, MovementError> {
+        self.ships[ship].high_energy_turn(facing, &mut self.dice)
+    }
+
+    // The attacker's weapon mounts that can fire at the target on this
+    // impulse: ready, bearing and in range, with no more phasers than the
+    // capacitor can power.
+    pub fn weapons_to_fire(&self, attacker: usize, target: usize) -> Vec<usize> {
+        let a = &self.ships[attacker];
+        let hex = self.ships[target].position.hex;
+        let range = a.position.hex.range_to(&hex);
+        let ready = a.weapons_ready(self.turn(), self.impulse());
+        let mut charge = a.capacitor().charge();
+        a.weapons_bearing_on(&hex)
+            .into_iter()
+            .filter(|i| ready.contains(i))
+            .filter(|i| {
+                let mount = &a.weapons()[*i];
+                combat::armed_in_range(mount.weapon.kind, mount.arming, range)
+            })
+            .filter(|i| match a.weapon_kind(*i).phaser_energy() {
+                Some(energy) if energy <= charge => {
+                    charge -= energy;
+                    true
+                }
+                Some(_) => false,
+                None => true,
+            })
+            .collect()
+    }
+
+    // Fires everything that can from one ship at another.
+    pub fn fire(&mut self, attacker: usize, target: usize) -> Result<FireReport, FireError> {
+        if attacker == target {
+            return Err(FireError::SelfTarget);
+        }
+        let (turn, impulse) = (self.turn(), self.impulse());
+        let mounts = self.weapons_to_fire(attacker, target);
+        let (a, t) = if attacker < target {
+            let (left, right) = self.ships.split_at_mut(target);
+            (&mut left[attacker], &mut right[0])
+        } else {
+            let (left, right) = self.ships.split_at_mut(attacker);
+            (&mut right[0], &mut left[target])
+        };
+        combat::fire(a, &mounts, t, turn, impulse, &mut self.dice)
+    }
+
+    // The ship's phasers that can fire at a seeking weapon on this impulse,
+    // with no more than the capacitor can power.
+    fn phasers_for_seeker(&self, attacker: usize, seeker: usize) -> Vec<usize> {
+        let a = &self.ships[attacker];
+        let hex = self.seekers[seeker].position.hex;
+        let range = a.position.hex.range_to(&hex);
+        let ready = a.weapons_ready(self.turn(), self.impulse());
+        let mut charge = a.capacitor().charge();
+        a.weapons_bearing_on(&hex)
+            .into_iter()
+            .filter(|i| ready.contains(i))
+            .filter(|i| {
+                let mount = &a.weapons()[*i];
+                combat::armed_in_range(mount.weapon.kind, mount.arming, range)
+            })
+            .filter(|i| match a.weapon_kind(*i).phaser_energy() {
+                Some(energy) if energy <= charge => {
+                    charge -= energy;
+                    true
+                }
+                _ => false,
+            })
+            .collect()
+    }
+
+    // Fires a ship's phasers one at a time at the nearest enemy seeking
+    // weapon they can reach, stopping once it's destroyed.
+    pub fn fire_at_seeker(&mut self, attacker: usize) -> Result<FireReport, FireError> {
+        let (turn, impulse) = (self.turn(), self.impulse());
+        let from = self.ships[attacker].position.hex;
+        let target = (0..self.seekers.len())
+            .filter(|s| self.are_enemies(attacker, self.seekers[*s].launcher))
+            .filter(|s| !self.phasers_for_seeker(attacker, *s).is_empty())
+            .min_by_key(|s| from.distance_to(&self.seekers[*s].position.hex))
+            .ok_or(FireError::NoTarget)?;
+        let mut report: Option<FireReport> = None;
+        for mount in self.phasers_for_seeker(attacker, target) {
+            let volley = combat::fire(
+                &mut self.ships[attacker],
+                &[mount],
+                &mut self.seekers[target],
+                turn,
+                impulse,
+                &mut self.dice,
+            )?;
+            let destroyed = volley.target_destroyed;
+            match report.as_mut() {
+                Some(r) => {
+                    r.shots.extend(volley.shots);
+                    r.target_destroyed = destroyed;
+                }
+                None => report = Some(volley),
+            }
+            if destroyed {
+                break;
+            }
+        }
+        self.seekers.retain(|s| !s.is_destroyed());
+        report.ok_or(FireError::NoTarget)
+    }
+
+    // Launches a seeking weapon from one of the ship's launchers at an enemy.
+    pub fn launch(&mut self, kind: SeekingKind, from: usize, target: usize) -> Result<(), LaunchError> {
+        if !self.are_enemies(from, target) {
+            return Err(LaunchError::NotAnEnemy(target));
+        }
+        let (turn, impulse) = (self.turn(), self.impulse());
+        let mount = self.ships[from]
+            .launcher_ready(kind, turn, impulse)
+            .ok_or(LaunchError::NoLauncher(kind))?;
+        self.ships[from].launch(mount, turn, impulse);
+        self.seekers_launched += 1;
+        let name = format!("{} {}", kind, self.seekers_launched);
+        let position = self.ships[from].position;
+        self.seekers
+            .push(SeekingWeapon::launch(name, kind, position, from, target));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex::{Hex, MapEdges};
+
+    fn duel() -> Game {
+        let ca = Ship::new(
+            "federation/ca.toml",
+            Position {
+                hex: Hex::new(10, 20),
+                facing: Facing::A,
+            },
+            8,
+        );
+        let d7 = Ship::new(
+            "klingon/d7.toml",
+            Position {
+                hex: Hex::new(10, 5),
+                facing: Facing::D,
+            },
+            0,
+        );
+        Game::new(vec![ca, d7], 7)
+    }
+
+    // The duel with a Kzinti BC, which carries drone racks, in the D7's place.
+    fn drone_duel() -> Game {
+        let mut game = duel();
+        let position = game.ships[1].position;
+        game.ships[1] = Ship::new("kzinti/bc.toml", position, 0);
+        game
+    }
+
+    #[test]
+    fn orders() {
+        let mut game = duel();
+        let e = game.ships[1].get_energy_allocation();
+        assert_eq!(game.order(1, &Order::Allocate(e.clone())), Ok(None));
+        assert_eq!(
+            game.order(1, &Order::Allocate(e)),
+            Err(OrderError::Allocation(AllocationError::AlreadyAllocated))
+        );
+        game.order(0, &Order::Sideslip(Side::Left)).unwrap();
+        assert_eq!(
+            game.order(0, &Order::Sideslip(Side::Left)),
+            Err(OrderError::Movement(MovementError::Sideslip))
+        );
+        assert_eq!(
+            game.order(1, &Order::Fire { target: 1 }),
+            Err(OrderError::NotAnEnemy(1))
+        );
+        assert_eq!(
+            game.order(1, &Order::Fire { target: 2 }),
+            Err(OrderError::NoSuchShip(2))
+        );
+        assert_eq!(game.order(2, &Order::FireAtSeeker), Err(OrderError::NoSuchShip(2)));
+        assert_eq!(
+            game.order(
+                1,
+                &Order::Launch {
+                    kind: SeekingKind::Drone,
+                    target: 0
+                }
+            ),
+            Err(OrderError::Launch(LaunchError::NoLauncher(SeekingKind::Drone)))
+        );
+    }
+
+    #[test]
+    fn allocations_keep_the_ships_limits() {
+        let mut game = duel();
+        let mut e = game.ships[0].get_energy_allocation();
+        e.available.warp += 10;
+        e.speed += 10;
+        assert_eq!(
+            game.order(0, &Order::Allocate(e)),
+            Err(OrderError::Allocation(AllocationError::LimitMismatch(
+                "available power"
+            )))
+        );
+        let mut e = game.ships[0].get_energy_allocation();
+        e.heavy_weapons[0].max += 2;
+        e.heavy_weapons[0].energy = e.heavy_weapons[0].max;
+        assert_eq!(
+            game.order(0, &Order::Allocate(e)),
+            Err(OrderError::Allocation(AllocationError::LimitMismatch("heavy weapons")))
+        );
+        let e = game.ships[0].get_energy_allocation();
+        assert_eq!(game.order(0, &Order::Allocate(e)), Ok(None));
+    }
+
+    #[test]
+    fn high_energy_turns_need_an_allocation() {
+        let mut game = duel();
+        let het_cost = game.ships[0].spec().movement.het_cost;
+        assert_eq!(
+            game.order(0, &Order::HighEnergyTurn(Facing::D)),
+            Err(OrderError::Movement(MovementError::InsufficientEnergy {
+                required: het_cost,
+                available: 0
+            }))
+        );
+        let mut e = game.ships[0].get_energy_allocation();
+        e.speed = 0;
+        e.high_energy_turns = het_cost;
+        assert_eq!(game.order(0, &Order::Allocate(e)), Ok(None));
+        assert!(game.order(0, &Order::HighEnergyTurn(Facing::D)).is_ok());
+    }
+
+    #[test]
+    fn allocations_arm_each_weapon_once() {
+        let mut game = duel();
+        let mut e = game.ships[0].get_energy_allocation();
+        e.heavy_weapons[0].mount = 99;
+        assert_eq!(
+            game.order(0, &Order::Allocate(e)),
+            Err(OrderError::Allocation(AllocationError::NotArmable(99)))
+        );
+        let mut e = game.ships[0].get_energy_allocation();
+        let first = e.heavy_weapons[0].clone();
+        e.heavy_weapons.push(first.clone());
+        assert_eq!(
+            game.order(0, &Order::Allocate(e)),
+            Err(OrderError::Allocation(AllocationError::DuplicateWeapon(first.mount)))
+        );
+        assert_eq!(game.ships[0].get_energy_allocation().heavy_weapons[0], first);
+    }
+
+    #[test]
+    fn launches_need_a_launcher_and_an_enemy() {
+        let mut game = drone_duel();
+        let drone = |target| Order::Launch {
+            kind: SeekingKind::Drone,
+            target,
+        };
+        assert_eq!(
+            game.order(1, &drone(1)),
+            Err(OrderError::Launch(LaunchError::NotAnEnemy(1)))
+        );
+        assert_eq!(game.order(1, &drone(5)), Err(OrderError::NoSuchShip(5)));
+        // Each of the two racks launches once a turn.
+        game.order(1, &drone(0)).unwrap();
+        game.order(1, &drone(0)).unwrap();
+        assert_eq!(
+            game.order(1, &drone(0)),
+            Err(OrderError::Launch(LaunchError::NoLauncher(SeekingKind::Drone)))
+        );
+        assert_eq!(
+            game.order(
+                1,
+                &Order::Launch {
+                    kind: SeekingKind::Plasma,
+                    target: 0
+                }
+            ),
+            Err(OrderError::Launch(LaunchError::NoLauncher(SeekingKind::Plasma)))
+        );
+        assert_eq!(game.seekers.len(), 2);
+        assert_eq!(game.seekers[1].name, "Drone 2");
+    }
+
+    #[test]
+    fn allocation_between_turns_and_fire_at_enemies() {
+        let mut game = duel();
+        game.run_impulse();
+        let e = game.ships[0].get_energy_allocation();
+        assert_eq!(game.order(0, &Order::Allocate(e)), Err(OrderError::MidTurn));
+        game.ship_sides = vec![0, 0];
+        assert_eq!(
+            game.order(0, &Order::Fire { target: 1 }),
+            Err(OrderError::NotAnEnemy(1))
+        );
+    }
+
+    #[test]
+    fn concealed_games_hide_the_dice() {
+        let (a, b) = (Game::new(duel().ships, 1), Game::new(duel().ships, 2));
+        let json = |g: &Game| serde_json::to_string(g).unwrap();
+        assert_ne!(json(&a), json(&b));
+        assert_eq!(json(&a.concealed(0)), json(&b.concealed(0)));
+    }
+
+    #[test]
+    fn runs_a_turn() {
+        let mut game = duel();
+        let mut events = Vec::new();
+        for _ in 0..IMPULSES_PER_TURN {
+            events.extend(game.run_impulse());
+        }
+        let moves = events
+            .iter()
+            .filter(|e| matches!(e, Event::Moved { ship: 0, .. }))
+            .count();
+        assert_eq!(moves, 8);
+        assert_eq!(events.last(), Some(&Event::TurnEnded(1)));
+        assert_eq!(game.ships[0].position.hex, Hex::new(10, 12));
+        assert_eq!(game.ships[1].position.hex, Hex::new(10, 5));
+    }
+
+    #[test]
+    fn floating_map_keeps_units_on_the_board() {
+        // The same game on a fixed map and a floating one, with the D7 right
+        // by the top edge.
+        let start = |edges| {
+            let mut game = drone_duel();
+            game.ships[1].position.hex = Hex::new(10, 1);
+            game.rules.map.edges = edges;
+            game.launch(SeekingKind::Drone, 1, 0).unwrap();
+            game
+        };
+        let mut fixed = start(MapEdges::Fixed);
+        let mut floating = start(MapEdges::Floating);
+        let mut shifts = Vec::new();
+        for _ in 0..IMPULSES_PER_TURN {
+            assert!(!fixed.run_impulse().iter().any(|e| matches!(e, Event::MapShifted(_))));
+            for event in floating.run_impulse() {
+                if let Event::MapShifted(shift) = event {
+                    shifts.push(shift);
+                }
+            }
+            let (a, b) = (&fixed.ships[0].position.hex, &fixed.ships[1].position.hex);
+            let (c, d) = (&floating.ships[0].position.hex, &floating.ships[1].position.hex);
+            assert_eq!(c.distance_to(d), a.distance_to(b));
+            assert_eq!(c.bearing_to(d), a.bearing_to(b));
+            assert_eq!(d.bearing_to(c), b.bearing_to(a));
+            for (x, y) in fixed.seekers.iter().zip(floating.seekers.iter()) {
+                assert_eq!(y.position.hex.distance_to(c), x.position.hex.distance_to(a));
+                assert_eq!(y.position.hex.bearing_to(c), x.position.hex.bearing_to(a));
+            }
+        }
+        assert!(!shifts.is_empty());
+        let total = shifts.iter().fold(Shift { cols: 0, rows: 0 }, |t, s| Shift {
+            cols: t.cols + s.cols,
+            rows: t.rows + s.rows,
+        });
+        assert_eq!(
+            floating.ships[1].position.hex,
+            fixed.ships[1].position.hex.shifted(total)
+        );
+        assert!(floating
+            .ships
+            .iter()
+            .all(|s| floating.rules.map.is_inside(&s.position.hex)));
+        assert_eq!(
+            Event::MapShifted(shifts[0]).to_string(),
+            format!(
+                "Everything shifts {} columns and {} rows to stay on the map",
+                shifts[0].cols, shifts[0].rows
+            )
+        );
+    }
+
+    #[test]
+    fn victory_conditions() {
+        let mut game = duel();
+        game.rules = Rules {
+            turn_limit: Some(1),
+            map: Map::default(),
+            victory: vec![
+                Victory::DestroyEnemy,
+                Victory::Survive {
+                    side: String::from("Klingon D7"),
+                },
+            ],
+        };
+        for _ in 0..IMPULSES_PER_TURN {
+            assert_eq!(game.outcome(), None);
+            game.run_impulse();
+        }
+        assert_eq!(game.outcome(), Some(Outcome::Won(String::from("Klingon D7"))));
+        game.rules.victory = vec![Victory::DestroyEnemy];
+        assert_eq!(game.outcome(), Some(Outcome::Draw));
+    }
+
+    #[test]
+    fn phasers_fire_at_enemy_seekers_until_they_are_gone() {
+        let mut game = drone_duel();
+        let mut e = game.ships[0].get_energy_allocation();
+        e.speed = 0;
+        e.phaser_capacitor = e.capacitor_capacity - e.capacitor_charge;
+        game.order(0, &Order::Allocate(e)).unwrap();
+        game.launch(SeekingKind::Drone, 1, 0).unwrap();
+        game.seekers[0].position.hex = Hex::new(10, 19);
+
+        // Ships only shoot at seekers from the other side.
+        game.ship_sides = vec![0, 0];
+        assert_eq!(game.fire_at_seeker(0), Err(FireError::NoTarget));
+        game.ship_sides = vec![0, 1];
+
+        // A phaser 1 at range 1 is enough for a drone on any roll.
+        let report = game.fire_at_seeker(0).unwrap();
+        assert_eq!(report.shots.len(), 1);
+        assert!(report.target_destroyed);
+        assert!(game.seekers.is_empty());
+        assert_eq!(game.fire_at_seeker(0), Err(FireError::NoTarget));
+    }
+
+    #[test]
+    fn ships_cant_fire_at_themselves() {
+        let mut game = duel();
+        assert_eq!(game.fire(0, 0).unwrap_err(), FireError::SelfTarget);
+    }
+
+    #[test]
+    fn drones_chase_their_target() {
+        let mut game = drone_duel();
+        game.launch(SeekingKind::Drone, 1, 0).unwrap();
+        let mut hit = None;
+        for _ in 0..IMPULSES_PER_TURN {
+            for event in game.run_impulse() {
+                if let Event::SeekerHit(h) = event {
+                    hit = Some(h);
+                }
+            }
+        }
+        let hit = hit.unwrap();
+        assert_eq!(hit.seeker, "Drone 1");
+        assert_eq!(hit.target, "Federation CA");
+        assert_eq!(hit.shield.shield, 0);
+        assert!(game.seekers.is_empty());
+    }
+}
@@ -1,8 +1,9 @@
 // https://www.redblobgames.com/grids/hexagons
 // The game map uses offset coordinates in an "odd-q" layout.
 
-use crate::screen::map::MapState;
-use ggez::nalgebra::Point2;
+use crate::cube::Cube;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
 use std::fmt;
 
 /* The hex facing printed at the bottom left of the map.
@@ -13,7 +14,7 @@ use std::fmt;
  *  E   C
  *    D
  */
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 pub enum Facing {
     A,
     B,
@@ -24,6 +25,19 @@ pub enum Facing {
 }
 
 impl Facing {
+    // 0 is A, counting clockwise; wraps around past F.
+    pub fn from_index(i: u8) -> Facing {
+        use Facing::*;
+        match i % 6 {
+            0 => A,
+            1 => B,
+            2 => C,
+            3 => D,
+            4 => E,
+            _ => F,
+        }
+    }
+
     pub fn to_degrees(&self) -> u16 {
         use Facing::*;
         match self {
@@ -64,7 +78,9 @@ impl Facing {
     }
 }
 
-#[derive(PartialEq, Eq, Debug)]
+// Bearings come in the order they fall clockwise from straight up, with the
+// exact boundaries between the six hexside directions in between.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum BearingTo {
     A,
     AOrB,
@@ -80,80 +96,127 @@ pub enum BearingTo {
     FOrA,
 }
 
-const MIN_COL: i8 = 0;
-const MAX_COL: i8 = 59;
-const MIN_ROW: i8 = 0;
-const MAX_ROW: i8 = 29;
-
-const DIRECTIONS: [[[i8; 2]; 6]; 2] = [
-    [[0, -1], [1, -1], [1, 0], [0, 1], [-1, 0], [-1, -1]],
-    [[0, -1], [1, 0], [1, 1], [0, 1], [-1, 1], [-1, 0]],
-];
+impl BearingTo {
+    fn from_index(i: u8) -> BearingTo {
+        use BearingTo::*;
+        match i % 12 {
+            0 => A,
+            1 => AOrB,
+            2 => B,
+            3 => BOrC,
+            4 => C,
+            5 => COrD,
+            6 => D,
+            7 => DOrE,
+            8 => E,
+            9 => EOrF,
+            10 => F,
+            _ => FOrA,
+        }
+    }
 
-#[derive(Copy, Clone, Eq, Debug)]
-pub struct Hex {
-    pub col: i8,
-    pub row: i8,
+    // The bearing as seen by a unit with the given facing, so that A is
+    // always dead ahead.
+    pub fn relative_to(&self, facing: Facing) -> BearingTo {
+        BearingTo::from_index(*self as u8 + 12 - 2 * facing as u8)
+    }
 
-    _private: (),
-}
+    // The hexside direction, or None when the bearing lies on a boundary.
+    pub fn facing(&self) -> Option<Facing> {
+        let i = *self as u8;
+        if i.is_multiple_of(2) {
+            Some(Facing::from_index(i / 2))
+        } else {
+            None
+        }
+    }
 
-impl PartialEq for Hex {
-    fn eq(&self, other: &Self) -> bool {
-        return self.col == other.col && self.row == other.row;
+    // The directions on either side of a boundary bearing, or the direction
+    // itself twice.
+    pub fn sides(&self) -> (Facing, Facing) {
+        let i = *self as u8;
+        (Facing::from_index(i / 2), Facing::from_index(i.div_ceil(2)))
     }
 }
 
-#[derive(PartialEq, Eq, Debug)]
-struct Cube {
-    x: i8,
-    y: i8,
-    z: i8,
+const DIRECTIONS: [[[i16; 2]; 6]; 2] = [
+    [[0, -1], [1, -1], [1, 0], [0, 1], [-1, 0], [-1, -1]],
+    [[0, -1], [1, 0], [1, 1], [0, 1], [-1, 1], [-1, 0]],
+];
+
+// A hex anywhere, on the map or not. Whether a hex is on the map depends on
+// the map; see Map.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct Hex {
+    pub col: i16,
+    pub row: i16,
 }
 
+// Hexes are labelled with their column and then their row, counting from 1,
+// as four digits: 0629 is column 6, row 29. Beyond the 99th column or row,
+// or before the first, the two are written out in full with a dot between
+// them, as in 104.29.
 impl fmt::Display for Hex {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:04}", self.number())
+        let (col, row) = (self.col + 1, self.row + 1);
+        if (1..=99).contains(&col) && (1..=99).contains(&row) {
+            write!(f, "{:02}{:02}", col, row)
+        } else {
+            write!(f, "{:02}.{:02}", col, row)
+        }
     }
 }
 
 impl Hex {
-    pub fn new(col: i8, row: i8) -> Option<Hex> {
-        if col < MIN_COL || col > MAX_COL || row < MIN_ROW || row > MAX_ROW {
-            return None;
-        }
-        Some(Hex {
-            col,
-            row,
-            _private: (),
-        })
+    pub fn new(col: i16, row: i16) -> Hex {
+        Hex { col, row }
     }
 
+    // Reads a label back; see the Display impl.
     pub fn from_label(label: &str) -> Option<Hex> {
-        let number: i16 = label.parse().unwrap();
-        let mut col = (number / 100) as i8;
-        if col > 0 {
-            col -= 1;
-        }
-        let mut row = (number % 100) as i8;
-        if row > 0 {
-            row -= 1;
-        }
-        Hex::new(col, row)
-    }
-
-    pub fn number(&self) -> i16 {
-        (self.col as i16 + 1) * 100 + (self.row as i16 + 1)
+        let (col, row): (i16, i16) = match label.split_once('.') {
+            Some((col, row)) => (col.parse().ok()?, row.parse().ok()?),
+            None if label.len() == 4 && label.bytes().all(|b| b.is_ascii_digit()) => {
+                (label[..2].parse().ok()?, label[2..].parse().ok()?)
+            }
+            None => return None,
+        };
+        Some(Hex::new(col - 1, row - 1))
     }
 
-    pub fn neighbor(&self, f: Facing) -> Option<Hex> {
+    pub fn neighbor(&self, f: Facing) -> Hex {
         let parity = (self.col & 1) as usize;
         let dir = DIRECTIONS[parity][f as usize];
-        Hex::new((self.col + dir[0]) as i8, (self.row + dir[1]) as i8)
+        Hex::new(self.col + dir[0], self.row + dir[1])
+    }
+
+    pub fn shifted(&self, shift: Shift) -> Hex {
+        Hex::new(self.col + shift.cols, self.row + shift.rows)
     }
 
-    pub fn distance_to(&self, other: &Hex) -> i8 {
-        return Hex::cubic_distance(self.to_cube(), other.to_cube());
+    pub fn distance_to(&self, other: &Hex) -> i32 {
+        Cube::from(*self).distance_to(&Cube::from(*other))
+    }
+
+    // The distance as a weapon range. Anything too far to count is as far
+    // as can be.
+    pub fn range_to(&self, other: &Hex) -> u8 {
+        u8::try_from(self.distance_to(other)).unwrap_or(u8::MAX)
+    }
+
+    // The hexes exactly `radius` away, on the map or not.
+    pub fn ring(&self, radius: u32) -> Vec<Hex> {
+        Cube::from(*self).ring(radius).iter().map(Cube::to_hex).collect()
+    }
+
+    // The hexes within `radius`, nearest first.
+    pub fn spiral(&self, radius: u32) -> Vec<Hex> {
+        Cube::from(*self).spiral(radius).iter().map(Cube::to_hex).collect()
+    }
+
+    // The hexes a line to the other hex passes through, both ends included.
+    pub fn line_to(&self, other: &Hex) -> Vec<Hex> {
+        Cube::from(*self).line_to(&Cube::from(*other)).iter().map(Cube::to_hex).collect()
     }
 
     pub fn bearing_to(&self, other: &Hex) -> BearingTo {
@@ -175,20 +238,12 @@ impl Hex {
         }
     }
 
-    pub fn to_screen(&self, map_state: &MapState) -> Point2<f32> {
-        let x = map_state.hex_edge * 3.0 / 2.0 * self.col as f32 + map_state.start_point.x;
-        let y = map_state.hex_height * (self.row as f32 + 0.5 * (self.col & 1) as f32) + map_state.start_point.y;
-        let p = Point2::new(x, y);
-        // println!("{:?} is at screen coordinate {:?}", self, p);
-        p
-    }
-
     fn angle_to(&self, other: &Hex) -> i16 {
         let self_center = self.center_coords();
         let other_center = other.center_coords();
 
-        let dx = other_center.x - self_center.x;
-        let dy = self_center.y - other_center.y;
+        let dx = other_center.0 - self_center.0;
+        let dy = self_center.1 - other_center.1;
 
         if dx == 0_f32 {
             if dy >= 0_f32 {
@@ -212,268 +267,295 @@ impl Hex {
         }
     }
 
-    fn center_coords(&self) -> Point2<f32> {
+    fn center_coords(&self) -> (f32, f32) {
         let sqrt_3 = 3_f32.sqrt();
 
-        let tx = self.number() / 100;
+        let tx = self.col as i32 + 1;
         let x = 1_f32 / (2_f32 * sqrt_3) + (tx - 1) as f32 * (sqrt_3 / 2_f32);
 
-        let ty = (self.number() % 100) as f32;
-        let y = ty - 0.5 * (tx % 2) as f32;
+        let ty = (self.row + 1) as f32;
+        let y = ty - 0.5 * (tx & 1) as f32;
 
-        Point2::new(x, y)
+        (x, y)
     }
+}
 
-    // adapted from https://web.archive.org/web/20161024224848/http://gdreflections.com/2011/02/hexagonal-grid-math.html
-    pub fn from_screen(point: Point2<f32>, map_state: &MapState) -> Option<Hex> {
-        let translated_point = Point2::new(point.x - map_state.origin.x, point.y - map_state.origin.y);
-        let side = map_state.hex_edge * 3.0 / 2.0;
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MapEdges {
+    // Ships can't leave the map.
+    #[default]
+    Fixed,
+    // The map has no edges; units can go anywhere, and when they come near
+    // an edge they're all moved back toward the middle together.
+    Floating,
+}
 
-        let ci = (translated_point.x / side).floor() as i8;
-        let cx = translated_point.x - side * ci as f32;
+// How far every unit on a floating map is moved at once. Columns only move
+// in pairs, since what's adjacent to a hex depends on whether its column is
+// odd; that way everything keeps its ranges and bearings to everything else.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Shift {
+    pub cols: i16,
+    pub rows: i16,
+}
 
-        let ty = translated_point.y - (ci % 2) as f32 * map_state.hex_height / 2.0;
-        let cj = (ty / map_state.hex_height).floor() as i8;
-        let cy = ty - map_state.hex_height * cj as f32;
+impl fmt::Display for Shift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} columns and {} rows", self.cols, self.rows)
+    }
+}
 
-        if cx > (map_state.hex_edge / 2.0 - map_state.hex_edge * cy / map_state.hex_height).abs() {
-            Hex::new(ci, cj)
-        } else {
-            let minus = if cy < map_state.hex_height / 2.0 { 1 } else { 0 };
-            Hex::new(ci - 1, cj + (ci % 2) - minus)
-        }
+// How close to the edge of a floating map a unit can come before everything
+// is moved back.
+const EDGE_MARGIN: i16 = 3;
+
+// The map a game is played on: how many columns and rows it has, counting
+// from column 0, row 0 at the upper left, and whether its edges stop units.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Map {
+    pub cols: i16,
+    pub rows: i16,
+    pub edges: MapEdges,
+}
+
+// The map that comes with the game.
+pub const STANDARD_MAP: Map = Map {
+    cols: 60,
+    rows: 30,
+    edges: MapEdges::Fixed,
+};
+
+impl Default for Map {
+    fn default() -> Map {
+        STANDARD_MAP
     }
+}
 
-    fn to_cube(&self) -> Cube {
-        let x = self.col;
-        let z = self.row - (self.col - (self.col & 1)) / 2;
-        let y = -x - z;
-        return Cube { x, y, z };
+impl Map {
+    // Whether the hex is inside the map's columns and rows.
+    pub fn is_inside(&self, hex: &Hex) -> bool {
+        (0..self.cols).contains(&hex.col) && (0..self.rows).contains(&hex.row)
     }
 
-    fn cubic_distance(a: Cube, b: Cube) -> i8 {
-        return ((a.x - b.x).abs() + (a.y - b.y).abs() + (a.z - b.z).abs()) / 2;
+    // Whether a unit can be in the hex. Anywhere is on a floating map.
+    pub fn contains(&self, hex: &Hex) -> bool {
+        self.edges == MapEdges::Floating || self.is_inside(hex)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    pub fn hex(&self, col: i16, row: i16) -> Option<Hex> {
+        Some(Hex::new(col, row)).filter(|hex| self.contains(hex))
+    }
 
-    #[test]
-    fn number_bounds() {
-        let h0101 = Hex::new(0, 0);
-        match h0101 {
-            Some(h) => assert_eq!("0101", format!("{}", h)),
-            None => assert!(false),
-        }
+    // The next hex in a direction, or None if that's off the map.
+    pub fn neighbor(&self, hex: &Hex, f: Facing) -> Option<Hex> {
+        Some(hex.neighbor(f)).filter(|hex| self.contains(hex))
+    }
 
-        let h6030 = Hex::new(59, 29);
-        match h6030 {
-            Some(h) => assert_eq!("6030", format!("{}", h)),
-            None => assert!(false),
+    // On a floating map, the shift that centers the given hexes on the map
+    // once any of them comes within a few hexes of its edge. None if they're
+    // clear of the edges, or already as central as they can be.
+    pub fn recenter(&self, hexes: &[Hex]) -> Option<Shift> {
+        if self.edges != MapEdges::Floating || hexes.is_empty() {
+            return None;
         }
-
-        let h_oob = Hex::new(-1, -1);
-        match h_oob {
-            Some(_) => assert!(false),
-            None => assert!(true),
+        let margin_cols = EDGE_MARGIN.min(self.cols / 4);
+        let margin_rows = EDGE_MARGIN.min(self.rows / 4);
+        let near_edge = |hex: &Hex| {
+            hex.col < margin_cols
+                || hex.col >= self.cols - margin_cols
+                || hex.row < margin_rows
+                || hex.row >= self.rows - margin_rows
+        };
+        if !hexes.iter().any(near_edge) {
+            return None;
         }
+        let min_col = hexes.iter().map(|h| h.col).min()?;
+        let max_col = hexes.iter().map(|h| h.col).max()?;
+        let min_row = hexes.iter().map(|h| h.row).min()?;
+        let max_row = hexes.iter().map(|h| h.row).max()?;
+        // Twice the distance from the middle of the hexes to the middle of
+        // the map, so the sums stay whole. Rounding toward zero means hexes
+        // that are already centered stay put.
+        let cols = self.cols as i32 - 1 - min_col as i32 - max_col as i32;
+        let rows = self.rows as i32 - 1 - min_row as i32 - max_row as i32;
+        let shift = Shift {
+            cols: (cols / 4 * 2) as i16,
+            rows: (rows / 2) as i16,
+        };
+        Some(shift).filter(|s| s.cols != 0 || s.rows != 0)
+    }
 
-        let h_oob = Hex::new(60, 30);
-        match h_oob {
-            Some(_) => assert!(false),
-            None => assert!(true),
-        }
+    // Every hex inside the map, column by column.
+    pub fn hexes(&self) -> impl Iterator<Item = Hex> {
+        let rows = self.rows;
+        (0..self.cols).flat_map(move |col| (0..rows).map(move |row| Hex::new(col, row)))
     }
+}
 
-    #[test]
-    fn display_formatter() {
-        let h0101 = Hex::new(0, 0);
-        match h0101 {
-            Some(h) => assert_eq!(h.number(), 101),
-            None => assert!(false),
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let h6030 = Hex::new(59, 29);
-        match h6030 {
-            Some(h) => assert_eq!(h.number(), 6030),
-            None => assert!(false),
-        }
+    #[test]
+    fn labels() {
+        assert_eq!("0101", format!("{}", Hex::new(0, 0)));
+        assert_eq!("6030", format!("{}", Hex::new(59, 29)));
+        assert_eq!("9999", format!("{}", Hex::new(98, 98)));
+        assert_eq!("100.30", format!("{}", Hex::new(99, 29)));
+        assert_eq!("250.120", format!("{}", Hex::new(249, 119)));
+        assert_eq!("00.-4", format!("{}", Hex::new(-1, -5)));
     }
 
     #[test]
     fn from_label() {
-        let h0101 = Hex::from_label("0101");
-        match h0101 {
-            Some(h) => assert_eq!(h.number(), 101),
-            None => assert!(false),
+        for hex in [Hex::new(0, 0), Hex::new(59, 29), Hex::new(99, 29), Hex::new(249, 119), Hex::new(-1, -5)] {
+            assert_eq!(Hex::from_label(&hex.to_string()), Some(hex));
         }
+        assert_eq!(Hex::from_label("0629"), Some(Hex::new(5, 28)));
+        assert_eq!(Hex::from_label("629"), None);
+        assert_eq!(Hex::from_label("06x9"), None);
+        assert_eq!(Hex::from_label("1.a"), None);
+    }
 
-        let h6030 = Hex::from_label("6030");
-        match h6030 {
-            Some(h) => assert_eq!(h.number(), 6030),
-            None => assert!(false),
-        }
+    #[test]
+    fn map_bounds() {
+        let map = STANDARD_MAP;
+        assert!(map.contains(&Hex::new(0, 0)));
+        assert!(map.contains(&Hex::new(59, 29)));
+        assert_eq!(map.hex(-1, -1), None);
+        assert_eq!(map.hex(60, 30), None);
+        assert_eq!(map.hexes().count(), 60 * 30);
+
+        let big = Map {
+            cols: 150,
+            rows: 80,
+            ..STANDARD_MAP
+        };
+        assert_eq!(big.hex(120, 70), Some(Hex::new(120, 70)));
+
+        let floating = Map {
+            edges: MapEdges::Floating,
+            ..STANDARD_MAP
+        };
+        assert_eq!(floating.hex(-1, 300), Some(Hex::new(-1, 300)));
+        assert!(!floating.is_inside(&Hex::new(-1, 300)));
+    }
+
+    #[test]
+    fn recentering() {
+        let floating = Map {
+            edges: MapEdges::Floating,
+            ..STANDARD_MAP
+        };
+        // Clear of the edges, or on a fixed map, nothing moves.
+        let clear = [Hex::new(20, 10), Hex::new(30, 20)];
+        assert_eq!(floating.recenter(&clear), None);
+        let near = [Hex::new(57, 10), Hex::new(45, 2)];
+        assert_eq!(STANDARD_MAP.recenter(&near), None);
+
+        let shift = floating.recenter(&near).unwrap();
+        assert_eq!(shift, Shift { cols: -20, rows: 8 });
+        let moved: Vec<Hex> = near.iter().map(|h| h.shifted(shift)).collect();
+        assert!(moved.iter().all(|h| floating.is_inside(h)));
+        assert_eq!(moved[0].distance_to(&moved[1]), near[0].distance_to(&near[1]));
+        assert_eq!(moved[0].bearing_to(&moved[1]), near[0].bearing_to(&near[1]));
+        assert_eq!(floating.recenter(&moved), None);
+
+        // Units too far apart to all be clear of the edges are centered once
+        // and then left alone.
+        let wide = [Hex::new(0, 15), Hex::new(55, 15)];
+        let shift = floating.recenter(&wide).unwrap();
+        assert_eq!(shift.cols % 2, 0);
+        let moved: Vec<Hex> = wide.iter().map(|h| h.shifted(shift)).collect();
+        assert_eq!(floating.recenter(&moved), None);
     }
 
     #[test]
     fn upper_left_boundary_neighbors() {
-        if let Some(h) = Hex::new(MIN_COL, MIN_ROW) {
-            assert!(h.neighbor(Facing::A).is_none());
-            assert!(h.neighbor(Facing::B).is_none());
-            assert_eq!(h.neighbor(Facing::C), Hex::new(MIN_COL + 1, MIN_ROW));
-            assert_eq!(h.neighbor(Facing::D), Hex::new(MIN_COL, MIN_ROW + 1));
-            assert!(h.neighbor(Facing::E).is_none());
-            assert!(h.neighbor(Facing::F).is_none());
-        } else {
-            assert!(false)
-        }
+        let map = STANDARD_MAP;
+        let h = Hex::new(0, 0);
+        assert!(map.neighbor(&h, Facing::A).is_none());
+        assert!(map.neighbor(&h, Facing::B).is_none());
+        assert_eq!(map.neighbor(&h, Facing::C), Some(Hex::new(1, 0)));
+        assert_eq!(map.neighbor(&h, Facing::D), Some(Hex::new(0, 1)));
+        assert!(map.neighbor(&h, Facing::E).is_none());
+        assert!(map.neighbor(&h, Facing::F).is_none());
+        assert_eq!(h.neighbor(Facing::A), Hex::new(0, -1));
     }
 
     #[test]
     fn lower_right_boundary_neighbors() {
-        if let Some(h) = Hex::new(MAX_COL, MAX_ROW) {
-            assert_eq!(h.neighbor(Facing::A), Hex::new(MAX_COL, MAX_ROW - 1));
-            assert!(h.neighbor(Facing::B).is_none());
-            assert!(h.neighbor(Facing::C).is_none());
-            assert!(h.neighbor(Facing::D).is_none());
-            assert!(h.neighbor(Facing::E).is_none());
-            assert_eq!(h.neighbor(Facing::F), Hex::new(MAX_COL - 1, MAX_ROW));
-        } else {
-            assert!(false)
-        }
+        let map = STANDARD_MAP;
+        let h = Hex::new(59, 29);
+        assert_eq!(map.neighbor(&h, Facing::A), Some(Hex::new(59, 28)));
+        assert!(map.neighbor(&h, Facing::B).is_none());
+        assert!(map.neighbor(&h, Facing::C).is_none());
+        assert!(map.neighbor(&h, Facing::D).is_none());
+        assert!(map.neighbor(&h, Facing::E).is_none());
+        assert_eq!(map.neighbor(&h, Facing::F), Some(Hex::new(58, 29)));
     }
 
     #[test]
     fn distance_sanity() {
-        if let Some(h) = Hex::new(1, 1) {
-            assert_eq!(0, h.distance_to(&h));
-
-            if let Some(h2) = Hex::new(2, 1) {
-                assert_eq!(1, h.distance_to(&h2))
-            } else {
-                assert!(false)
-            }
-
-            if let Some(h2) = Hex::new(1, 2) {
-                assert_eq!(1, h.distance_to(&h2))
-            } else {
-                assert!(false)
-            }
-
-            if let Some(h2) = Hex::new(1, 10) {
-                assert_eq!(9, h.distance_to(&h2))
-            } else {
-                assert!(false)
-            }
-
-            if let Some(h2) = Hex::new(10, 1) {
-                assert_eq!(9, h.distance_to(&h2))
-            } else {
-                assert!(false)
-            }
-        } else {
-            assert!(false)
-        }
+        let h = Hex::new(1, 1);
+        assert_eq!(0, h.distance_to(&h));
+        assert_eq!(1, h.distance_to(&Hex::new(2, 1)));
+        assert_eq!(1, h.distance_to(&Hex::new(1, 2)));
+        assert_eq!(9, h.distance_to(&Hex::new(1, 10)));
+        assert_eq!(9, h.distance_to(&Hex::new(10, 1)));
+        assert_eq!(100, Hex::new(150, 10).distance_to(&Hex::new(150, 110)));
     }
 
     #[test]
     fn bearing_to_sanity() {
-        if let Some(h) = Hex::new(39, 1) {
-            if let Some(h2) = Hex::new(39, 0) {
-                assert_eq!(BearingTo::A, h.bearing_to(&h2))
-            } else {
-                assert!(false)
-            }
-            if let Some(h2) = Hex::new(40, 0) {
-                assert_eq!(BearingTo::AOrB, h.bearing_to(&h2))
-            } else {
-                assert!(false)
-            }
-            if let Some(h2) = Hex::new(40, 1) {
-                assert_eq!(BearingTo::B, h.bearing_to(&h2))
-            } else {
-                assert!(false)
-            }
-            if let Some(h2) = Hex::new(41, 1) {
-                assert_eq!(BearingTo::BOrC, h.bearing_to(&h2))
-            } else {
-                assert!(false)
-            }
-            if let Some(h2) = Hex::new(40, 2) {
-                assert_eq!(BearingTo::C, h.bearing_to(&h2))
-            } else {
-                assert!(false)
-            }
-            if let Some(h2) = Hex::new(40, 3) {
-                assert_eq!(BearingTo::COrD, h.bearing_to(&h2))
-            } else {
-                assert!(false)
-            }
-            if let Some(h2) = Hex::new(39, 2) {
-                assert_eq!(BearingTo::D, h.bearing_to(&h2))
-            } else {
-                assert!(false)
-            }
-            if let Some(h2) = Hex::new(38, 3) {
-                assert_eq!(BearingTo::DOrE, h.bearing_to(&h2))
-            } else {
-                assert!(false)
-            }
-            if let Some(h2) = Hex::new(38, 2) {
-                assert_eq!(BearingTo::E, h.bearing_to(&h2))
-            } else {
-                assert!(false)
-            }
-            if let Some(h2) = Hex::new(37, 1) {
-                assert_eq!(BearingTo::EOrF, h.bearing_to(&h2))
-            } else {
-                assert!(false)
-            }
-            if let Some(h2) = Hex::new(38, 1) {
-                assert_eq!(BearingTo::F, h.bearing_to(&h2))
-            } else {
-                assert!(false)
-            }
-            if let Some(h2) = Hex::new(38, 0) {
-                assert_eq!(BearingTo::FOrA, h.bearing_to(&h2))
-            } else {
-                assert!(false)
-            }
+        let h = Hex::new(39, 1);
+        assert_eq!(BearingTo::A, h.bearing_to(&Hex::new(39, 0)));
+        assert_eq!(BearingTo::AOrB, h.bearing_to(&Hex::new(40, 0)));
+        assert_eq!(BearingTo::B, h.bearing_to(&Hex::new(40, 1)));
+        assert_eq!(BearingTo::BOrC, h.bearing_to(&Hex::new(41, 1)));
+        assert_eq!(BearingTo::C, h.bearing_to(&Hex::new(40, 2)));
+        assert_eq!(BearingTo::COrD, h.bearing_to(&Hex::new(40, 3)));
+        assert_eq!(BearingTo::D, h.bearing_to(&Hex::new(39, 2)));
+        assert_eq!(BearingTo::DOrE, h.bearing_to(&Hex::new(38, 3)));
+        assert_eq!(BearingTo::E, h.bearing_to(&Hex::new(38, 2)));
+        assert_eq!(BearingTo::EOrF, h.bearing_to(&Hex::new(37, 1)));
+        assert_eq!(BearingTo::F, h.bearing_to(&Hex::new(38, 1)));
+        assert_eq!(BearingTo::FOrA, h.bearing_to(&Hex::new(38, 0)));
+        assert_eq!(BearingTo::D, h.bearing_to(&Hex::new(38, 6)));
+
+        // The same past the 99th column.
+        let far = Hex::new(139, 101);
+        assert_eq!(BearingTo::AOrB, far.bearing_to(&Hex::new(140, 100)));
+        assert_eq!(BearingTo::EOrF, far.bearing_to(&Hex::new(137, 101)));
+    }
 
-            if let Some(h2) = Hex::new(38, 6) {
-                assert_eq!(BearingTo::D, h.bearing_to(&h2))
-            } else {
-                assert!(false)
-            }
-        } else {
-            assert!(false)
-        }
+    #[test]
+    fn relative_bearing() {
+        assert_eq!(BearingTo::A.relative_to(Facing::A), BearingTo::A);
+        assert_eq!(BearingTo::A.relative_to(Facing::D), BearingTo::D);
+        assert_eq!(BearingTo::C.relative_to(Facing::B), BearingTo::B);
+        assert_eq!(BearingTo::AOrB.relative_to(Facing::F), BearingTo::BOrC);
+        assert_eq!(BearingTo::FOrA.relative_to(Facing::A), BearingTo::FOrA);
+
+        assert_eq!(BearingTo::E.facing(), Some(Facing::E));
+        assert_eq!(BearingTo::EOrF.facing(), None);
+        assert_eq!(BearingTo::EOrF.sides(), (Facing::E, Facing::F));
+        assert_eq!(BearingTo::FOrA.sides(), (Facing::F, Facing::A));
+        assert_eq!(BearingTo::C.sides(), (Facing::C, Facing::C));
     }
 
     #[test]
-    fn screen_to_hex() {
-        use crate::screen::map;
-        use crate::screen::{MENU_HEIGHT, WINDOW_HEIGHT};
-
-        let map_state = map::init(Point2::new(0.0, MENU_HEIGHT), WINDOW_HEIGHT - MENU_HEIGHT);
-
-        assert_eq!(None, Hex::from_screen(Point2::new(0.0, MENU_HEIGHT), &map_state));
-
-        assert_eq!(
-            Hex::new(0, 0),
-            Hex::from_screen(Point2::new(map_state.origin.x + map_state.hex_edge / 2.0, map_state.origin.y + map_state.hex_edge / 2.0), &map_state)
-        );
-
-        for col in MIN_COL..MAX_COL {
-            for row in MIN_ROW..MAX_ROW {
-                let h = Hex::new(col, row).unwrap();
-                assert_eq!(
-                    h,
-                    Hex::from_screen(h.to_screen(&map_state), &map_state).unwrap()
-                );
-            }
-        }
+    fn rings_and_lines() {
+        let corner = Hex::new(0, 0);
+        assert_eq!(corner.ring(1).iter().filter(|h| STANDARD_MAP.contains(h)).count(), 2);
+        assert_eq!(corner.spiral(1).len(), 7);
+        assert_eq!(Hex::new(10, 10).ring(2).len(), 12);
+
+        let line = Hex::new(4, 4).line_to(&Hex::new(8, 4));
+        assert_eq!(line.len(), 5);
+        assert_eq!(line.first(), Some(&Hex::new(4, 4)));
+        assert_eq!(line.last(), Some(&Hex::new(8, 4)));
     }
 }
@@ -0,0 +1,184 @@
+// The 32-impulse turn. A ship moving at speed S moves on the impulses where
+// the running total of S/32 crosses a whole hex, which reproduces the
+// standard impulse chart: speed 1 moves on impulse 32, speed 16 on every even
+// impulse, speed 32 on every impulse.
+
+use crate::hex::Map;
+use crate::ship::Position;
+use serde::{Deserialize, Serialize};
+
+pub const IMPULSES_PER_TURN: u8 = 32;
+pub const MAX_SPEED: u8 = 32;
+
+pub fn moves_on_impulse(speed: u8, impulse: u8) -> bool {
+    if impulse == 0 || impulse > IMPULSES_PER_TURN {
+        return false;
+    }
+    let speed = speed.min(MAX_SPEED) as u16;
+    let impulse = impulse as u16;
+    let per_turn = IMPULSES_PER_TURN as u16;
+    impulse * speed / per_turn > (impulse - 1) * speed / per_turn
+}
+
+// One row of the impulse chart: the impulses on which a unit at the given
+// speed moves.
+pub fn movement_impulses(speed: u8) -> Vec<u8> {
+    (1..=IMPULSES_PER_TURN)
+        .filter(|i| moves_on_impulse(speed, *i))
+        .collect()
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Move {
+    pub ship: usize,
+    pub from: Position,
+    // None when the move would take the ship off the map.
+    pub to: Option<Position>,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct Impulse {
+    pub turn: u16,
+    pub impulse: u8,
+    pub moves: Vec<Move>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Sequencer {
+    turn: u16,
+    impulse: u8,
+}
+
+impl Default for Sequencer {
+    fn default() -> Self {
+        Sequencer::new()
+    }
+}
+
+impl Sequencer {
+    // Starts before the first impulse of turn 1.
+    pub fn new() -> Sequencer {
+        Sequencer { turn: 1, impulse: 0 }
+    }
+
+    pub fn turn(&self) -> u16 {
+        self.turn
+    }
+
+    // The impulse most recently run, or 0 if the current turn hasn't started.
+    pub fn impulse(&self) -> u8 {
+        self.impulse
+    }
+
+    // Runs the next impulse, rolling over into a new turn after impulse 32.
+    // Every ship whose speed calls for movement on this impulse moves one hex
+    // forward along its facing, unless that would take it off the map.
+    pub fn run_impulse(&mut self, ships: &[(Position, u8)], map: &Map) -> Impulse {
+        if self.impulse == IMPULSES_PER_TURN {
+            self.turn += 1;
+            self.impulse = 0;
+        }
+        self.impulse += 1;
+
+        let moves = ships
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, speed))| moves_on_impulse(*speed, self.impulse))
+            .map(|(ship, (from, _))| Move {
+                ship,
+                from: *from,
+                to: map.neighbor(&from.hex, from.facing).map(|hex| Position {
+                    hex,
+                    facing: from.facing,
+                }),
+            })
+            .collect();
+
+        Impulse {
+            turn: self.turn,
+            impulse: self.impulse,
+            moves,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex::{Facing, Hex, STANDARD_MAP};
+
+    #[test]
+    fn chart_rows() {
+        assert_eq!(movement_impulses(0), Vec::<u8>::new());
+        assert_eq!(movement_impulses(1), vec![32]);
+        assert_eq!(movement_impulses(2), vec![16, 32]);
+        assert_eq!(movement_impulses(4), vec![8, 16, 24, 32]);
+        assert_eq!(movement_impulses(16), (1..=16).map(|i| i * 2).collect::<Vec<u8>>());
+        assert_eq!(movement_impulses(32), (1..=32).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn moves_equal_speed() {
+        for speed in 0..=MAX_SPEED {
+            assert_eq!(speed as usize, movement_impulses(speed).len());
+        }
+    }
+
+    #[test]
+    fn out_of_range_impulses() {
+        assert!(!moves_on_impulse(32, 0));
+        assert!(!moves_on_impulse(32, 33));
+    }
+
+    #[test]
+    fn sequencer_moves_ships() {
+        let slow = Position {
+            hex: Hex::new(10, 10),
+            facing: Facing::A,
+        };
+        let fast = Position {
+            hex: Hex::new(20, 20),
+            facing: Facing::D,
+        };
+        let mut sequencer = Sequencer::new();
+
+        let first = sequencer.run_impulse(&[(slow, 1), (fast, 32)], &STANDARD_MAP);
+        assert_eq!(first.turn, 1);
+        assert_eq!(first.impulse, 1);
+        assert_eq!(
+            first.moves,
+            vec![Move {
+                ship: 1,
+                from: fast,
+                to: Some(Position {
+                    hex: Hex::new(20, 21),
+                    facing: Facing::D,
+                }),
+            }]
+        );
+
+        for _ in 2..IMPULSES_PER_TURN {
+            sequencer.run_impulse(&[(slow, 1)], &STANDARD_MAP);
+        }
+        let last = sequencer.run_impulse(&[(slow, 1)], &STANDARD_MAP);
+        assert_eq!(last.impulse, 32);
+        assert_eq!(last.moves.len(), 1);
+        assert_eq!(last.moves[0].to.unwrap().hex, Hex::new(10, 9));
+
+        let next = sequencer.run_impulse(&[(slow, 1)], &STANDARD_MAP);
+        assert_eq!(next.turn, 2);
+        assert_eq!(next.impulse, 1);
+        assert!(next.moves.is_empty());
+    }
+
+    #[test]
+    fn blocked_at_map_edge() {
+        let edge = Position {
+            hex: Hex::new(0, 0),
+            facing: Facing::A,
+        };
+        let mut sequencer = Sequencer::new();
+        let impulse = sequencer.run_impulse(&[(edge, 32)], &STANDARD_MAP);
+        assert_eq!(impulse.moves[0].to, None);
+    }
+}
@@ -0,0 +1,27 @@
+// The Star Fleet Battles rules engine. Nothing in here draws anything, so it
+// runs the same under the game window, in tests and in command-line tools.
+
+pub mod ai;
+pub mod combat;
+pub mod controller;
+pub mod cube;
+pub mod damage;
+pub mod dice;
+pub mod energy;
+pub mod game;
+pub mod hex;
+pub mod impulse;
+pub mod movement;
+pub mod net;
+pub mod path;
+pub mod plot;
+pub mod replay;
+pub mod resources;
+pub mod save;
+pub mod scenario;
+pub mod seeking;
+pub mod shields;
+pub mod sim;
+pub mod ship;
+pub mod ship_spec;
+pub mod weapons;
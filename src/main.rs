@@ -1,11 +1,16 @@
 #[macro_use]
 extern crate approx;
 
-mod hex;
 mod screen;
-mod ship;
-mod ship_spec;
 
+use std::env;
+use std::process;
+
+// Takes an optional scenario file to start with.
 fn main() {
-    screen::run().unwrap();
+    let scenario = env::args().nth(1).unwrap_or_else(|| String::from("the_duel.toml"));
+    if let Err(e) = screen::run(&scenario) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
 }
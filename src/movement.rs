@@ -0,0 +1,453 @@
+// Movement rules: turn modes and the bookkeeping needed to enforce them.
+
+use crate::dice::Dice;
+use crate::hex::{Facing, Map};
+use crate::impulse::IMPULSES_PER_TURN;
+use crate::energy::EnergyAllocation;
+use crate::ship::Position;
+use crate::ship_spec::Movement;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum TurnMode {
+    AA,
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+}
+
+impl TurnMode {
+    // The highest speed at which each successive hex count applies, so for
+    // turn mode D a ship at speeds 1-4 must move 1 hex between turns, at 5-8
+    // it must move 2, and so on.
+    fn brackets(&self) -> &'static [u8] {
+        use TurnMode::*;
+        match self {
+            AA => &[9, 19, 32],
+            A => &[8, 16, 24, 32],
+            B => &[6, 12, 18, 24, 32],
+            C => &[5, 10, 15, 20, 26, 32],
+            D => &[4, 8, 13, 19, 26, 32],
+            E => &[3, 7, 11, 16, 21, 26, 32],
+            F => &[2, 5, 9, 13, 18, 23, 28, 32],
+        }
+    }
+
+    // Hexes a ship must move straight ahead before it may turn at this speed.
+    pub fn hexes_required(&self, speed: u8) -> u8 {
+        let brackets = self.brackets();
+        match brackets.iter().position(|max| speed <= *max) {
+            Some(i) => i as u8 + 1,
+            None => brackets.len() as u8,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    pub fn turn(&self, facing: Facing) -> Facing {
+        match self {
+            Side::Left => facing.turn_left(),
+            Side::Right => facing.turn_right(),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum MovementError {
+    // A turn was attempted before the ship had moved far enough since its last one.
+    TurnMode { turn_mode: TurnMode, speed: u8, required: u8, moved: u8 },
+    // A ship that isn't moving can't turn under its turn mode.
+    Stationary,
+    // Sideslips must be separated by at least one hex of straight movement.
+    Sideslip,
+    // The move would take the ship off the map.
+    OffMap,
+    // Not enough energy was allocated for a high energy turn.
+    InsufficientEnergy { required: u8, available: u8 },
+    // Tactical maneuvers are only for ships at speed 0.
+    Moving,
+    // Only one tactical maneuver is allowed in each set of 8 impulses.
+    TacticalManeuverUsed,
+}
+
+impl fmt::Display for MovementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MovementError::TurnMode {
+                turn_mode,
+                speed,
+                required,
+                moved,
+            } => write!(
+                f,
+                "Turn mode {:?} at speed {} requires {} hexes straight before turning; moved {}",
+                turn_mode, speed, required, moved
+            ),
+            MovementError::Stationary => write!(f, "A ship at speed 0 can't turn"),
+            MovementError::Sideslip => write!(f, "A ship must move straight ahead between sideslips"),
+            MovementError::OffMap => write!(f, "That move would leave the map"),
+            MovementError::InsufficientEnergy { required, available } => write!(
+                f,
+                "A high energy turn needs {} energy; {} allocated",
+                required, available
+            ),
+            MovementError::Moving => write!(f, "Only a ship at speed 0 can make a tactical maneuver"),
+            MovementError::TacticalManeuverUsed => {
+                write!(f, "Only one tactical maneuver is allowed every {} impulses", TAC_IMPULSES)
+            }
+        }
+    }
+}
+
+// The size of the sets of impulses that tactical maneuvers are limited by.
+pub const TAC_IMPULSES: u8 = 8;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct HighEnergyTurn {
+    pub facing: Facing,
+    pub broke_down: bool,
+}
+
+// Tracks what a ship has done since its last turn.
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct MovementState {
+    // None until the ship makes its first turn; a ship starts the game free
+    // to turn.
+    hexes_since_turn: Option<u8>,
+    // Whether the last hex of movement was a sideslip.
+    sideslipped: bool,
+    // The turn and impulse set of the last tactical maneuver.
+    last_tactical: Option<(u16, u8)>,
+}
+
+impl MovementState {
+    pub fn new() -> MovementState {
+        MovementState::default()
+    }
+
+    pub fn hexes_since_turn(&self) -> Option<u8> {
+        self.hexes_since_turn
+    }
+
+    pub fn record_move(&mut self) {
+        self.count_hex();
+        self.sideslipped = false;
+    }
+
+    // Sideslips count toward the turn mode like any other hex of movement.
+    fn count_hex(&mut self) {
+        if let Some(hexes) = self.hexes_since_turn {
+            self.hexes_since_turn = Some(hexes.saturating_add(1));
+        }
+    }
+
+    pub fn check_turn(&self, turn_mode: TurnMode, speed: u8) -> Result<(), MovementError> {
+        if speed == 0 {
+            return Err(MovementError::Stationary);
+        }
+        let required = turn_mode.hexes_required(speed);
+        match self.hexes_since_turn {
+            Some(moved) if moved < required => Err(MovementError::TurnMode {
+                turn_mode,
+                speed,
+                required,
+                moved,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    // Validates and records a one hexside turn, returning the new facing.
+    pub fn turn(
+        &mut self,
+        facing: Facing,
+        side: Side,
+        turn_mode: TurnMode,
+        speed: u8,
+    ) -> Result<Facing, MovementError> {
+        self.check_turn(turn_mode, speed)?;
+        self.hexes_since_turn = Some(0);
+        Ok(side.turn(facing))
+    }
+
+    // Turns to any facing regardless of turn mode, paid for from the energy
+    // allocated to high energy turns. The ship then rolls for breakdown: on a
+    // roll at or above its breakdown rating it ends up facing wherever a second
+    // die sends it instead.
+    pub fn high_energy_turn(
+        &mut self,
+        facing: Facing,
+        spec: &Movement,
+        energy: &mut EnergyAllocation,
+        dice: &mut dyn Dice,
+    ) -> Result<HighEnergyTurn, MovementError> {
+        if energy.high_energy_turns < spec.het_cost {
+            return Err(MovementError::InsufficientEnergy {
+                required: spec.het_cost,
+                available: energy.high_energy_turns,
+            });
+        }
+        energy.high_energy_turns -= spec.het_cost;
+        self.hexes_since_turn = Some(0);
+
+        if dice.d6() >= spec.breakdown {
+            return Ok(HighEnergyTurn {
+                facing: Facing::from_index(dice.d6() - 1),
+                broke_down: true,
+            });
+        }
+        Ok(HighEnergyTurn {
+            facing,
+            broke_down: false,
+        })
+    }
+
+    // A ship at speed 0 may pivot one hexside once in each set of 8 impulses.
+    pub fn tactical_maneuver(
+        &mut self,
+        facing: Facing,
+        side: Side,
+        speed: u8,
+        turn: u16,
+        impulse: u8,
+    ) -> Result<Facing, MovementError> {
+        if speed != 0 {
+            return Err(MovementError::Moving);
+        }
+        let set = (impulse.clamp(1, IMPULSES_PER_TURN) - 1) / TAC_IMPULSES;
+        if self.last_tactical == Some((turn, set)) {
+            return Err(MovementError::TacticalManeuverUsed);
+        }
+        self.last_tactical = Some((turn, set));
+        Ok(side.turn(facing))
+    }
+
+    pub fn check_sideslip(&self) -> Result<(), MovementError> {
+        if self.sideslipped {
+            return Err(MovementError::Sideslip);
+        }
+        Ok(())
+    }
+
+    // Validates and records a sideslip into the forward-left or forward-right
+    // hex, returning the new position. The facing doesn't change.
+    pub fn sideslip(&mut self, position: Position, side: Side, map: &Map) -> Result<Position, MovementError> {
+        self.check_sideslip()?;
+        let hex = map
+            .neighbor(&position.hex, side.turn(position.facing))
+            .ok_or(MovementError::OffMap)?;
+        self.count_hex();
+        self.sideslipped = true;
+        Ok(Position {
+            hex,
+            facing: position.facing,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dice::{LoadedDice, SeededDice};
+    use crate::hex::{Hex, STANDARD_MAP};
+
+    #[test]
+    fn turn_mode_chart() {
+        assert_eq!(TurnMode::D.hexes_required(1), 1);
+        assert_eq!(TurnMode::D.hexes_required(4), 1);
+        assert_eq!(TurnMode::D.hexes_required(5), 2);
+        assert_eq!(TurnMode::D.hexes_required(15), 4);
+        assert_eq!(TurnMode::D.hexes_required(32), 6);
+        assert_eq!(TurnMode::B.hexes_required(15), 3);
+        assert_eq!(TurnMode::AA.hexes_required(32), 3);
+        assert_eq!(TurnMode::F.hexes_required(40), 8);
+    }
+
+    #[test]
+    fn first_turn_is_free() {
+        let mut state = MovementState::new();
+        assert_eq!(state.turn(Facing::A, Side::Right, TurnMode::D, 15), Ok(Facing::B));
+    }
+
+    #[test]
+    fn turn_mode_enforced() {
+        let mut state = MovementState::new();
+        state.turn(Facing::A, Side::Left, TurnMode::D, 15).unwrap();
+        assert_eq!(
+            state.turn(Facing::F, Side::Left, TurnMode::D, 15),
+            Err(MovementError::TurnMode {
+                turn_mode: TurnMode::D,
+                speed: 15,
+                required: 4,
+                moved: 0
+            })
+        );
+
+        for _ in 0..3 {
+            state.record_move();
+        }
+        assert!(state.check_turn(TurnMode::D, 15).is_err());
+        state.record_move();
+        assert_eq!(state.turn(Facing::F, Side::Left, TurnMode::D, 15), Ok(Facing::E));
+        assert_eq!(state.hexes_since_turn(), Some(0));
+    }
+
+    #[test]
+    fn sideslip_keeps_facing() {
+        let mut state = MovementState::new();
+        let start = Position {
+            hex: Hex::new(10, 10),
+            facing: Facing::A,
+        };
+        assert_eq!(
+            state.sideslip(start, Side::Left, &STANDARD_MAP),
+            Ok(Position {
+                hex: Hex::new(9, 9),
+                facing: Facing::A,
+            })
+        );
+        assert_eq!(
+            MovementState::new().sideslip(start, Side::Right, &STANDARD_MAP),
+            Ok(Position {
+                hex: Hex::new(11, 9),
+                facing: Facing::A,
+            })
+        );
+    }
+
+    #[test]
+    fn straight_move_between_sideslips() {
+        let mut state = MovementState::new();
+        let start = Position {
+            hex: Hex::new(10, 10),
+            facing: Facing::C,
+        };
+        let slipped = state.sideslip(start, Side::Right, &STANDARD_MAP).unwrap();
+        assert_eq!(state.sideslip(slipped, Side::Right, &STANDARD_MAP), Err(MovementError::Sideslip));
+        state.record_move();
+        assert!(state.sideslip(slipped, Side::Left, &STANDARD_MAP).is_ok());
+    }
+
+    #[test]
+    fn sideslip_counts_toward_turn_mode() {
+        let mut state = MovementState::new();
+        state.turn(Facing::A, Side::Left, TurnMode::A, 4).unwrap();
+        let start = Position {
+            hex: Hex::new(10, 10),
+            facing: Facing::F,
+        };
+        state.sideslip(start, Side::Left, &STANDARD_MAP).unwrap();
+        assert_eq!(state.hexes_since_turn(), Some(1));
+        assert!(state.check_turn(TurnMode::A, 4).is_ok());
+    }
+
+    #[test]
+    fn sideslip_off_map() {
+        let mut state = MovementState::new();
+        let corner = Position {
+            hex: Hex::new(0, 0),
+            facing: Facing::A,
+        };
+        assert_eq!(state.sideslip(corner, Side::Left, &STANDARD_MAP), Err(MovementError::OffMap));
+        // A failed sideslip doesn't count against the next one.
+        assert!(state.check_sideslip().is_ok());
+    }
+
+    fn movement_spec() -> Movement {
+        Movement {
+            turn_mode: TurnMode::D,
+            movement_cost: 1,
+            het_cost: 5,
+            breakdown: 5,
+        }
+    }
+
+    #[test]
+    fn high_energy_turn() {
+        let mut state = MovementState::new();
+        let mut energy = EnergyAllocation {
+            high_energy_turns: 6,
+            ..Default::default()
+        };
+        let mut dice = LoadedDice::new(vec![4]);
+        assert_eq!(
+            state.high_energy_turn(Facing::D, &movement_spec(), &mut energy, &mut dice),
+            Ok(HighEnergyTurn {
+                facing: Facing::D,
+                broke_down: false
+            })
+        );
+        assert_eq!(energy.high_energy_turns, 1);
+        assert_eq!(state.hexes_since_turn(), Some(0));
+
+        assert_eq!(
+            state.high_energy_turn(Facing::A, &movement_spec(), &mut energy, &mut dice),
+            Err(MovementError::InsufficientEnergy {
+                required: 5,
+                available: 1
+            })
+        );
+    }
+
+    #[test]
+    fn high_energy_turn_breakdown() {
+        let mut state = MovementState::new();
+        let mut energy = EnergyAllocation {
+            high_energy_turns: 5,
+            ..Default::default()
+        };
+        let mut dice = LoadedDice::new(vec![5, 3]);
+        assert_eq!(
+            state.high_energy_turn(Facing::D, &movement_spec(), &mut energy, &mut dice),
+            Ok(HighEnergyTurn {
+                facing: Facing::C,
+                broke_down: true
+            })
+        );
+    }
+
+    #[test]
+    fn high_energy_turn_seeded() {
+        let spec = movement_spec();
+        let turn = |seed| {
+            let mut energy = EnergyAllocation {
+                high_energy_turns: 5,
+                ..Default::default()
+            };
+            MovementState::new().high_energy_turn(Facing::B, &spec, &mut energy, &mut SeededDice::new(seed))
+        };
+        assert_eq!(turn(1234), turn(1234));
+    }
+
+    #[test]
+    fn tactical_maneuvers() {
+        let mut state = MovementState::new();
+        assert_eq!(state.tactical_maneuver(Facing::A, Side::Right, 3, 1, 1), Err(MovementError::Moving));
+        assert_eq!(state.tactical_maneuver(Facing::A, Side::Right, 0, 1, 1), Ok(Facing::B));
+        assert_eq!(
+            state.tactical_maneuver(Facing::B, Side::Right, 0, 1, 8),
+            Err(MovementError::TacticalManeuverUsed)
+        );
+        assert_eq!(state.tactical_maneuver(Facing::B, Side::Right, 0, 1, 9), Ok(Facing::C));
+        assert_eq!(state.tactical_maneuver(Facing::C, Side::Left, 0, 2, 9), Ok(Facing::B));
+    }
+
+    #[test]
+    fn stationary_ships_cant_turn() {
+        let mut state = MovementState::new();
+        assert_eq!(
+            state.turn(Facing::A, Side::Left, TurnMode::A, 0),
+            Err(MovementError::Stationary)
+        );
+    }
+}
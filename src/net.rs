@@ -0,0 +1,611 @@
+// Two-player games over TCP. One player hosts: their machine keeps the real
+// game and checks every command against the rules before carrying it out.
+// The other joins and keeps a copy, which the host sends again after every
+// change. Messages are JSON, one to a line.
+//
+// What the host sends is what either player could see across the table. The
+// dice stay on the host, so the joining player only learns a roll once it's
+// made, and can't work out the ones to come.
+// Energy plots stay on the host until both are in. Then each player gets
+// the allocation orders for their own ships, and only the speed plotted for
+// the other player's.
+
+use crate::energy::EnergyAllocation;
+use crate::game::{Game, Order, OrderError};
+use crate::plot::{Plot, PlotError};
+use crate::replay::Entry;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+pub const DEFAULT_PORT: u16 = 7341;
+
+// How long a joining player waits to hear from the host.
+const WELCOME_TIMEOUT: Duration = Duration::from_secs(10);
+
+// What a player asks of the host.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Command {
+    // The player's secret energy plot for the coming turn.
+    Plot(Vec<(usize, EnergyAllocation)>),
+    // An order for one of the player's ships between impulses.
+    Order { ship: usize, order: Order },
+    // Done giving orders; the next impulse runs once both players are.
+    Ready,
+}
+
+// Something that happened in the game, as both players hear of it.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Update {
+    // An order or impulse the host carried out.
+    Entry(Entry),
+    // A side has committed its plot, though not what's in it.
+    Plotted(usize),
+    // The speed plotted for one of the other player's ships, which is all of
+    // its allocation they get to see.
+    PlottedSpeed { ship: usize, speed: u8 },
+    // The host turned down one of this player's commands.
+    Rejected(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Message {
+    Welcome { side: usize, game: Game },
+    Update(Update),
+    // The game as it stands after the entries that follow, less the dice
+    // and the other side's allocations.
+    Game(Game),
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum NetError {
+    Io(String),
+    // A message that couldn't be read.
+    Invalid(String),
+    Disconnected,
+    // Hosting needs a scenario with exactly two sides.
+    NotTwoSides(usize),
+    // The host never said hello.
+    NoWelcome,
+    Rejected(CommandError),
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetError::Io(e) => write!(f, "Network error: {}", e),
+            NetError::Invalid(e) => write!(f, "Couldn't read a message from the other player: {}", e),
+            NetError::Disconnected => write!(f, "The other player has disconnected"),
+            NetError::NotTwoSides(sides) => write!(f, "Network games need two sides, not {}", sides),
+            NetError::NoWelcome => write!(f, "The host didn't start the game"),
+            NetError::Rejected(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<io::Error> for NetError {
+    fn from(e: io::Error) -> NetError {
+        NetError::Io(e.to_string())
+    }
+}
+
+// Why the host turned a command down.
+#[derive(PartialEq, Eq, Debug)]
+pub enum CommandError {
+    GameOver,
+    NotYourShip(usize),
+    // Allocations only come in plots.
+    UnplottedAllocation,
+    Order(OrderError),
+    Plot(PlotError),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::GameOver => write!(f, "The game is over"),
+            CommandError::NotYourShip(ship) => write!(f, "Ship {} belongs to the other player", ship),
+            CommandError::UnplottedAllocation => write!(f, "Energy is allocated by plotting between turns"),
+            CommandError::Order(e) => write!(f, "{}", e),
+            CommandError::Plot(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+// A line-at-a-time connection. Lines are read on their own thread so the
+// game can check for them without waiting.
+struct Connection {
+    stream: TcpStream,
+    incoming: Receiver<io::Result<String>>,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> Result<Connection, NetError> {
+        stream.set_nodelay(true)?;
+        let reader = BufReader::new(stream.try_clone()?);
+        let (sender, incoming) = mpsc::channel();
+        thread::spawn(move || {
+            for line in reader.lines() {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Connection { stream, incoming })
+    }
+
+    fn send<T: Serialize>(&mut self, message: &T) -> Result<(), NetError> {
+        let line = serde_json::to_string(message).map_err(|e| NetError::Invalid(e.to_string()))?;
+        writeln!(self.stream, "{}", line)?;
+        self.stream.flush()?;
+        Ok(())
+    }
+
+    // The next message if one has come in.
+    fn try_recv<T: DeserializeOwned>(&mut self) -> Result<Option<T>, NetError> {
+        match self.incoming.try_recv() {
+            Ok(line) => parse(line).map(Some),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(NetError::Disconnected),
+        }
+    }
+
+    fn recv_timeout<T: DeserializeOwned>(&mut self, timeout: Duration) -> Result<Option<T>, NetError> {
+        match self.incoming.recv_timeout(timeout) {
+            Ok(line) => parse(line).map(Some),
+            Err(mpsc::RecvTimeoutError::Timeout) => Ok(None),
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(NetError::Disconnected),
+        }
+    }
+}
+
+fn parse<T: DeserializeOwned>(line: io::Result<String>) -> Result<T, NetError> {
+    serde_json::from_str(&line?).map_err(|e| NetError::Invalid(e.to_string()))
+}
+
+// Waits for the other player to join.
+pub struct Listener {
+    listener: TcpListener,
+}
+
+impl Listener {
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> Result<Listener, NetError> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Listener { listener })
+    }
+
+    pub fn local_addr(&self) -> Result<String, NetError> {
+        Ok(self.listener.local_addr()?.to_string())
+    }
+
+    // Starts the game with whoever has joined, if anyone has. The host plays
+    // the given side and the other player the other one.
+    pub fn accept(&self, game: Game, side: usize) -> Result<Option<Host>, NetError> {
+        if game.sides.len() != 2 {
+            return Err(NetError::NotTwoSides(game.sides.len()));
+        }
+        let stream = match self.listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        stream.set_nonblocking(false)?;
+        let mut client = Connection::new(stream)?;
+        let client_side = 1 - side;
+        client.send(&Message::Welcome {
+            side: client_side,
+            game: game.concealed(client_side),
+        })?;
+        let ready = vec![false; game.sides.len()];
+        Ok(Some(Host {
+            game,
+            client,
+            side,
+            client_side,
+            plot: None,
+            revealed_turn: None,
+            ready,
+        }))
+    }
+}
+
+// The hosting player's end, which keeps the game.
+pub struct Host {
+    game: Game,
+    client: Connection,
+    side: usize,
+    client_side: usize,
+    // The coming turn's plots until both are in, and the last turn whose
+    // plots were revealed.
+    plot: Option<Plot>,
+    revealed_turn: Option<u16>,
+    // Which sides are done giving orders for the coming impulse.
+    ready: Vec<bool>,
+}
+
+impl Host {
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn side(&self) -> usize {
+        self.side
+    }
+
+    // Carries out one of the host's own commands.
+    pub fn send(&mut self, command: Command) -> Result<Vec<Update>, NetError> {
+        let updates = self.carry_out(self.side, command).map_err(NetError::Rejected)?;
+        self.tell_client(&updates)?;
+        Ok(updates)
+    }
+
+    // Carries out whatever the other player has sent. Their rejected
+    // commands are sent back to them rather than reported here.
+    pub fn poll(&mut self) -> Result<Vec<Update>, NetError> {
+        let mut updates = Vec::new();
+        while let Some(command) = self.client.try_recv()? {
+            match self.carry_out(self.client_side, command) {
+                Ok(done) => {
+                    self.tell_client(&done)?;
+                    updates.extend(done);
+                }
+                Err(e) => self.client.send(&Message::Update(Update::Rejected(e.to_string())))?,
+            }
+        }
+        Ok(updates)
+    }
+
+    fn tell_client(&mut self, updates: &[Update]) -> Result<(), NetError> {
+        // The game goes first, so it's up to date by the time the client
+        // hears what happened.
+        if updates.iter().any(|u| matches!(u, Update::Entry(_))) {
+            self.client.send(&Message::Game(self.game.concealed(self.client_side)))?;
+        }
+        for update in updates {
+            let update = match update {
+                Update::Entry(Entry::Order {
+                    ship,
+                    order: Order::Allocate(e),
+                    ..
+                }) if self.game.side_of(*ship) != self.client_side => Update::PlottedSpeed {
+                    ship: *ship,
+                    speed: e.speed,
+                },
+                update => update.clone(),
+            };
+            self.client.send(&Message::Update(update))?;
+        }
+        Ok(())
+    }
+
+    fn carry_out(&mut self, side: usize, command: Command) -> Result<Vec<Update>, CommandError> {
+        if self.game.outcome().is_some() {
+            return Err(CommandError::GameOver);
+        }
+        let mut updates = Vec::new();
+        match command {
+            Command::Order { ship, order } => {
+                if ship >= self.game.ships.len() || self.game.side_of(ship) != side {
+                    return Err(CommandError::NotYourShip(ship));
+                }
+                if let Order::Allocate(_) = order {
+                    return Err(CommandError::UnplottedAllocation);
+                }
+                updates.push(Update::Entry(self.order(ship, order)?));
+            }
+            Command::Plot(allocations) => {
+                let turn = self.game.next_impulse().0;
+                if self.revealed_turn == Some(turn) {
+                    return Err(CommandError::Plot(PlotError::AlreadyPlotted(side)));
+                }
+                let game = &self.game;
+                let plot = self.plot.get_or_insert_with(|| Plot::new(game));
+                plot.commit(game, side, allocations).map_err(CommandError::Plot)?;
+                updates.push(Update::Plotted(side));
+                if plot.is_complete() {
+                    let orders = plot.reveal().map_err(CommandError::Plot)?;
+                    self.plot = None;
+                    self.revealed_turn = Some(turn);
+                    for (ship, order) in orders {
+                        updates.push(Update::Entry(self.order(ship, order)?));
+                    }
+                }
+            }
+            Command::Ready => {
+                self.ready[side] = true;
+                if let Some(entry) = self.run_impulse_if_ready() {
+                    updates.push(Update::Entry(entry));
+                }
+            }
+        }
+        Ok(updates)
+    }
+
+    fn order(&mut self, ship: usize, order: Order) -> Result<Entry, CommandError> {
+        let event = self.game.order(ship, &order).map_err(CommandError::Order)?;
+        Ok(Entry::Order {
+            turn: self.game.turn(),
+            impulse: self.game.impulse(),
+            ship,
+            order,
+            event,
+        })
+    }
+
+    // Runs the next impulse once both players are done with their orders,
+    // and with their plots if a turn is starting.
+    fn run_impulse_if_ready(&mut self) -> Option<Entry> {
+        let plotted = !self.game.between_turns() || self.revealed_turn == Some(self.game.next_impulse().0);
+        if !plotted || !self.ready.iter().all(|r| *r) {
+            return None;
+        }
+        self.ready.iter_mut().for_each(|r| *r = false);
+        self.plot = None;
+        let events = self.game.run_impulse();
+        Some(Entry::Impulse {
+            turn: self.game.turn(),
+            impulse: self.game.impulse(),
+            events,
+        })
+    }
+}
+
+// The joining player's end, with a copy of the host's game.
+pub struct Client {
+    game: Game,
+    host: Connection,
+    side: usize,
+}
+
+impl Client {
+    // Joins a hosted game and waits for the host to start it.
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<Client, NetError> {
+        let mut host = Connection::new(TcpStream::connect(addr)?)?;
+        match host.recv_timeout(WELCOME_TIMEOUT)? {
+            Some(Message::Welcome { side, game }) => Ok(Client { game, host, side }),
+            _ => Err(NetError::NoWelcome),
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn side(&self) -> usize {
+        self.side
+    }
+
+    // Sends a command to the host. What comes of it arrives by poll.
+    pub fn send(&mut self, command: Command) -> Result<Vec<Update>, NetError> {
+        self.host.send(&command)?;
+        Ok(Vec::new())
+    }
+
+    // Catches up with whatever the host has sent.
+    pub fn poll(&mut self) -> Result<Vec<Update>, NetError> {
+        let mut updates = Vec::new();
+        while let Some(message) = self.host.try_recv()? {
+            match message {
+                Message::Update(update) => updates.push(update),
+                Message::Game(game) => self.game = game,
+                Message::Welcome { .. } => (),
+            }
+        }
+        Ok(updates)
+    }
+}
+
+// Either end of a network game, for code that doesn't care which.
+pub enum Session {
+    Host(Host),
+    Client(Client),
+}
+
+impl Session {
+    pub fn game(&self) -> &Game {
+        match self {
+            Session::Host(host) => host.game(),
+            Session::Client(client) => client.game(),
+        }
+    }
+
+    // The side this end plays.
+    pub fn side(&self) -> usize {
+        match self {
+            Session::Host(host) => host.side(),
+            Session::Client(client) => client.side(),
+        }
+    }
+
+    pub fn send(&mut self, command: Command) -> Result<Vec<Update>, NetError> {
+        match self {
+            Session::Host(host) => host.send(command),
+            Session::Client(client) => client.send(command),
+        }
+    }
+
+    pub fn poll(&mut self) -> Result<Vec<Update>, NetError> {
+        match self {
+            Session::Host(host) => host.poll(),
+            Session::Client(client) => client.poll(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::combat_allocation;
+    use crate::movement::Side;
+    use crate::save;
+    use crate::scenario::Scenario;
+    use crate::seeking::{LaunchError, SeekingKind};
+    use std::time::Instant;
+
+    // A host playing the Federation and a client playing the Klingons,
+    // connected over loopback.
+    fn connect() -> (Host, Client) {
+        let game = Scenario::load("the_duel.toml").unwrap().start(3).unwrap();
+        let listener = Listener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let joining = thread::spawn(move || Client::connect(addr));
+        let host = loop {
+            if let Some(host) = listener.accept(game.clone(), 0).unwrap() {
+                break host;
+            }
+            thread::sleep(Duration::from_millis(1));
+        };
+        (host, joining.join().unwrap().unwrap())
+    }
+
+    // Polls until some updates arrive.
+    fn wait(session: &mut dyn FnMut() -> Result<Vec<Update>, NetError>) -> Vec<Update> {
+        let start = Instant::now();
+        loop {
+            let updates = session().unwrap();
+            if !updates.is_empty() {
+                return updates;
+            }
+            assert!(start.elapsed() < Duration::from_secs(5), "nothing arrived");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn plays_over_loopback() {
+        let (mut host, mut client) = connect();
+        assert_eq!((host.side(), client.side()), (0, 1));
+
+        // The host's plot stays secret until the client's is in too.
+        let mut fed = combat_allocation(&host.game().ships[0], 10);
+        fed.speed = 9;
+        assert_eq!(
+            host.send(Command::Plot(vec![(0, fed)])).unwrap(),
+            vec![Update::Plotted(0)]
+        );
+        assert_eq!(
+            wait(&mut || host.poll().and_then(|_| client.poll())),
+            vec![Update::Plotted(0)]
+        );
+        assert_eq!(client.game().ships[0].speed, 15);
+
+        let klingon = combat_allocation(&client.game().ships[1], 12);
+        client.send(Command::Plot(vec![(1, klingon)])).unwrap();
+        let revealed = wait(&mut || host.poll());
+        assert_eq!(revealed.len(), 3);
+        // The client sees its own allocation, but only the host's speed.
+        let mut seen = Vec::new();
+        while seen.len() < 3 {
+            seen.extend(wait(&mut || host.poll().and_then(|_| client.poll())));
+        }
+        assert_eq!(seen[0], Update::Plotted(1));
+        assert!(seen.contains(&Update::PlottedSpeed { ship: 0, speed: 9 }));
+        assert!(seen[1..].iter().all(|u| match u {
+            Update::Entry(Entry::Order { ship, .. }) => *ship == 1,
+            u => matches!(u, Update::PlottedSpeed { .. }),
+        }));
+        assert_eq!((client.game().ships[0].speed, client.game().ships[1].speed), (9, 12));
+        assert_eq!(client.game().ships[0].get_energy_allocation().phaser_capacitor, 0);
+        assert_ne!(host.game().ships[0].get_energy_allocation().phaser_capacitor, 0);
+
+        // The impulse runs once both players are ready.
+        assert_eq!(host.send(Command::Ready).unwrap(), Vec::new());
+        client
+            .send(Command::Order {
+                ship: 1,
+                order: Order::Turn(Side::Left),
+            })
+            .unwrap();
+        client.send(Command::Ready).unwrap();
+        let mut played = Vec::new();
+        while played.len() < 2 {
+            played.extend(wait(&mut || host.poll()));
+        }
+        assert!(matches!(played[1], Update::Entry(Entry::Impulse { impulse: 1, .. })));
+        let mut followed = Vec::new();
+        while followed.len() < 2 {
+            followed.extend(wait(&mut || host.poll().and_then(|_| client.poll())));
+        }
+        assert_eq!(followed, played);
+        // The client's copy is the host's game without the dice or the
+        // host's allocation.
+        assert_eq!(
+            save::to_string(client.game()).unwrap(),
+            save::to_string(&host.game().concealed(1)).unwrap()
+        );
+        assert_ne!(
+            save::to_string(client.game()).unwrap(),
+            save::to_string(host.game()).unwrap()
+        );
+    }
+
+    #[test]
+    fn host_checks_every_command() {
+        let (mut host, mut client) = connect();
+        client
+            .send(Command::Order {
+                ship: 0,
+                order: Order::Turn(Side::Left),
+            })
+            .unwrap();
+        assert_eq!(
+            wait(&mut || host.poll().and_then(|_| client.poll())),
+            vec![Update::Rejected(CommandError::NotYourShip(0).to_string())]
+        );
+
+        let e = combat_allocation(&client.game().ships[1], 10);
+        client
+            .send(Command::Order {
+                ship: 1,
+                order: Order::Allocate(e),
+            })
+            .unwrap();
+        assert_eq!(
+            wait(&mut || host.poll().and_then(|_| client.poll())),
+            vec![Update::Rejected(CommandError::UnplottedAllocation.to_string())]
+        );
+
+        // The rules engine has the last word on the order itself.
+        client
+            .send(Command::Order {
+                ship: 1,
+                order: Order::Fire { target: 1 },
+            })
+            .unwrap();
+        assert_eq!(
+            wait(&mut || host.poll().and_then(|_| client.poll())),
+            vec![Update::Rejected(OrderError::NotAnEnemy(1).to_string())]
+        );
+        // The D7 has nothing to launch drones from.
+        client
+            .send(Command::Order {
+                ship: 1,
+                order: Order::Launch {
+                    kind: SeekingKind::Drone,
+                    target: 0,
+                },
+            })
+            .unwrap();
+        let refused = OrderError::Launch(LaunchError::NoLauncher(SeekingKind::Drone));
+        assert_eq!(
+            wait(&mut || host.poll().and_then(|_| client.poll())),
+            vec![Update::Rejected(refused.to_string())]
+        );
+        assert!(host.game().seekers.is_empty());
+
+        assert!(matches!(
+            host.send(Command::Plot(Vec::new())),
+            Err(NetError::Rejected(CommandError::Plot(PlotError::Unplotted(0))))
+        ));
+        assert_eq!(host.game().ships[1].position, client.game().ships[1].position);
+    }
+}
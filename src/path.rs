@@ -0,0 +1,223 @@
+// Pathfinding: the shortest way for a ship to reach a hex, moving straight
+// ahead one hex at a time and turning only when its turn mode allows. Turns
+// cost no movement, so the shortest path is the one with the fewest hexes.
+
+use crate::hex::{Facing, Hex, Map, Shift};
+use crate::movement::{MovementState, Side, TurnMode};
+use crate::ship::Position;
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Step {
+    Forward,
+    Turn(Side),
+}
+
+// A planned path, with where the ship stands after each step.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Path {
+    pub steps: Vec<Step>,
+    pub positions: Vec<Position>,
+}
+
+impl Path {
+    // Hexes of movement along the path.
+    pub fn hexes(&self) -> usize {
+        self.steps.iter().filter(|s| **s == Step::Forward).count()
+    }
+
+    // What comes next for a ship at the given position on the path, or None
+    // if it's off the path or at the end.
+    pub fn next_step(&self, from: &Position, start: &Position) -> Option<Step> {
+        if from == start {
+            return self.steps.first().copied();
+        }
+        let i = self.positions.iter().position(|p| p == from)?;
+        self.steps.get(i + 1).copied()
+    }
+
+    // The same path after a floating map has shifted.
+    pub fn shift(&mut self, shift: Shift) {
+        for position in self.positions.iter_mut() {
+            position.hex = position.hex.shifted(shift);
+        }
+    }
+}
+
+// Where a ship stands during the search: its position, and how many hexes
+// it has moved since its last turn, counting no higher than its turn mode
+// requires.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+struct State {
+    col: i16,
+    row: i16,
+    facing: Facing,
+    since_turn: u8,
+}
+
+impl State {
+    fn position(&self) -> Position {
+        Position {
+            hex: Hex::new(self.col, self.row),
+            facing: self.facing,
+        }
+    }
+}
+
+// Finds the shortest path from a position to a hex, arriving with the given
+// facing if there is one. The ship's movement so far decides when it may
+// first turn. Returns None if the hex can't be reached without leaving the
+// map, or at all at speed 0. On a floating map the search doesn't stray
+// much further outside the map than the hex it's looking for.
+pub fn find(
+    map: &Map,
+    from: Position,
+    movement: &MovementState,
+    turn_mode: TurnMode,
+    speed: u8,
+    to: Hex,
+    facing: Option<Facing>,
+) -> Option<Path> {
+    let arrived = |p: &Position| p.hex == to && facing.is_none_or(|f| f == p.facing);
+    if arrived(&from) {
+        return Some(Path {
+            steps: Vec::new(),
+            positions: Vec::new(),
+        });
+    }
+    if speed == 0 {
+        return None;
+    }
+    let required = turn_mode.hexes_required(speed);
+    let start = State {
+        col: from.hex.col,
+        row: from.hex.row,
+        facing: from.facing,
+        since_turn: movement.hexes_since_turn().map_or(required, |h| h.min(required)),
+    };
+
+    let limit = from.hex.distance_to(&to) + 2 * required as i32 + 6;
+
+    // Breadth first, with the free turns searched ahead of the next hex.
+    let mut came_from: HashMap<State, (State, Step)> = HashMap::new();
+    let mut queue = VecDeque::new();
+    queue.push_back(start);
+    let mut end = None;
+    while let Some(state) = queue.pop_front() {
+        let position = state.position();
+        if arrived(&position) {
+            end = Some(state);
+            break;
+        }
+        if state.since_turn >= required {
+            for side in [Side::Left, Side::Right] {
+                let next = State {
+                    facing: side.turn(state.facing),
+                    since_turn: 0,
+                    ..state
+                };
+                if next != start && !came_from.contains_key(&next) {
+                    came_from.insert(next, (state, Step::Turn(side)));
+                    queue.push_front(next);
+                }
+            }
+        }
+        if let Some(hex) = map
+            .neighbor(&position.hex, state.facing)
+            .filter(|hex| map.is_inside(hex) || hex.distance_to(&to) <= limit)
+        {
+            let next = State {
+                col: hex.col,
+                row: hex.row,
+                facing: state.facing,
+                since_turn: (state.since_turn + 1).min(required),
+            };
+            if next != start && !came_from.contains_key(&next) {
+                came_from.insert(next, (state, Step::Forward));
+                queue.push_back(next);
+            }
+        }
+    }
+
+    let mut state = end?;
+    let mut steps = Vec::new();
+    let mut positions = Vec::new();
+    while state != start {
+        let (previous, step) = came_from[&state];
+        steps.push(step);
+        positions.push(state.position());
+        state = previous;
+    }
+    steps.reverse();
+    positions.reverse();
+    Some(Path { steps, positions })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex::{MapEdges, STANDARD_MAP};
+
+    fn position(col: i16, row: i16, facing: Facing) -> Position {
+        Position {
+            hex: Hex::new(col, row),
+            facing,
+        }
+    }
+
+    // Checks a path step by step against the movement rules.
+    fn follow(from: Position, turn_mode: TurnMode, speed: u8, path: &Path) -> Position {
+        let mut state = MovementState::new();
+        let mut at = from;
+        for (step, expected) in path.steps.iter().zip(path.positions.iter()) {
+            match step {
+                Step::Forward => {
+                    at.hex = at.hex.neighbor(at.facing);
+                    state.record_move();
+                }
+                Step::Turn(side) => at.facing = state.turn(at.facing, *side, turn_mode, speed).unwrap(),
+            }
+            assert_eq!(at, *expected);
+        }
+        at
+    }
+
+    #[test]
+    fn straight_ahead() {
+        let from = position(10, 20, Facing::A);
+        let path = find(&STANDARD_MAP, from, &MovementState::new(), TurnMode::D, 15, Hex::new(10, 15), None).unwrap();
+        assert_eq!(path.steps, vec![Step::Forward; 5]);
+        assert_eq!(path.hexes() as i32, from.hex.distance_to(&Hex::new(10, 15)));
+    }
+
+    #[test]
+    fn turn_mode_lengthens_the_path() {
+        let from = position(10, 20, Facing::A);
+        let to = Hex::new(10, 24);
+        let nimble = find(&STANDARD_MAP, from, &MovementState::new(), TurnMode::A, 8, to, Some(Facing::A)).unwrap();
+        let clumsy = find(&STANDARD_MAP, from, &MovementState::new(), TurnMode::F, 32, to, Some(Facing::A)).unwrap();
+        assert!(nimble.hexes() < clumsy.hexes());
+        assert_eq!(follow(from, TurnMode::A, 8, &nimble), position(10, 24, Facing::A));
+        assert_eq!(follow(from, TurnMode::F, 32, &clumsy), position(10, 24, Facing::A));
+        assert_eq!(nimble.next_step(&from, &from), nimble.steps.first().copied());
+    }
+
+    #[test]
+    fn stays_on_the_map() {
+        // In the corner facing off the map, every way out leaves it.
+        let to = Hex::new(0, 3);
+        let cornered = position(0, 0, Facing::A);
+        assert_eq!(find(&STANDARD_MAP, cornered, &MovementState::new(), TurnMode::D, 15, to, None), None);
+        let floating = Map {
+            edges: MapEdges::Floating,
+            ..STANDARD_MAP
+        };
+        let around = find(&floating, cornered, &MovementState::new(), TurnMode::D, 15, to, None).unwrap();
+        assert_eq!(follow(cornered, TurnMode::D, 15, &around).hex, to);
+
+        let from = position(0, 0, Facing::C);
+        let path = find(&STANDARD_MAP, from, &MovementState::new(), TurnMode::D, 15, to, None).unwrap();
+        assert_eq!(follow(from, TurnMode::D, 15, &path).hex, to);
+        assert_eq!(find(&STANDARD_MAP, from, &MovementState::new(), TurnMode::D, 0, to, None), None);
+    }
+}
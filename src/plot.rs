@@ -0,0 +1,182 @@
+// Secret plotting between turns. Each side's player allocates energy, speed
+// included, for every ship they command. Nothing is carried out until every
+// player has committed, so nobody learns a rival's speed before settling on
+// their own, and then all of it is revealed together.
+
+use crate::energy::{AllocationError, EnergyAllocation};
+use crate::game::{Game, Order};
+use std::fmt;
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum PlotError {
+    // The game is in the middle of a turn.
+    NotBetweenTurns,
+    NoSuchSide(usize),
+    AlreadyPlotted(usize),
+    // A ship that isn't the plotting side's to command.
+    NotYourShip(usize),
+    // One of the side's ships was left out, or allocated for twice.
+    Unplotted(usize),
+    Allocation { ship: usize, error: AllocationError },
+    // Revealed before the side had plotted.
+    Waiting(usize),
+}
+
+impl fmt::Display for PlotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlotError::NotBetweenTurns => write!(f, "Energy is only allocated between turns"),
+            PlotError::NoSuchSide(side) => write!(f, "There's no side {}", side),
+            PlotError::AlreadyPlotted(side) => write!(f, "Side {} has already plotted this turn", side),
+            PlotError::NotYourShip(ship) => write!(f, "Ship {} belongs to another side", ship),
+            PlotError::Unplotted(ship) => write!(f, "Ship {} needs exactly one allocation", ship),
+            PlotError::Allocation { ship, error } => write!(f, "Ship {}: {}", ship, error),
+            PlotError::Waiting(side) => write!(f, "Still waiting on side {} to plot", side),
+        }
+    }
+}
+
+// The plots for one turn, kept hidden until every side has made theirs.
+#[derive(Clone, Debug)]
+pub struct Plot {
+    // Each side's allocations, once it has committed them. Sides with nothing
+    // left in the fight have nothing to plot.
+    sides: Vec<Option<Vec<(usize, EnergyAllocation)>>>,
+}
+
+impl Plot {
+    pub fn new(game: &Game) -> Plot {
+        let sides = (0..game.sides.len())
+            .map(|side| {
+                if game.ships_of(side).is_empty() {
+                    Some(Vec::new())
+                } else {
+                    None
+                }
+            })
+            .collect();
+        Plot { sides }
+    }
+
+    // The next side that has yet to plot.
+    pub fn waiting_on(&self) -> Option<usize> {
+        self.sides.iter().position(|p| p.is_none())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.waiting_on().is_none()
+    }
+
+    pub fn has_plotted(&self, side: usize) -> bool {
+        matches!(self.sides.get(side), Some(Some(_)))
+    }
+
+    // Takes a side's allocations for all of its ships. They're checked now
+    // but not carried out until the plot is revealed.
+    pub fn commit(
+        &mut self,
+        game: &Game,
+        side: usize,
+        allocations: Vec<(usize, EnergyAllocation)>,
+    ) -> Result<(), PlotError> {
+        if !game.between_turns() {
+            return Err(PlotError::NotBetweenTurns);
+        }
+        match self.sides.get(side) {
+            None => return Err(PlotError::NoSuchSide(side)),
+            Some(Some(_)) => return Err(PlotError::AlreadyPlotted(side)),
+            Some(None) => (),
+        }
+        for (ship, e) in &allocations {
+            if *ship >= game.ships.len() || game.side_of(*ship) != side {
+                return Err(PlotError::NotYourShip(*ship));
+            }
+            e.validate().map_err(|error| PlotError::Allocation { ship: *ship, error })?;
+        }
+        let ships = game.ships_of(side);
+        let plotted: Vec<usize> = allocations.iter().map(|(ship, _)| *ship).collect();
+        if let Some(ship) = ships
+            .iter()
+            .chain(plotted.iter())
+            .find(|ship| !ships.contains(ship) || plotted.iter().filter(|p| p == ship).count() != 1)
+        {
+            return Err(PlotError::Unplotted(*ship));
+        }
+        self.sides[side] = Some(allocations);
+        Ok(())
+    }
+
+    // Every side's allocations as orders, in ship order, once they're all in.
+    pub fn reveal(&self) -> Result<Vec<(usize, Order)>, PlotError> {
+        if let Some(side) = self.waiting_on() {
+            return Err(PlotError::Waiting(side));
+        }
+        let mut orders: Vec<(usize, Order)> = self
+            .sides
+            .iter()
+            .flatten()
+            .flatten()
+            .map(|(ship, e)| (*ship, Order::Allocate(e.clone())))
+            .collect();
+        orders.sort_by_key(|(ship, _)| *ship);
+        Ok(orders)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::combat_allocation;
+    use crate::scenario::Scenario;
+
+    #[test]
+    fn hidden_until_everyone_has_plotted() {
+        let mut game = Scenario::load("the_duel.toml").unwrap().start(1).unwrap();
+        let mut plot = Plot::new(&game);
+        assert_eq!(plot.waiting_on(), Some(0));
+
+        let mut fed = combat_allocation(&game.ships[0], 10);
+        fed.speed = 7;
+        plot.commit(&game, 0, vec![(0, fed)]).unwrap();
+        assert_eq!(plot.waiting_on(), Some(1));
+        assert_eq!(plot.reveal(), Err(PlotError::Waiting(1)));
+        assert_eq!(game.ships[0].speed, 15);
+
+        let klingon = combat_allocation(&game.ships[1], 12);
+        plot.commit(&game, 1, vec![(1, klingon)]).unwrap();
+        assert!(plot.is_complete());
+        for (ship, order) in plot.reveal().unwrap() {
+            game.order(ship, &order).unwrap();
+        }
+        assert_eq!((game.ships[0].speed, game.ships[1].speed), (7, 12));
+    }
+
+    #[test]
+    fn players_plot_only_their_own_ships() {
+        let game = Scenario::load("the_duel.toml").unwrap().start(1).unwrap();
+        let mut plot = Plot::new(&game);
+        let fed = combat_allocation(&game.ships[0], 10);
+        let klingon = combat_allocation(&game.ships[1], 10);
+
+        assert_eq!(
+            plot.commit(&game, 0, vec![(1, klingon.clone())]),
+            Err(PlotError::NotYourShip(1))
+        );
+        assert_eq!(plot.commit(&game, 0, Vec::new()), Err(PlotError::Unplotted(0)));
+        assert_eq!(
+            plot.commit(&game, 0, vec![(0, fed.clone()), (0, fed.clone())]),
+            Err(PlotError::Unplotted(0))
+        );
+        assert_eq!(plot.commit(&game, 2, Vec::new()), Err(PlotError::NoSuchSide(2)));
+
+        let mut greedy = fed.clone();
+        greedy.speed = 31;
+        assert!(matches!(
+            plot.commit(&game, 0, vec![(0, greedy)]),
+            Err(PlotError::Allocation { ship: 0, .. })
+        ));
+
+        plot.commit(&game, 0, vec![(0, fed.clone())]).unwrap();
+        assert_eq!(plot.commit(&game, 0, vec![(0, fed)]), Err(PlotError::AlreadyPlotted(0)));
+    }
+}
@@ -0,0 +1,470 @@
+// Replays: an append-only log of everything done in a game, and playback of
+// it an impulse at a time in either direction.
+//
+// The log starts with the whole game as it stood, dice and all. After that
+// comes every order that was carried out and every impulse that was run,
+// each with what came of it: moves, fire with its die rolls, and damage.
+// The rules are deterministic given the dice, so playing the orders back
+// reproduces the game exactly, and the recorded events prove it.
+
+use crate::game::{Event, Game, Order, OrderError};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path;
+
+pub const REPLAY_VERSION: u32 = 2;
+
+const REPLAYS_PATH: &str = "./replays";
+
+#[derive(Serialize)]
+struct Header<'a> {
+    version: u32,
+    start: &'a Game,
+}
+
+#[derive(Deserialize)]
+struct StartHeader {
+    start: Game,
+}
+
+#[derive(Deserialize)]
+struct Version {
+    version: u32,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(tag = "entry", rename_all = "kebab-case")]
+pub enum Entry {
+    // An order carried out between impulses. Refused orders change nothing
+    // and aren't logged.
+    Order {
+        turn: u16,
+        impulse: u8,
+        ship: usize,
+        order: Order,
+        event: Option<Event>,
+    },
+    // An impulse that was run, numbered as it was run.
+    Impulse {
+        turn: u16,
+        impulse: u8,
+        events: Vec<Event>,
+    },
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum ReplayError {
+    Io { path: String, error: String },
+    // A line of the log that couldn't be read, counting from 1.
+    Invalid { line: usize, error: String },
+    Version { found: u32, supported: u32 },
+    // Playing back the entry (counting from 0) didn't turn out as logged.
+    Diverged { entry: usize },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io { path, error } => write!(f, "Couldn't use {}: {}", path, error),
+            ReplayError::Invalid { line, error } => write!(f, "Line {} of the replay is invalid: {}", line, error),
+            ReplayError::Version { found, supported } => write!(
+                f,
+                "This replay was written in format version {}; only version {} can be played",
+                found, supported
+            ),
+            ReplayError::Diverged { entry } => {
+                write!(f, "The replay no longer matches the game at entry {}", entry)
+            }
+        }
+    }
+}
+
+// A game that logs everything done to it. Writing stops at the first error,
+// which is kept for the caller to pick up.
+pub struct Recorder {
+    game: Game,
+    out: Option<Box<dyn Write>>,
+    error: Option<String>,
+}
+
+impl Recorder {
+    // Starts logging the game from where it stands. With no output it just
+    // passes things through.
+    pub fn new(game: Game, out: Option<Box<dyn Write>>) -> Recorder {
+        let mut recorder = Recorder { game, out, error: None };
+        let header = Header {
+            version: REPLAY_VERSION,
+            start: &recorder.game,
+        };
+        let line = serde_json::to_string(&header).map_err(|e| e.to_string());
+        recorder.write(line);
+        recorder
+    }
+
+    // Logs to a new file in the replays directory.
+    pub fn to_file(game: Game, file: &str) -> Result<Recorder, ReplayError> {
+        let mut path = path::PathBuf::from(REPLAYS_PATH);
+        let io_error = |path: &path::Path, err: std::io::Error| ReplayError::Io {
+            path: path.display().to_string(),
+            error: err.to_string(),
+        };
+        fs::create_dir_all(&path).map_err(|e| io_error(&path, e))?;
+        path.push(file);
+        let out = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| io_error(&path, e))?;
+        Ok(Recorder::new(game, Some(Box::new(out))))
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn into_game(self) -> Game {
+        self.game
+    }
+
+    // Why the log stopped being written, if it did.
+    pub fn take_error(&mut self) -> Option<String> {
+        self.error.take()
+    }
+
+    pub fn order(&mut self, ship: usize, order: &Order) -> Result<Option<Event>, OrderError> {
+        let event = self.game.order(ship, order)?;
+        let entry = Entry::Order {
+            turn: self.game.turn(),
+            impulse: self.game.impulse(),
+            ship,
+            order: order.clone(),
+            event: event.clone(),
+        };
+        self.append(&entry);
+        Ok(event)
+    }
+
+    pub fn run_impulse(&mut self) -> Vec<Event> {
+        let events = self.game.run_impulse();
+        let entry = Entry::Impulse {
+            turn: self.game.turn(),
+            impulse: self.game.impulse(),
+            events: events.clone(),
+        };
+        self.append(&entry);
+        events
+    }
+
+    fn append(&mut self, entry: &Entry) {
+        let line = serde_json::to_string(entry).map_err(|e| e.to_string());
+        self.write(line);
+    }
+
+    fn write(&mut self, line: Result<String, String>) {
+        let out = match self.out.as_mut() {
+            Some(out) => out,
+            None => return,
+        };
+        let written = line.and_then(|line| {
+            writeln!(out, "{}", line)
+                .and_then(|_| out.flush())
+                .map_err(|e| e.to_string())
+        });
+        if let Err(e) = written {
+            self.error = Some(format!("Stopped writing the replay: {}", e));
+            self.out = None;
+        }
+    }
+}
+
+// A replay read back from its log.
+#[derive(Clone, Debug)]
+pub struct Replay {
+    pub start: Game,
+    pub entries: Vec<Entry>,
+}
+
+impl Replay {
+    pub fn parse(s: &str) -> Result<Replay, ReplayError> {
+        let mut lines = s.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+        let invalid = |line: usize, e: serde_json::Error| ReplayError::Invalid {
+            line: line + 1,
+            error: e.to_string(),
+        };
+        let (n, header) = lines.next().ok_or(ReplayError::Invalid {
+            line: 1,
+            error: String::from("the replay is empty"),
+        })?;
+        let version: Version = serde_json::from_str(header).map_err(|e| invalid(n, e))?;
+        if version.version != REPLAY_VERSION {
+            return Err(ReplayError::Version {
+                found: version.version,
+                supported: REPLAY_VERSION,
+            });
+        }
+        let header: StartHeader = serde_json::from_str(header).map_err(|e| invalid(n, e))?;
+        let mut entries = Vec::new();
+        for (n, line) in lines {
+            entries.push(serde_json::from_str(line).map_err(|e| invalid(n, e))?);
+        }
+        Ok(Replay {
+            start: header.start,
+            entries,
+        })
+    }
+
+    pub fn load(file: &str) -> Result<Replay, ReplayError> {
+        let mut path = path::PathBuf::from(REPLAYS_PATH);
+        path.push(file);
+        let s = fs::read_to_string(&path).map_err(|err| ReplayError::Io {
+            path: path.display().to_string(),
+            error: err.to_string(),
+        })?;
+        Replay::parse(&s)
+    }
+}
+
+// Steps through a replay an impulse at a time. Stepping back plays the log
+// again from the start, so every state shown is one the rules produced.
+pub struct Playback {
+    replay: Replay,
+    game: Game,
+    // How many entries have been played.
+    next: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Playback {
+        let game = replay.start.clone();
+        Playback { replay, game, next: 0 }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn at_start(&self) -> bool {
+        self.next == 0
+    }
+
+    pub fn at_end(&self) -> bool {
+        self.next == self.replay.entries.len()
+    }
+
+    // Plays the orders given before the next impulse, then the impulse.
+    pub fn step_forward(&mut self) -> Result<(), ReplayError> {
+        while !self.at_end() {
+            let impulse = matches!(self.replay.entries[self.next], Entry::Impulse { .. });
+            self.play_next()?;
+            if impulse {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // Goes back to just after the impulse before the last one played.
+    pub fn step_back(&mut self) -> Result<(), ReplayError> {
+        let entries = &self.replay.entries;
+        let after_impulse = |i: usize| i == 0 || matches!(entries[i - 1], Entry::Impulse { .. });
+        let target = (0..self.next).rev().find(|i| after_impulse(*i)).unwrap_or(0);
+        self.game = self.replay.start.clone();
+        self.next = 0;
+        while self.next < target {
+            self.play_next()?;
+        }
+        Ok(())
+    }
+
+    pub fn play_to_end(&mut self) -> Result<(), ReplayError> {
+        while !self.at_end() {
+            self.play_next()?;
+        }
+        Ok(())
+    }
+
+    fn play_next(&mut self) -> Result<(), ReplayError> {
+        if !apply(&mut self.game, &self.replay.entries[self.next]) {
+            return Err(ReplayError::Diverged { entry: self.next });
+        }
+        self.next += 1;
+        Ok(())
+    }
+}
+
+// Carries out a logged entry, returning whether it came out as logged.
+pub fn apply(game: &mut Game, entry: &Entry) -> bool {
+    match entry {
+        Entry::Order {
+            turn,
+            impulse,
+            ship,
+            order,
+            event,
+        } => (*turn, *impulse) == (game.turn(), game.impulse()) && game.order(*ship, order).ok().as_ref() == Some(event),
+        Entry::Impulse { turn, impulse, events } => {
+            let played = game.run_impulse();
+            (*turn, *impulse) == (game.turn(), game.impulse()) && played == *events
+        }
+    }
+}
+
+// A file name for a game's replay, told apart from others by a timestamp.
+pub fn file_name(game: &Game, stamp: u64) -> String {
+    let sides: Vec<String> = game
+        .sides
+        .iter()
+        .map(|s| s.to_lowercase().replace(' ', "-"))
+        .collect();
+    format!("{}-{}.jsonl", sides.join("-vs-"), stamp)
+}
+
+// The replays on disk, in alphabetical order.
+pub fn list() -> Vec<String> {
+    let mut files: Vec<String> = fs::read_dir(REPLAYS_PATH)
+        .map(|dir| {
+            dir.filter_map(|entry| entry.ok())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .filter(|name| name.ends_with(".jsonl"))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::{Brawler, Controller};
+    use crate::save;
+    use crate::scenario::Scenario;
+    use crate::seeking::SeekingKind;
+    use crate::ship::Ship;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    // Collects what's written so the test can read it back.
+    #[derive(Clone, Default)]
+    struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn record(impulses: usize) -> (Recorder, Buffer) {
+        let mut game = Scenario::load("the_duel.toml").unwrap().start(9).unwrap();
+        // A Kzinti BC in the D7's place, so there are drones to log.
+        let position = game.ships[1].position;
+        game.ships[1] = Ship::new("kzinti/bc.toml", position, 15);
+        let buffer = Buffer::default();
+        let mut recorder = Recorder::new(game, Some(Box::new(buffer.clone())));
+        let mut brawler = Brawler { fire_range: 10 };
+        recorder
+            .order(
+                1,
+                &Order::Launch {
+                    kind: SeekingKind::Drone,
+                    target: 0,
+                },
+            )
+            .unwrap();
+        for _ in 0..impulses {
+            for i in 0..2 {
+                if recorder.game().between_turns() {
+                    let e = brawler.allocate(recorder.game(), i);
+                    recorder.order(i, &Order::Allocate(e)).unwrap();
+                }
+                for order in brawler.orders(recorder.game(), i) {
+                    let _ = recorder.order(i, &order);
+                }
+            }
+            recorder.run_impulse();
+        }
+        (recorder, buffer)
+    }
+
+    fn log(buffer: &Buffer) -> String {
+        String::from_utf8(buffer.0.borrow().clone()).unwrap()
+    }
+
+    #[test]
+    fn plays_back_exactly() {
+        let (recorder, buffer) = record(200);
+        let replay = Replay::parse(&log(&buffer)).unwrap();
+        assert!(replay
+            .entries
+            .iter()
+            .any(|e| matches!(e, Entry::Order { event: Some(Event::Fired(_)), .. })));
+
+        let mut playback = Playback::new(replay);
+        playback.play_to_end().unwrap();
+        assert_eq!(
+            save::to_string(playback.game()).unwrap(),
+            save::to_string(recorder.game()).unwrap()
+        );
+    }
+
+    #[test]
+    fn steps_both_ways() {
+        let (_, buffer) = record(40);
+        let mut playback = Playback::new(Replay::parse(&log(&buffer)).unwrap());
+        assert!(playback.at_start());
+        for _ in 0..3 {
+            playback.step_forward().unwrap();
+        }
+        assert_eq!((playback.game().turn(), playback.game().impulse()), (1, 3));
+        let at_three = save::to_string(playback.game()).unwrap();
+        playback.step_forward().unwrap();
+        playback.step_back().unwrap();
+        assert_eq!(save::to_string(playback.game()).unwrap(), at_three);
+        for _ in 0..3 {
+            playback.step_back().unwrap();
+        }
+        assert!(playback.at_start());
+        while !playback.at_end() {
+            playback.step_forward().unwrap();
+        }
+        assert_eq!((playback.game().turn(), playback.game().impulse()), (2, 8));
+    }
+
+    #[test]
+    fn catches_a_tampered_log() {
+        let (_, buffer) = record(40);
+        let mut replay = Replay::parse(&log(&buffer)).unwrap();
+        // Nudge the dice: the same orders no longer give the logged results.
+        let mut start = serde_json::to_value(&replay.start).unwrap();
+        start["dice"]["state"] = serde_json::json!(12345);
+        replay.start = serde_json::from_value(start).unwrap();
+        let entry = replay
+            .entries
+            .iter()
+            .position(|e| matches!(e, Entry::Order { event: Some(Event::Fired(_)), .. }))
+            .unwrap();
+        let mut playback = Playback::new(replay);
+        assert_eq!(playback.play_to_end(), Err(ReplayError::Diverged { entry }));
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let (_, buffer) = record(1);
+        let version = format!("{{\"version\":{}", REPLAY_VERSION);
+        let old = log(&buffer).replacen(&version, "{\"version\":0", 1);
+        assert_eq!(
+            Replay::parse(&old).unwrap_err(),
+            ReplayError::Version {
+                found: 0,
+                supported: REPLAY_VERSION
+            }
+        );
+        assert!(matches!(Replay::parse(""), Err(ReplayError::Invalid { line: 1, .. })));
+    }
+}
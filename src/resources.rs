@@ -0,0 +1,37 @@
+// Where the game's data files are found. Under cargo, or run straight from
+// the build directory, that's the source tree's resources whatever directory
+// it's started from; an installed copy looks beside the executable, and
+// failing all of those, in the current directory.
+
+use std::env;
+use std::path::PathBuf;
+
+pub fn dir() -> PathBuf {
+    let roots = [
+        env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from),
+        env::current_exe().ok().and_then(|exe| exe.parent().map(PathBuf::from)),
+        Some(PathBuf::from(env!("CARGO_MANIFEST_DIR"))),
+    ];
+    roots
+        .iter()
+        .flatten()
+        .map(|root| root.join("resources"))
+        .find(|dir| dir.is_dir())
+        .unwrap_or_else(|| PathBuf::from("./resources"))
+}
+
+// One of the subdirectories, such as "ship_specs".
+pub fn path(subdir: &str) -> PathBuf {
+    dir().join(subdir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn found_from_anywhere() {
+        assert!(dir().is_absolute());
+        assert!(path("ship_specs").join("federation/ca.toml").is_file());
+    }
+}
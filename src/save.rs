@@ -0,0 +1,184 @@
+// Saved games: everything about a game in progress, down to the state of the
+// dice, written out as JSON. Each file carries a version number so a save
+// from an incompatible build is turned away instead of misread.
+
+use crate::game::Game;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path;
+
+pub const SAVE_VERSION: u32 = 2;
+
+const SAVES_PATH: &str = "./saves";
+
+#[derive(Serialize)]
+struct SaveFile<'a> {
+    version: u32,
+    game: &'a Game,
+}
+
+#[derive(Deserialize)]
+struct LoadFile {
+    game: Game,
+}
+
+#[derive(Deserialize)]
+struct Version {
+    version: u32,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum SaveError {
+    Io { path: String, error: String },
+    Invalid(String),
+    Version { found: u32, supported: u32 },
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io { path, error } => write!(f, "Couldn't use {}: {}", path, error),
+            SaveError::Invalid(error) => write!(f, "Not a saved game: {}", error),
+            SaveError::Version { found, supported } => write!(
+                f,
+                "This game was saved in format version {}; only version {} can be loaded",
+                found, supported
+            ),
+        }
+    }
+}
+
+pub fn to_string(game: &Game) -> Result<String, SaveError> {
+    let file = SaveFile {
+        version: SAVE_VERSION,
+        game,
+    };
+    serde_json::to_string_pretty(&file).map_err(|e| SaveError::Invalid(e.to_string()))
+}
+
+pub fn from_str(s: &str) -> Result<Game, SaveError> {
+    let version: Version = serde_json::from_str(s).map_err(|e| SaveError::Invalid(e.to_string()))?;
+    if version.version != SAVE_VERSION {
+        return Err(SaveError::Version {
+            found: version.version,
+            supported: SAVE_VERSION,
+        });
+    }
+    let file: LoadFile = serde_json::from_str(s).map_err(|e| SaveError::Invalid(e.to_string()))?;
+    if let Some(hex) = file.game.off_map() {
+        return Err(SaveError::Invalid(format!("hex {} is off the map", hex)));
+    }
+    Ok(file.game)
+}
+
+// A file name for saving the game at its current impulse.
+pub fn file_name(game: &Game) -> String {
+    let sides: Vec<String> = game
+        .sides
+        .iter()
+        .map(|s| s.to_lowercase().replace(' ', "-"))
+        .collect();
+    format!(
+        "{}-turn-{}-impulse-{}.json",
+        sides.join("-vs-"),
+        game.turn(),
+        game.impulse()
+    )
+}
+
+// Writes the game to the saves directory, returning where it went.
+pub fn save(game: &Game, file: &str) -> Result<String, SaveError> {
+    let mut path = path::PathBuf::from(SAVES_PATH);
+    let io_error = |path: &path::Path, err: std::io::Error| SaveError::Io {
+        path: path.display().to_string(),
+        error: err.to_string(),
+    };
+    fs::create_dir_all(&path).map_err(|e| io_error(&path, e))?;
+    path.push(file);
+    fs::write(&path, to_string(game)?).map_err(|e| io_error(&path, e))?;
+    Ok(path.display().to_string())
+}
+
+pub fn load(file: &str) -> Result<Game, SaveError> {
+    let mut path = path::PathBuf::from(SAVES_PATH);
+    path.push(file);
+    let s = fs::read_to_string(&path).map_err(|err| SaveError::Io {
+        path: path.display().to_string(),
+        error: err.to_string(),
+    })?;
+    from_str(&s)
+}
+
+// The saved games, newest name last.
+pub fn list() -> Vec<String> {
+    let mut files: Vec<String> = fs::read_dir(SAVES_PATH)
+        .map(|dir| {
+            dir.filter_map(|entry| entry.ok())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .filter(|name| name.ends_with(".json"))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::{Brawler, Controller};
+    use crate::game::Order;
+    use crate::scenario::Scenario;
+
+    // Plays impulses with both ships under brawlers.
+    fn play(game: &mut Game, impulses: usize) {
+        let mut brawler = Brawler { fire_range: 10 };
+        for _ in 0..impulses {
+            for i in 0..game.ships.len() {
+                if game.between_turns() {
+                    let e = brawler.allocate(game, i);
+                    game.order(i, &Order::Allocate(e)).unwrap();
+                }
+                for order in brawler.orders(game, i) {
+                    let _ = game.order(i, &order);
+                }
+            }
+            game.run_impulse();
+        }
+    }
+
+    #[test]
+    fn resumes_where_it_left_off() {
+        let mut game = Scenario::load("the_duel.toml").unwrap().start(5).unwrap();
+        play(&mut game, 150);
+        let saved = to_string(&game).unwrap();
+        let mut loaded = from_str(&saved).unwrap();
+        assert_eq!((loaded.turn(), loaded.impulse()), (game.turn(), game.impulse()));
+        assert_eq!(to_string(&loaded).unwrap(), saved);
+
+        // Same dice, same energy and arming: the two games play on identically.
+        play(&mut game, 100);
+        play(&mut loaded, 100);
+        assert_eq!(to_string(&loaded).unwrap(), to_string(&game).unwrap());
+        assert_eq!(file_name(&game), "federation-vs-klingons-turn-8-impulse-26.json");
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let game = Scenario::load("the_duel.toml").unwrap().start(5).unwrap();
+        let version = format!("\"version\": {}", SAVE_VERSION);
+        let saved = to_string(&game).unwrap().replacen(&version, "\"version\": 99", 1);
+        assert_eq!(
+            from_str(&saved).unwrap_err(),
+            SaveError::Version {
+                found: 99,
+                supported: SAVE_VERSION
+            }
+        );
+        assert!(matches!(from_str("{}"), Err(SaveError::Invalid(_))));
+
+        let off_map = to_string(&game).unwrap().replacen("\"col\": 6", "\"col\": 90", 1);
+        assert!(matches!(from_str(&off_map), Err(SaveError::Invalid(e)) if e.contains("off the map")));
+    }
+}
@@ -0,0 +1,307 @@
+// Scenarios: who's fighting, where they start and how the game is won, read
+// from TOML files in resources/scenarios.
+
+use crate::game::{Game, Rules, Victory};
+use crate::hex::{Facing, Map};
+use crate::impulse::MAX_SPEED;
+use crate::resources;
+use crate::ship::{Position, Ship};
+use crate::ship_spec::{ShipSpec, SpecError};
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+
+const SCENARIOS_DIR: &str = "scenarios";
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub turn_limit: Option<u16>,
+    // The standard map unless the scenario says otherwise.
+    #[serde(default)]
+    pub map: Map,
+    pub victory: Vec<Victory>,
+    pub sides: Vec<SideSetup>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SideSetup {
+    pub name: String,
+    pub ships: Vec<ShipSetup>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShipSetup {
+    // Relative to resources/ship_specs.
+    pub spec: String,
+    pub col: i16,
+    pub row: i16,
+    pub facing: Facing,
+    pub speed: u8,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum ScenarioError {
+    // The file couldn't be read.
+    Io { path: String, error: String },
+    Invalid { file: String, error: String },
+    // A scenario needs at least two sides, each with a ship.
+    TooFewSides,
+    NoShips { side: String },
+    DuplicateSide(String),
+    OffMap { spec: String, col: i16, row: i16 },
+    // The map has no hexes.
+    EmptyMap,
+    TooFast { spec: String, speed: u8 },
+    // A victory condition names a side that isn't in the scenario.
+    UnknownSide(String),
+    Spec(SpecError),
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioError::Io { path, error } => write!(f, "Failed to open {}: {}", path, error),
+            ScenarioError::Invalid { file, error } => write!(f, "Invalid scenario '{}': {}", file, error),
+            ScenarioError::TooFewSides => write!(f, "A scenario needs at least two sides"),
+            ScenarioError::NoShips { side } => write!(f, "Side '{}' has no ships", side),
+            ScenarioError::DuplicateSide(side) => write!(f, "There's more than one side named '{}'", side),
+            ScenarioError::OffMap { spec, col, row } => {
+                write!(f, "The {} starts off the map at column {}, row {}", spec, col, row)
+            }
+            ScenarioError::EmptyMap => write!(f, "The map needs at least one column and one row"),
+            ScenarioError::TooFast { spec, speed } => write!(
+                f,
+                "The {} starts at speed {}; the most is {}",
+                spec, speed, MAX_SPEED
+            ),
+            ScenarioError::UnknownSide(side) => {
+                write!(f, "A victory condition names side '{}', which isn't in the scenario", side)
+            }
+            ScenarioError::Spec(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Scenario {
+    // Reads a scenario file from resources/scenarios.
+    pub fn load(file: &str) -> Result<Scenario, ScenarioError> {
+        let mut path = resources::path(SCENARIOS_DIR);
+        path.push(file);
+        let s = fs::read_to_string(&path).map_err(|err| ScenarioError::Io {
+            path: path.display().to_string(),
+            error: err.to_string(),
+        })?;
+        Scenario::parse(file, &s)
+    }
+
+    // Parses and checks a scenario; the file name is only for messages.
+    pub fn parse(file: &str, s: &str) -> Result<Scenario, ScenarioError> {
+        let scenario: Scenario = toml::from_str(s).map_err(|err| ScenarioError::Invalid {
+            file: file.to_string(),
+            error: err.to_string(),
+        })?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    fn validate(&self) -> Result<(), ScenarioError> {
+        if self.sides.len() < 2 {
+            return Err(ScenarioError::TooFewSides);
+        }
+        if self.map.cols < 1 || self.map.rows < 1 {
+            return Err(ScenarioError::EmptyMap);
+        }
+        for (i, side) in self.sides.iter().enumerate() {
+            if self.sides[..i].iter().any(|s| s.name == side.name) {
+                return Err(ScenarioError::DuplicateSide(side.name.clone()));
+            }
+            if side.ships.is_empty() {
+                return Err(ScenarioError::NoShips {
+                    side: side.name.clone(),
+                });
+            }
+            for ship in &side.ships {
+                ship.position(&self.map)?;
+                if ship.speed > MAX_SPEED {
+                    return Err(ScenarioError::TooFast {
+                        spec: ship.spec.clone(),
+                        speed: ship.speed,
+                    });
+                }
+            }
+        }
+        for victory in &self.victory {
+            if let Victory::Survive { side } = victory {
+                if !self.sides.iter().any(|s| s.name == *side) {
+                    return Err(ScenarioError::UnknownSide(side.clone()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn rules(&self) -> Rules {
+        Rules {
+            turn_limit: self.turn_limit,
+            map: self.map,
+            victory: self.victory.clone(),
+        }
+    }
+
+    // Sets up a new game, loading each ship's spec.
+    pub fn start(&self, seed: u64) -> Result<Game, ScenarioError> {
+        let mut sides = Vec::new();
+        for side in &self.sides {
+            let mut ships = Vec::new();
+            for ship in &side.ships {
+                let spec = ShipSpec::load(&ship.spec).map_err(ScenarioError::Spec)?;
+                ships.push(Ship::from_spec(spec, ship.position(&self.map)?, ship.speed));
+            }
+            sides.push((side.name.clone(), ships));
+        }
+        Ok(Game::with_sides(sides, self.rules(), seed))
+    }
+}
+
+impl ShipSetup {
+    pub fn position(&self, map: &Map) -> Result<Position, ScenarioError> {
+        let hex = map.hex(self.col, self.row).ok_or_else(|| ScenarioError::OffMap {
+            spec: self.spec.clone(),
+            col: self.col,
+            row: self.row,
+        })?;
+        Ok(Position {
+            hex,
+            facing: self.facing,
+        })
+    }
+}
+
+// The scenario files available, by name, in alphabetical order.
+pub fn list() -> Vec<String> {
+    let mut files: Vec<String> = fs::read_dir(resources::path(SCENARIOS_DIR))
+        .map(|dir| {
+            dir.filter_map(|entry| entry.ok())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .filter(|name| name.ends_with(".toml"))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Outcome;
+    use crate::hex::{Hex, MapEdges, STANDARD_MAP};
+
+    const SCENARIO: &str = r#"
+name = "Test"
+turn_limit = 10
+victory = [{ condition = "destroy-enemy" }]
+
+[[sides]]
+name = "Federation"
+[[sides.ships]]
+spec = "federation/ca.toml"
+col = 6
+row = 29
+facing = "A"
+speed = 15
+
+[[sides]]
+name = "Klingons"
+[[sides.ships]]
+spec = "klingon/d7.toml"
+col = 41
+row = 2
+facing = "E"
+speed = 15
+"#;
+
+    #[test]
+    fn the_duel() {
+        let scenario = Scenario::load("the_duel.toml").unwrap();
+        assert_eq!(scenario.map, STANDARD_MAP);
+        let game = scenario.start(1).unwrap();
+        assert_eq!(game.ships.len(), 2);
+        assert_eq!(game.ships[0].position.hex, Hex::new(6, 29));
+        assert_eq!(game.ships[1].position.facing, Facing::E);
+        assert!(game.are_enemies(0, 1));
+        assert_eq!(game.outcome(), None);
+        assert!(list().contains(&String::from("the_duel.toml")));
+    }
+
+    #[test]
+    fn sides_and_rules() {
+        let game = Scenario::parse("test.toml", SCENARIO).unwrap().start(1).unwrap();
+        assert_eq!(game.sides, vec!["Federation", "Klingons"]);
+        assert_eq!(game.side_of(1), 1);
+        assert_eq!(game.rules.turn_limit, Some(10));
+        assert_ne!(game.outcome(), Some(Outcome::Draw));
+    }
+
+    #[test]
+    fn helpful_errors() {
+        let error = |from: &str, to: &str| Scenario::parse("test.toml", &SCENARIO.replacen(from, to, 1)).unwrap_err();
+        assert_eq!(
+            error("col = 6", "col = 60"),
+            ScenarioError::OffMap {
+                spec: String::from("federation/ca.toml"),
+                col: 60,
+                row: 29
+            }
+        );
+        assert_eq!(
+            error("speed = 15", "speed = 40"),
+            ScenarioError::TooFast {
+                spec: String::from("federation/ca.toml"),
+                speed: 40
+            }
+        );
+        assert_eq!(error("Klingons", "Federation"), ScenarioError::DuplicateSide(String::from("Federation")));
+        assert_eq!(
+            error("\"destroy-enemy\" }", "\"survive\", side = \"Romulans\" }"),
+            ScenarioError::UnknownSide(String::from("Romulans"))
+        );
+        match error("facing = \"A\"", "facing = \"G\"") {
+            ScenarioError::Invalid { file, .. } => assert_eq!(file, "test.toml"),
+            e => panic!("unexpected error {:?}", e),
+        }
+
+        let scenario = Scenario::parse("test.toml", &SCENARIO.replace("klingon/d7", "klingon/d8")).unwrap();
+        match scenario.start(1) {
+            Err(ScenarioError::Spec(SpecError::Io { .. })) => (),
+            _ => panic!("started with a missing ship spec"),
+        }
+        assert!(matches!(Scenario::load("nope.toml"), Err(ScenarioError::Io { .. })));
+    }
+
+    #[test]
+    fn map_from_the_scenario() {
+        let with_map = |map: &str, col: &str| {
+            let s = SCENARIO
+                .replacen("[[sides]]", &format!("[map]\n{}\n\n[[sides]]", map), 1)
+                .replacen("col = 6", col, 1);
+            Scenario::parse("test.toml", &s)
+        };
+        let big = with_map("cols = 120\nrows = 40", "col = 104").unwrap();
+        assert_eq!((big.map.cols, big.map.rows, big.map.edges), (120, 40, MapEdges::Fixed));
+        let game = big.start(1).unwrap();
+        assert_eq!(game.rules.map, big.map);
+        assert_eq!(game.ships[0].position.hex.to_string(), "105.30");
+
+        assert!(with_map("cols = 100", "col = 104").is_err());
+        let floating = with_map("edges = \"floating\"", "col = -20").unwrap();
+        assert_eq!((floating.map.cols, floating.map.edges), (60, MapEdges::Floating));
+        assert_eq!(with_map("rows = 0", "col = 6").unwrap_err(), ScenarioError::EmptyMap);
+    }
+}
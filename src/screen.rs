@@ -1,23 +1,43 @@
+mod course;
 mod imgui_wrapper;
 
 pub mod energy_allocation_window;
 pub mod main_menu;
 pub mod map;
+mod seeker;
+mod sprite;
 
-use crate::hex::{Facing, Hex};
-use crate::ship::{Position, Ship};
 use ggez::conf::{WindowMode, WindowSetup};
 use ggez::event::{KeyCode, KeyMods, MouseButton};
 use ggez::nalgebra::Point2;
+use course::Course;
 use ggez::*;
 use imgui_wrapper::ImGuiWrapper;
 use map::MapState;
-use std::env;
-use std::path;
+use main_menu::MenuAction;
+use sfb::game::{Event, Game, Order};
+use sfb::hex::{Facing, Hex};
+use sfb::ai::Ai;
+use sfb::combat::Target;
+use sfb::controller::{self, Controller};
+use sfb::energy::EnergyAllocation;
+use sfb::movement::Side;
+use sfb::net::{self, Client, Command, Listener, NetError, Session, Update};
+use sfb::plot::Plot;
+use sfb::replay::{self, Entry, Playback, Recorder, Replay};
+use sfb::resources;
+use sfb::save;
+use sfb::scenario::Scenario;
+use sfb::seeking::SeekingKind;
+use sfb::ship::Position;
+use sprite::Sprite;
+use std::time::SystemTime;
 
 pub const MENU_HEIGHT: f32 = 20.0;
 pub const WINDOW_HEIGHT: f32 = MENU_HEIGHT + 800.0; // Laptop
 //const WINDOW_HEIGHT: f32 = MENU_HEIGHT + 1300.0; // Desktop
+// Maps wider than the standard one are drawn smaller to fit.
+const MAX_WINDOW_WIDTH: f32 = 1400.0;
 
 struct GameState {
     imgui_wrapper: ImGuiWrapper,
@@ -25,25 +45,61 @@ struct GameState {
     mouse_down: bool,
     map_state: MapState,
     map_mesh: graphics::Mesh,
-    ships: Vec<Box<Ship>>,
-    // actors: Vec<Box<dyn Actor>>,
+    // The game being played, logged for replay as it goes.
+    recorder: Recorder,
+    // A recorded game being watched in place of the one in progress.
+    playback: Option<Playback>,
+    // Where the game came from, for starting it over.
+    scenario_file: String,
+    // One for each ship, in the same order.
+    sprites: Vec<Sprite>,
+    running: bool,
+    // The ship that takes orders from the keyboard.
+    selected: usize,
+    // The coming turn's secret plots while the players take turns making
+    // them, the ships the player at the device has to plot and what they've
+    // plotted so far.
+    plot: Option<Plot>,
+    plotting: Vec<usize>,
+    plotted: Vec<(usize, EnergyAllocation)>,
+    // The last turn whose plots are done with here.
+    plotted_turn: Option<u16>,
+    // A network game in place of the local one, or a hosted one waiting for
+    // the other player to join, and whether we're waiting on the other
+    // player to run the next impulse.
+    net: Option<Session>,
+    listener: Option<Listener>,
+    ready_sent: bool,
+    // The computer opponent, which plays the last side.
+    computer: Option<Ai>,
+    // A path for one of the player's ships, planned on the map.
+    course: Option<Course>,
 }
 
-// A thing that can be drawn on the map.
-//pub trait Actor {
-//    fn draw(&mut self, ctx: &mut Context, map_state: &MapState) -> GameResult<()>;
-//    fn move_to(&mut self, new_position: Position);
-//}
-
-pub fn run() -> GameResult<()> {
-    let resource_dir = if let Ok(manifest_dir) = env::var("CARGO_MANIFEST_DIR") {
-        let mut path = path::PathBuf::from(manifest_dir);
-        path.push("resources");
-        path
-    } else {
-        path::PathBuf::from("./resources")
-    };
-    let map_state = map::init(Point2::new(0.0, MENU_HEIGHT), WINDOW_HEIGHT - MENU_HEIGHT);
+// A unit that's drawn on the map and moves around it.
+pub trait Actor {
+    fn draw(&mut self, ctx: &mut Context, map_state: &MapState) -> GameResult<()>;
+    fn move_to(&mut self, new_position: Position);
+    // Whether it's still animating its way to a new position.
+    fn is_moving(&self) -> bool;
+    // Forgets anything computed from the map's screen layout.
+    fn invalidate(&mut self);
+}
+
+// Opens the game window on the given scenario, from resources/scenarios.
+pub fn run(scenario_file: &str) -> GameResult<()> {
+    let scenario = Scenario::load(scenario_file).map_err(|e| GameError::ResourceLoadError(e.to_string()))?;
+    let game = scenario
+        .start(new_seed())
+        .map_err(|e| GameError::ResourceLoadError(e.to_string()))?;
+
+    let resource_dir = resources::dir();
+    let map_state = map::init(
+        Point2::new(0.0, MENU_HEIGHT),
+        MAX_WINDOW_WIDTH,
+        WINDOW_HEIGHT - MENU_HEIGHT,
+        &game.rules.map,
+    );
 
     let (ref mut ctx, ref mut event_loop) = ContextBuilder::new("sfbv1", "ian_olsen")
         .add_resource_path(resource_dir)
@@ -60,41 +116,661 @@ pub fn run() -> GameResult<()> {
 
     let map_mesh = map::build_mesh(ctx, &map_state)?;
 
-    // "The Duel"
-    let ca: Box<Ship> = Box::new(Ship::new(
-        ctx,
-        "federation/ca.toml",
-        Position {
-            hex: Hex::new(6, 29).unwrap(),
-            facing: Facing::A,
-        },
-        15,
-    ));
-    let d7: Box<Ship> = Box::new(Ship::new(
-        ctx,
-        "klingon/d7.toml",
-        Position {
-            hex: Hex::new(41, 2).unwrap(),
-            facing: Facing::E,
-        },
-        15,
-    ));
-    let ships = vec![ca, d7];
-
+    let sprites = sprites(ctx, &game);
+    let mut imgui_wrapper = ImGuiWrapper::new(ctx);
+    let recorder = record(game, &mut imgui_wrapper);
     let state = &mut GameState {
-        imgui_wrapper: ImGuiWrapper::new(ctx),
+        imgui_wrapper,
         hidpi_factor,
         mouse_down: false,
         map_state,
         map_mesh,
-        ships,
+        recorder,
+        playback: None,
+        scenario_file: scenario_file.to_string(),
+        sprites,
+        running: false,
+        selected: 0,
+        plot: None,
+        plotting: Vec::new(),
+        plotted: Vec::new(),
+        plotted_turn: None,
+        net: None,
+        listener: None,
+        ready_sent: false,
+        computer: None,
+        course: None,
     };
 
     event::run(ctx, event_loop, state)
 }
 
+fn new_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// Starts logging a game to the replays directory. If the log can't be opened
+// the game goes on unrecorded.
+fn record(game: Game, imgui_wrapper: &mut ImGuiWrapper) -> Recorder {
+    let file = replay::file_name(&game, new_seed());
+    let recorder = match Recorder::to_file(game.clone(), &file) {
+        Ok(recorder) => recorder,
+        Err(e) => {
+            imgui_wrapper.show_message(format!("{}; this game won't be recorded", e));
+            Recorder::new(game, None)
+        }
+    };
+    imgui_wrapper.refresh_files();
+    recorder
+}
+
+// The game being played: across the network if there's a session, otherwise
+// the local one.
+fn in_play<'a>(net: &'a Option<Session>, recorder: &'a Recorder) -> &'a Game {
+    match net {
+        Some(session) => session.game(),
+        None => recorder.game(),
+    }
+}
+
+fn sprites(ctx: &mut Context, game: &Game) -> Vec<Sprite> {
+    game.ships
+        .iter()
+        .map(|s| Sprite::new(ctx, &s.spec().fx.image, s.position))
+        .collect()
+}
+
+impl GameState {
+    // Replaces the game in progress with a new one from a scenario file. A bad
+    // scenario leaves the current game alone.
+    fn open_scenario(&mut self, ctx: &mut Context, file: &str) {
+        match Scenario::load(file).and_then(|s| s.start(new_seed())) {
+            Ok(game) => {
+                self.scenario_file = file.to_string();
+                self.replace_game(ctx, game);
+            }
+            Err(e) => self.imgui_wrapper.show_message(e.to_string()),
+        }
+    }
+
+    fn replace_game(&mut self, ctx: &mut Context, game: Game) {
+        self.sprites = sprites(ctx, &game);
+        self.recorder = record(game, &mut self.imgui_wrapper);
+        self.playback = None;
+        self.running = false;
+        self.selected = 0;
+        self.plotted_turn = None;
+        self.net = None;
+        self.listener = None;
+        self.course = None;
+        self.abandon_plot();
+    }
+
+    // The game on screen: a replay if one is being watched, otherwise the one
+    // in progress.
+    fn game(&self) -> &Game {
+        match &self.playback {
+            Some(playback) => playback.game(),
+            None => self.game_in_play(),
+        }
+    }
+
+    // Fits the map on screen, for a new window size or a game on a map of a
+    // different size.
+    fn lay_out_map(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        let map = self.game().rules.map;
+        self.map_state = map::init(Point2::new(0.0, MENU_HEIGHT), width, height - MENU_HEIGHT, &map);
+        self.map_mesh = map::build_mesh(ctx, &self.map_state).unwrap();
+
+        for sprite in self.sprites.iter_mut() {
+            sprite.invalidate();
+        }
+    }
+
+    fn game_in_play(&self) -> &Game {
+        in_play(&self.net, &self.recorder)
+    }
+
+    // Waits for another player to join the game in progress. The host plays
+    // the first side.
+    fn host(&mut self) {
+        match Listener::bind(("0.0.0.0", net::DEFAULT_PORT)) {
+            Ok(listener) => {
+                self.listener = Some(listener);
+                self.running = false;
+                self.imgui_wrapper.show_message(format!(
+                    "Waiting for the other player to join on port {}",
+                    net::DEFAULT_PORT
+                ));
+            }
+            Err(e) => self.imgui_wrapper.show_message(e.to_string()),
+        }
+    }
+
+    fn accept(&mut self) {
+        let listener = match &self.listener {
+            Some(listener) => listener,
+            None => return,
+        };
+        match listener.accept(self.recorder.game().clone(), 0) {
+            Ok(Some(host)) => self.start_network_game(Session::Host(host)),
+            Ok(None) => (),
+            Err(e) => {
+                self.listener = None;
+                self.imgui_wrapper.show_message(e.to_string());
+            }
+        }
+    }
+
+    fn join(&mut self, ctx: &mut Context, addr: &str) {
+        let addr = if addr.contains(':') {
+            addr.to_string()
+        } else {
+            format!("{}:{}", addr, net::DEFAULT_PORT)
+        };
+        match Client::connect(addr.as_str()) {
+            Ok(client) => {
+                self.sprites = sprites(ctx, client.game());
+                self.start_network_game(Session::Client(client));
+            }
+            Err(e) => self.imgui_wrapper.show_message(e.to_string()),
+        }
+    }
+
+    fn start_network_game(&mut self, session: Session) {
+        let game = session.game();
+        let side = session.side();
+        self.selected = game.ships_of(side).first().copied().unwrap_or(0);
+        self.imgui_wrapper
+            .show_message(format!("Network game started: you're playing the {}", game.sides[side]));
+        self.net = Some(session);
+        self.listener = None;
+        self.computer = None;
+        self.playback = None;
+        self.plotted_turn = None;
+        self.ready_sent = false;
+        self.abandon_plot();
+    }
+
+    // Carries on alone with the game as it stood.
+    fn leave_network_game(&mut self) {
+        if let Some(session) = self.net.take() {
+            self.recorder = record(session.game().clone(), &mut self.imgui_wrapper);
+            self.plotted_turn = None;
+            self.abandon_plot();
+        }
+        self.listener = None;
+    }
+
+    // Shows what came back from the other end of a network game.
+    fn net_updates(&mut self, result: Result<Vec<Update>, NetError>) {
+        let updates = match result {
+            Ok(updates) => updates,
+            Err(NetError::Rejected(e)) => return self.imgui_wrapper.show_message(e.to_string()),
+            Err(e) => {
+                self.imgui_wrapper.show_message(e.to_string());
+                return self.leave_network_game();
+            }
+        };
+        for update in updates {
+            match update {
+                Update::Entry(Entry::Order { event: Some(event), .. }) => self.show_event(&event),
+                Update::Entry(Entry::Order { .. }) => (),
+                Update::Entry(Entry::Impulse { turn, impulse, events }) => {
+                    println!("Turn {} impulse {}", turn, impulse);
+                    self.ready_sent = false;
+                    for event in events {
+                        self.show_event(&event);
+                    }
+                }
+                Update::Plotted(side) => println!("The {} have plotted", self.game_in_play().sides[side]),
+                Update::PlottedSpeed { ship, speed } => {
+                    let name = &self.game_in_play().ships[ship].spec().ship.name;
+                    println!("{} has plotted speed {}", name, speed)
+                }
+                Update::Rejected(e) => self.imgui_wrapper.show_message(e),
+            }
+        }
+    }
+
+    fn watch_replay(&mut self, ctx: &mut Context, file: &str) {
+        match Replay::load(file) {
+            Ok(replay) => {
+                self.abandon_plot();
+                let playback = Playback::new(replay);
+                self.sprites = sprites(ctx, playback.game());
+                self.playback = Some(playback);
+                self.running = false;
+                self.imgui_wrapper.show_message(String::from(
+                    "Watching a replay: Right steps forward an impulse, Left steps back, Escape returns to the game",
+                ));
+            }
+            Err(e) => self.imgui_wrapper.show_message(e.to_string()),
+        }
+    }
+
+    fn stop_watching(&mut self, ctx: &mut Context) {
+        if self.playback.take().is_some() {
+            self.sprites = sprites(ctx, self.game_in_play());
+        }
+    }
+
+    // Replay keys: stepping through the recorded impulses in either direction.
+    fn replay_key(&mut self, ctx: &mut Context, keycode: KeyCode) {
+        let playback = match self.playback.as_mut() {
+            Some(playback) => playback,
+            None => return,
+        };
+        let stepped = match keycode {
+            KeyCode::Right if !playback.at_end() => playback.step_forward(),
+            KeyCode::Left if !playback.at_start() => playback.step_back(),
+            KeyCode::Escape => {
+                self.stop_watching(ctx);
+                return;
+            }
+            _ => return,
+        };
+        match stepped {
+            Ok(()) => println!("Replay turn {} impulse {}", playback.game().turn(), playback.game().impulse()),
+            Err(e) => self.imgui_wrapper.show_message(e.to_string()),
+        }
+    }
+
+    // Starts the players plotting in secret when a turn is about to begin.
+    fn start_plot(&mut self) {
+        let game = in_play(&self.net, &self.recorder);
+        if self.playback.is_some()
+            || self.plot.is_some()
+            || !game.between_turns()
+            || game.outcome().is_some()
+            || self.plotted_turn == Some(game.next_impulse().0)
+        {
+            return;
+        }
+        self.plot = Some(Plot::new(game));
+        // Across a network each player plots on their own screen.
+        match self.net.as_ref().map(|session| session.side()) {
+            Some(side) => {
+                self.plotting = game.ships_of(side);
+                self.plotted.clear();
+            }
+            None => self.pass_device(),
+        }
+    }
+
+    // Hands the device to the next player to plot, or reveals every plot
+    // once they're all in.
+    fn pass_device(&mut self) {
+        let side = match self.plot.as_ref().and_then(|p| p.waiting_on()) {
+            Some(side) => side,
+            None => return self.reveal(),
+        };
+        if Some(side) == self.computer_side() {
+            return self.computer_plot(side);
+        }
+        let game = self.recorder.game();
+        self.plotting = game.ships_of(side);
+        self.plotted.clear();
+        // Against the computer there's nobody to hide the plot from.
+        if self.computer.is_none() {
+            self.imgui_wrapper.show_pass_screen(&game.sides[side]);
+        }
+    }
+
+    fn computer_side(&self) -> Option<usize> {
+        self.computer.as_ref().map(|_| self.recorder.game().sides.len() - 1)
+    }
+
+    fn computer_plot(&mut self, side: usize) {
+        let game = self.recorder.game();
+        let (ai, plot) = match (self.computer.as_mut(), self.plot.as_mut()) {
+            (Some(ai), Some(plot)) => (ai, plot),
+            _ => return,
+        };
+        let allocations = game.ships_of(side).into_iter().map(|i| (i, ai.allocate(game, i))).collect();
+        match plot.commit(game, side, allocations) {
+            Ok(()) => self.pass_device(),
+            Err(e) => {
+                // Hand its ships back to the players rather than stall.
+                self.computer = None;
+                self.imgui_wrapper
+                    .show_message(format!("The computer opponent couldn't plot: {}", e));
+                self.pass_device();
+            }
+        }
+    }
+
+    // The computer's orders for the coming impulse.
+    fn computer_orders(&mut self) {
+        let side = match self.computer_side() {
+            Some(side) => side,
+            None => return,
+        };
+        for ship in self.recorder.game().ships_of(side) {
+            let orders = match self.computer.as_mut() {
+                Some(ai) => ai.orders(self.recorder.game(), ship),
+                None => return,
+            };
+            // It tries things the rules don't allow now and then; those just
+            // don't happen.
+            for order in orders {
+                if let Ok(Some(event)) = self.recorder.order(ship, &order) {
+                    self.show_event(&event);
+                }
+            }
+        }
+    }
+
+    // Opens the allocation for the player's next ship, or commits their plot
+    // once every ship has one.
+    fn plot_next_ship(&mut self) {
+        let game = in_play(&self.net, &self.recorder);
+        if let Some(ship) = self.plotting.get(self.plotted.len()) {
+            self.imgui_wrapper.open_energy_allocation_window(*ship, &game.ships[*ship]);
+            return;
+        }
+        let plot = match self.plot.as_mut() {
+            Some(plot) => plot,
+            None => return,
+        };
+        let side = game.side_of(self.plotting[0]);
+        let plotted = std::mem::take(&mut self.plotted);
+        if let Err(e) = plot.commit(game, side, plotted.clone()) {
+            return self.imgui_wrapper.show_message(e.to_string());
+        }
+        match self.net.as_mut() {
+            Some(session) => {
+                let turn = session.game().next_impulse().0;
+                let result = session.send(Command::Plot(plotted));
+                self.plot = None;
+                self.plotted_turn = Some(turn);
+                self.net_updates(result);
+            }
+            None => self.pass_device(),
+        }
+    }
+
+    // Carries out everyone's plots together and shows the speeds they chose.
+    fn reveal(&mut self) {
+        let orders = match self.plot.take().map(|p| p.reveal()) {
+            Some(Ok(orders)) => orders,
+            Some(Err(e)) => return self.imgui_wrapper.show_message(e.to_string()),
+            None => return,
+        };
+        for (ship, order) in orders {
+            self.order(ship, order);
+        }
+        let game = self.recorder.game();
+        let turn = game.next_impulse().0;
+        self.plotted_turn = Some(turn);
+        let speeds: Vec<String> = game
+            .ships
+            .iter()
+            .enumerate()
+            .filter(|(_, ship)| !ship.is_destroyed())
+            .map(|(i, ship)| format!("{} {}: speed {}", game.sides[game.side_of(i)], ship.spec().ship.name, ship.speed))
+            .collect();
+        self.imgui_wrapper
+            .show_message(format!("Turn {} plots revealed\n{}", turn, speeds.join("\n")));
+    }
+
+    fn abandon_plot(&mut self) {
+        self.plot = None;
+        self.plotting.clear();
+        self.plotted.clear();
+        self.imgui_wrapper.close_plot_windows();
+    }
+
+    // Moves keyboard orders on to the next ship still in the fight.
+    // Across a network only our own ships take orders from here, and the
+    // computer gives its own.
+    fn select_next_ship(&mut self) {
+        let game = self.game_in_play();
+        let side = self.net.as_ref().map(|session| session.side());
+        let computer = self.computer_side();
+        let ships = &game.ships;
+        if let Some(next) = (1..=ships.len())
+            .map(|n| (self.selected + n) % ships.len())
+            .find(|i| {
+                !ships[*i].is_destroyed()
+                    && side.is_none_or(|side| game.side_of(*i) == side)
+                    && computer != Some(game.side_of(*i))
+            })
+        {
+            self.selected = next;
+        }
+    }
+
+    // Previews a path for the selected ship to a hex clicked on the map.
+    // Clicking the same hex again cycles through the facings to arrive with.
+    fn plan_course(&mut self, to: Hex) {
+        let facing = match &self.course {
+            Some(course) if course.ship == self.selected && course.to == to => course.next_facing(),
+            _ => None,
+        };
+        let game = self.game_in_play();
+        self.course = Course::plan(&game.rules.map, self.selected, &game.ships[self.selected], to, facing);
+        if self.course.is_none() {
+            let facing = facing.map_or(String::new(), |f| format!(" facing {:?}", f));
+            self.imgui_wrapper
+                .show_message(format!("No legal path to hex {}{}", to, facing));
+        }
+    }
+
+    // Keeps a previewed course starting from wherever its ship is now, and
+    // lets go of a committed one once the ship is done with it.
+    fn update_course(&mut self) {
+        let course = match &self.course {
+            Some(course) => course,
+            None => return,
+        };
+        let game = self.game_in_play();
+        let ship = &game.ships[course.ship];
+        if ship.is_destroyed() || (course.committed && course.is_finished(&ship.position)) {
+            self.course = None;
+        } else if !course.committed && ship.position != course.start {
+            self.course = Course::plan(&game.rules.map, course.ship, ship, course.to, course.facing);
+        }
+    }
+
+    // Makes the turn a committed course calls for before the next impulse.
+    fn steer(&mut self) {
+        let turn = match &self.course {
+            Some(course) if course.committed => course
+                .turn(&self.game_in_play().ships[course.ship].position)
+                .map(|side| (course.ship, side)),
+            _ => None,
+        };
+        if let Some((ship, side)) = turn {
+            self.order(ship, Order::Turn(side));
+        }
+    }
+
+    fn status(&self) -> String {
+        let game = self.game();
+        if self.playback.is_some() {
+            return format!("Replay: turn {} impulse {}", game.turn(), game.impulse());
+        }
+        if let Some(side) = self.plot.as_ref().and_then(|p| p.waiting_on()) {
+            return format!("Turn {}: {} plotting", game.next_impulse().0, game.sides[side]);
+        }
+        if self.listener.is_some() {
+            return String::from("Waiting for the other player to join");
+        }
+        let ship = &game.ships[self.selected];
+        let course = match &self.course {
+            Some(course) if course.committed => format!("  |  On course, {} hexes (Esc to cancel)", course.hexes()),
+            Some(course) => format!(
+                "  |  Path: {} hexes (click again for a facing, Enter to commit)",
+                course.hexes()
+            ),
+            None => String::new(),
+        };
+        let waiting = if self.ready_sent {
+            " (waiting for the other player)"
+        } else if self.running {
+            ""
+        } else {
+            " (paused)"
+        };
+        format!(
+            "Turn {} impulse {}{}  |  Orders for {} {} (Tab for the next ship){}",
+            game.turn(),
+            game.impulse(),
+            waiting,
+            game.sides[game.side_of(self.selected)],
+            ship.spec().ship.name,
+            course
+        )
+    }
+
+    fn menu_action(&mut self, ctx: &mut Context, action: MenuAction) {
+        match action {
+            MenuAction::NewGame => {
+                let file = self.scenario_file.clone();
+                self.open_scenario(ctx, &file);
+            }
+            MenuAction::OpenScenario(file) => self.open_scenario(ctx, &file),
+            MenuAction::Save => {
+                self.running = false;
+                let game = self.game_in_play();
+                match save::save(game, &save::file_name(game)) {
+                    Ok(path) => {
+                        self.imgui_wrapper.refresh_files();
+                        self.imgui_wrapper.show_message(format!("Saved to {}", path));
+                    }
+                    Err(e) => self.imgui_wrapper.show_message(e.to_string()),
+                }
+            }
+            MenuAction::Load(file) => match save::load(&file) {
+                Ok(game) => self.replace_game(ctx, game),
+                Err(e) => self.imgui_wrapper.show_message(e.to_string()),
+            },
+            MenuAction::Replay(file) => self.watch_replay(ctx, &file),
+            MenuAction::Host => self.host(),
+            MenuAction::Join(addr) => self.join(ctx, &addr),
+            MenuAction::LeaveNetworkGame => self.leave_network_game(),
+            MenuAction::Opponent(difficulty) => {
+                self.leave_network_game();
+                self.computer = difficulty.map(|d| Ai::new(d, new_seed()));
+                let side = self.computer_side();
+                if self.selected >= self.recorder.game().ships.len()
+                    || Some(self.recorder.game().side_of(self.selected)) == side
+                {
+                    self.selected = 0;
+                }
+                // Start this turn's plot over with the new opponent.
+                self.abandon_plot();
+            }
+        }
+    }
+
+    // Carries out an order, showing what came of it.
+    fn order(&mut self, ship: usize, order: Order) {
+        if self.playback.is_some() {
+            return;
+        }
+        if let Some(session) = self.net.as_mut() {
+            let result = session.send(Command::Order { ship, order });
+            return self.net_updates(result);
+        }
+        match self.recorder.order(ship, &order) {
+            Ok(Some(event)) => self.show_event(&event),
+            Ok(None) => (),
+            Err(e) => self.imgui_wrapper.show_message(e.to_string()),
+        }
+    }
+
+    // Prints an event, and pops up the ones worth stopping for.
+    fn show_event(&mut self, event: &Event) {
+        match event {
+            Event::Moved { .. } => return,
+            Event::MapShifted(shift) => {
+                for sprite in self.sprites.iter_mut() {
+                    sprite.shift(*shift);
+                }
+                if let Some(course) = self.course.as_mut() {
+                    course.shift(*shift);
+                }
+            }
+            Event::Fired(_) | Event::SeekerHit(_) | Event::BlockedByMapEdge { .. } => {
+                self.imgui_wrapper.show_message(event.to_string())
+            }
+            Event::HighEnergyTurn { turn, .. } if turn.broke_down => {
+                self.imgui_wrapper.show_message(event.to_string())
+            }
+            _ => (),
+        }
+        println!("{}", event);
+    }
+}
+
 impl ggez::event::EventHandler for GameState {
-    fn update(&mut self, _ctx: &mut Context) -> GameResult<()> {
+    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+        if let Some(action) = self.imgui_wrapper.take_menu_action() {
+            self.menu_action(ctx, action);
+        }
+        self.accept();
+        if let Some(session) = self.net.as_mut() {
+            let result = session.poll();
+            self.net_updates(result);
+        }
+        self.start_plot();
+        if let Some(allocation) = self.imgui_wrapper.take_committed_allocation() {
+            self.plotted.push(allocation);
+            self.plot_next_ship();
+        } else if self.plot.is_some() && !self.imgui_wrapper.is_passing() && !self.imgui_wrapper.is_allocating() {
+            // The next player is ready, or the last one cancelled and starts
+            // the ship over.
+            self.plot_next_ship();
+        }
+        self.update_course();
+        self.imgui_wrapper.set_status(self.status());
+
+        if let Some(error) = self.recorder.take_error() {
+            self.imgui_wrapper.show_message(error);
+        }
+
+        let map = self.game().rules.map;
+        if (map.cols, map.rows) != (self.map_state.cols, self.map_state.rows) {
+            let screen = graphics::screen_coordinates(ctx);
+            self.lay_out_map(ctx, screen.w, screen.h);
+        }
+
+        let positions: Vec<Position> = self.game().ships.iter().map(|ship| ship.position).collect();
+        for (sprite, position) in self.sprites.iter_mut().zip(positions) {
+            sprite.move_to(position);
+        }
+
+        // Let the previous impulse finish animating before running the next one.
+        if !self.running || self.playback.is_some() || self.plot.is_some() || self.sprites.iter().any(|s| s.is_moving()) {
+            return Ok(());
+        }
+
+        if self.net.is_some() {
+            // The host runs the impulse once both players are ready.
+            if !self.ready_sent {
+                self.steer();
+                self.ready_sent = true;
+                let result = self.net.as_mut().map(|session| session.send(Command::Ready));
+                if let Some(result) = result {
+                    self.net_updates(result);
+                }
+            }
+            return Ok(());
+        }
+
+        self.steer();
+        self.computer_orders();
+        let events = self.recorder.run_impulse();
+        let game = self.recorder.game();
+        println!("Turn {} impulse {}", game.turn(), game.impulse());
+        for event in events {
+            self.show_event(&event);
+        }
         Ok(())
     }
 
@@ -102,8 +778,14 @@ impl ggez::event::EventHandler for GameState {
         graphics::clear(ctx, graphics::BLACK);
         graphics::draw(ctx, &self.map_mesh, graphics::DrawParam::default())?;
 
-        for ship in self.ships.iter_mut() {
-            ship.draw(ctx, &self.map_state)?;
+        for sprite in self.sprites.iter_mut() {
+            sprite.draw(ctx, &self.map_state)?;
+        }
+        for mut seeker in self.game().seekers.iter().cloned() {
+            seeker.draw(ctx, &self.map_state)?;
+        }
+        if let (Some(course), None) = (&self.course, &self.playback) {
+            course.draw(ctx, &self.map_state)?;
         }
 
         self.imgui_wrapper.render(ctx, self.hidpi_factor);
@@ -124,15 +806,18 @@ impl ggez::event::EventHandler for GameState {
         ));
         self.mouse_down = true;
         let p = Point2::new(x, y);
-        let hex = Hex::from_screen(p, &self.map_state);
+        let hex = map::hex_from_screen(p, &self.map_state);
         println!("Mouse button pressed: {:?}, in hex {:?}", button, hex);
+        if let (MouseButton::Left, Some(hex), None) = (button, hex, &self.playback) {
+            self.plan_course(hex);
+        }
     }
 
     fn mouse_button_up_event(&mut self, _ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
         self.imgui_wrapper.update_mouse_down((false, false, false));
         self.mouse_down = false;
         let p = Point2::new(x, y);
-        let hex = Hex::from_screen(p, &self.map_state);
+        let hex = map::hex_from_screen(p, &self.map_state);
         println!("Mouse button released: {:?}, in hex {:?}", button, hex);
     }
 
@@ -148,55 +833,68 @@ impl ggez::event::EventHandler for GameState {
         } else {
             println!("Key down: {:?}-{:?}", keymods, keycode);
         }
+        if self.playback.is_some() {
+            self.replay_key(ctx, keycode);
+            return;
+        }
+        let ship = self.selected;
+        let target = controller::nearest_enemy(self.game_in_play(), ship);
         match keycode {
-            KeyCode::E => {
-                let d7 = &mut self.ships[1];
-                let pos = Position {
-                    facing: d7.position.facing.turn_right(),
-                    hex: d7.position.hex,
-                };
-                d7.move_to(pos);
+            KeyCode::A => self.order(ship, Order::Sideslip(Side::Left)),
+            KeyCode::D => self.order(ship, Order::Sideslip(Side::Right)),
+            KeyCode::E => self.order(ship, Order::Turn(Side::Right)),
+            KeyCode::F => {
+                if let Some(target) = target {
+                    self.order(ship, Order::Fire { target });
+                }
             }
-            KeyCode::P => {
-                self.imgui_wrapper.open_energy_allocation_window(&mut self.ships[0]);
+            KeyCode::L => {
+                if let Some(target) = target {
+                    // Whichever kind of launcher the ship has ready.
+                    let game = self.game_in_play();
+                    let plasma = game.ships[ship].launcher_ready(SeekingKind::Plasma, game.turn(), game.impulse());
+                    let kind = if plasma.is_some() {
+                        SeekingKind::Plasma
+                    } else {
+                        SeekingKind::Drone
+                    };
+                    self.order(ship, Order::Launch { kind, target });
+                }
             }
             KeyCode::Q => {
                 if input::keyboard::is_mod_active(ctx, input::keyboard::KeyMods::LOGO) {
                     println!("cmd-q: quitting");
                     event::quit(ctx);
                 } else {
-                    let d7 = &mut self.ships[1];
-                    let pos = Position {
-                        facing: d7.position.facing.turn_left(),
-                        hex: d7.position.hex,
-                    };
-                    d7.move_to(pos);
+                    self.order(ship, Order::Turn(Side::Left));
                 }
             }
-            KeyCode::W => {
-                let d7 = &mut self.ships[1];
-                let dest = d7.position.hex.neighbor(d7.position.facing);
-                if dest.is_some() {
-                    let pos = Position {
-                        facing: d7.position.facing,
-                        hex: dest.unwrap(),
-                    };
-                    d7.move_to(pos);
+            KeyCode::Key1 => self.order(ship, Order::HighEnergyTurn(Facing::A)),
+            KeyCode::Key2 => self.order(ship, Order::HighEnergyTurn(Facing::B)),
+            KeyCode::Key3 => self.order(ship, Order::HighEnergyTurn(Facing::C)),
+            KeyCode::Key4 => self.order(ship, Order::HighEnergyTurn(Facing::D)),
+            KeyCode::Key5 => self.order(ship, Order::HighEnergyTurn(Facing::E)),
+            KeyCode::Key6 => self.order(ship, Order::HighEnergyTurn(Facing::F)),
+            KeyCode::Space => {
+                self.running = !self.running;
+                println!("Impulse sequencer {}", if self.running { "running" } else { "paused" });
+            }
+            KeyCode::Return => {
+                if let Some(course) = self.course.as_mut() {
+                    course.committed = true;
                 }
             }
+            KeyCode::Escape => self.course = None,
+            KeyCode::Tab => self.select_next_ship(),
+            KeyCode::X => self.order(ship, Order::FireAtSeeker),
             _ => (),
         }
     }
 
-    fn resize_event(&mut self, ctx: &mut Context, _width: f32, height: f32) {
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
         // TODO DRY this when startup in run() is better.
         self.hidpi_factor = ggez::graphics::window(&ctx).get_hidpi_factor() as f32;
         println!("hidpi_factor = {}", self.hidpi_factor);
-        self.map_state = map::init(Point2::new(0.0, MENU_HEIGHT), height - MENU_HEIGHT);
-        self.map_mesh = map::build_mesh(ctx, &self.map_state).unwrap();
-
-        for ship in self.ships.iter_mut() {
-            ship.invalidate();
-        }
+        self.lay_out_map(ctx, width, height);
     }
 }
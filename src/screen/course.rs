@@ -0,0 +1,100 @@
+use crate::screen::map::{self, MapState};
+use ggez::graphics::{self, Color, DrawMode, MeshBuilder};
+use ggez::nalgebra::Point2;
+use ggez::{Context, GameResult};
+use sfb::hex::{Facing, Hex, Map, Shift};
+use sfb::movement::Side;
+use sfb::path::{Path, Step};
+use sfb::ship::{Position, Ship};
+
+// A path planned for a ship by clicking on the map. It's only a preview,
+// following the ship as it moves, until the player commits to it; after
+// that the ship makes the turns along it by itself.
+pub struct Course {
+    pub ship: usize,
+    pub to: Hex,
+    // The facing to arrive with, if the player picked one.
+    pub facing: Option<Facing>,
+    pub committed: bool,
+    // Where the ship was when the path was found.
+    pub start: Position,
+    path: Path,
+}
+
+impl Course {
+    pub fn plan(map: &Map, ship: usize, s: &Ship, to: Hex, facing: Option<Facing>) -> Option<Course> {
+        let path = s.path_to(map, to, facing)?;
+        Some(Course {
+            ship,
+            to,
+            facing,
+            committed: false,
+            start: s.position,
+            path,
+        })
+    }
+
+    // The next facing to ask for when clicking the same hex again: any
+    // facing, then each one in turn.
+    pub fn next_facing(&self) -> Option<Facing> {
+        match self.facing {
+            None => Some(Facing::A),
+            Some(Facing::F) => None,
+            Some(f) => Some(f.turn_right()),
+        }
+    }
+
+    pub fn hexes(&self) -> usize {
+        self.path.hexes()
+    }
+
+    // Whether the ship has reached the end of the course or strayed off it.
+    pub fn is_finished(&self, position: &Position) -> bool {
+        self.path.positions.last().is_none_or(|end| end == position)
+            || self.path.next_step(position, &self.start).is_none()
+    }
+
+    // The turn the ship should make now to stay on course, if any.
+    pub fn turn(&self, position: &Position) -> Option<Side> {
+        match self.path.next_step(position, &self.start) {
+            Some(Step::Turn(side)) => Some(side),
+            _ => None,
+        }
+    }
+
+    // Follows the ship when everything on a floating map moves.
+    pub fn shift(&mut self, shift: Shift) {
+        self.to = self.to.shifted(shift);
+        self.start.hex = self.start.hex.shifted(shift);
+        self.path.shift(shift);
+    }
+
+    pub fn draw(&self, ctx: &mut Context, map_state: &MapState) -> GameResult<()> {
+        let color = if self.committed {
+            Color::new(0.3, 1.0, 0.4, 0.8)
+        } else {
+            Color::new(1.0, 0.8, 0.2, 0.8)
+        };
+        let mut points = vec![map::hex_to_screen(&self.start.hex, map_state)];
+        for position in &self.path.positions {
+            let point = map::hex_to_screen(&position.hex, map_state);
+            if points.last() != Some(&point) {
+                points.push(point);
+            }
+        }
+        let builder = &mut MeshBuilder::new();
+        if points.len() > 1 {
+            builder.line(&points, 3.0, color)?;
+        }
+        // A tick at the end pointing the way the ship will face.
+        let end = self.path.positions.last().unwrap_or(&self.start);
+        let center = map::hex_to_screen(&end.hex, map_state);
+        let angle = end.facing.to_angle();
+        let length = map_state.hex_height * 0.45;
+        let tip = Point2::new(center.x + length * angle.sin(), center.y - length * angle.cos());
+        builder.line(&[center, tip], 3.0, color)?;
+        builder.circle(DrawMode::fill(), center, 4.0, 0.5, color);
+        let mesh = builder.build(ctx)?;
+        graphics::draw(ctx, &mesh, graphics::DrawParam::default())
+    }
+}
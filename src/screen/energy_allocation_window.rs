@@ -1,31 +1,89 @@
-use crate::ship::EnergyAllocation;
 use imgui::*;
+use sfb::energy::EnergyAllocation;
+use sfb::impulse::MAX_SPEED;
+
+pub enum AllocationAction {
+    Commit,
+    Cancel,
+}
 
 pub struct EnergyAllocationWindow<'a> {
-    alloc: &'a EnergyAllocation
+    ship: &'a str,
+    alloc: &'a mut EnergyAllocation
 }
 
 impl EnergyAllocationWindow<'_> {
-    pub fn new(e: &EnergyAllocation) -> EnergyAllocationWindow {
+    pub fn new<'a>(ship: &'a str, e: &'a mut EnergyAllocation) -> EnergyAllocationWindow<'a> {
         EnergyAllocationWindow {
+            ship,
             alloc: e,
         }
     }
 
-    pub fn show<'a>(&self, ui: &Ui<'a>) {
+    pub fn show<'a>(&mut self, ui: &Ui<'a>) -> Option<AllocationAction> {
+        let mut action = None;
+        let ship = self.ship;
+        let alloc = &mut *self.alloc;
+        let available = alloc.available;
+        // No single line can use more than the ship has in total.
+        let max = available.total().min(u8::MAX as u16) as u8;
+
         Window::new(im_str!("Energy Allocation"))
-            .size([300.0, 110.0], Condition::FirstUseEver)
+            .size([360.0, 620.0], Condition::FirstUseEver)
             // .opened(opened)
             .build(&ui, || {
-                ui.text(format!("Warp Power Available: {}", self.alloc.warp_available));
-                ui.text(format!("Impulse Power Available: {}", self.alloc.impulse_available));
-                ui.text(format!("Reactor Power Available: {}", self.alloc.reactor_available));
+                ui.text(ship);
+                ui.text(format!("Warp Power Available: {}", available.warp));
+                ui.text(format!("Impulse Power Available: {}", available.impulse));
+                ui.text(format!("Reactor Power Available: {}", available.reactor));
+                ui.text(format!("Battery Power Available: {}", available.battery));
                 ui.separator();
-                let mouse_pos = ui.io().mouse_pos;
+
+                Slider::new(im_str!("Life support"), 0..=max).build(ui, &mut alloc.life_support);
+                Slider::new(im_str!("Fire control"), 0..=max).build(ui, &mut alloc.fire_control);
+                Slider::new(im_str!("Speed"), 0..=MAX_SPEED).build(ui, &mut alloc.speed);
+                ui.text(format!("Movement: {} ({} per hex)", alloc.movement(), alloc.movement_cost));
+                let capacitor_room = alloc.capacitor_capacity.saturating_sub(alloc.capacitor_charge);
+                Slider::new(im_str!("Phaser capacitor"), 0..=capacitor_room).build(ui, &mut alloc.phaser_capacitor);
                 ui.text(format!(
-                        "Mouse Position: ({:.1},{:.1})",
-                        mouse_pos[0], mouse_pos[1]
-                        ));
-            })
+                    "Capacitor: {} of {}, +{} this turn",
+                    alloc.capacitor_charge, alloc.capacitor_capacity, alloc.phaser_capacitor
+                ));
+                for w in alloc.heavy_weapons.iter_mut() {
+                    Slider::new(&im_str!("{} (needs {})", w.label, w.required), 0..=w.max)
+                        .build(ui, &mut w.energy);
+                    if let Some(proximity) = w.proximity.as_mut() {
+                        ui.checkbox(&im_str!("{} proximity fuse", w.label), proximity);
+                    }
+                }
+                Slider::new(im_str!("High energy turns"), 0..=max).build(ui, &mut alloc.high_energy_turns);
+                Slider::new(im_str!("Battery charging"), 0..=alloc.battery_capacity)
+                    .build(ui, &mut alloc.battery_charge);
+
+                ui.separator();
+                Slider::new(im_str!("General reinforcement"), 0..=max / 2)
+                    .build(ui, &mut alloc.general_reinforcement);
+                for (i, energy) in alloc.specific_reinforcement.iter_mut().enumerate() {
+                    Slider::new(&im_str!("Shield {}", i + 1), 0..=max).build(ui, energy);
+                }
+
+                ui.separator();
+                ui.text(format!("Allocated: {} of {}", alloc.allocated(), available.total()));
+                ui.text(format!("Remaining: {}", alloc.remaining()));
+                match alloc.validate() {
+                    Ok(()) => {
+                        ui.text(format!("Battery after allocation: {}", alloc.battery_after()));
+                        if ui.button(im_str!("Commit"), [0.0, 0.0]) {
+                            action = Some(AllocationAction::Commit);
+                        }
+                        ui.same_line(0.0);
+                    }
+                    Err(e) => ui.text_colored([1.0, 0.3, 0.3, 1.0], e.to_string()),
+                }
+                if ui.button(im_str!("Cancel"), [0.0, 0.0]) {
+                    action = Some(AllocationAction::Cancel);
+                }
+            });
+        action
     }
 }
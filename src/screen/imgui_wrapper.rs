@@ -1,11 +1,16 @@
-use crate::screen::main_menu;
-use crate::screen::energy_allocation_window::EnergyAllocationWindow;
-use crate::ship::{EnergyAllocation, Ship};
+use crate::screen::main_menu::{self, MenuAction};
+use crate::screen::MENU_HEIGHT;
+use crate::screen::energy_allocation_window::{AllocationAction, EnergyAllocationWindow};
 use ggez::graphics;
 use ggez::Context;
 use gfx_core::{handle::RenderTargetView, memory::Typed};
 use imgui::*;
 use imgui_gfx_renderer::*;
+use sfb::energy::EnergyAllocation;
+use sfb::replay;
+use sfb::save;
+use sfb::scenario;
+use sfb::ship::Ship;
 use std::time::Instant;
 
 #[derive(Copy, Clone, PartialEq, Debug, Default)]
@@ -22,7 +27,21 @@ pub struct ImGuiWrapper {
     last_frame: Instant,
     mouse_state: MouseState,
 
-    energy_allocation: Option<EnergyAllocation>,
+    // The ship being allocated for, its name and its allocation in progress.
+    energy_allocation: Option<(usize, String, EnergyAllocation)>,
+    committed_allocation: Option<(usize, EnergyAllocation)>,
+    message: Option<String>,
+    // The player to hand the device to, until they say they're ready.
+    pass_to: Option<String>,
+    // What's going on, shown in the menu bar.
+    status: String,
+    // Scenario files, saved games and replays for the File menu.
+    scenarios: Vec<String>,
+    saves: Vec<String>,
+    replays: Vec<String>,
+    // Where to find a hosted network game.
+    join_address: ImString,
+    menu_action: Option<MenuAction>,
 }
 
 impl ImGuiWrapper {
@@ -59,6 +78,15 @@ impl ImGuiWrapper {
             last_frame: Instant::now(),
             mouse_state: MouseState::default(),
             energy_allocation: None,
+            committed_allocation: None,
+            message: None,
+            pass_to: None,
+            status: String::new(),
+            scenarios: scenario::list(),
+            saves: save::list(),
+            replays: replay::list(),
+            join_address: ImString::with_capacity(64),
+            menu_action: None,
         }
     }
 
@@ -76,17 +104,61 @@ impl ImGuiWrapper {
         self.imgui.io_mut().delta_time = delta_s;
 
         let ui = self.imgui.frame();
-        main_menu::show(&ui);
+        if let Some(action) = main_menu::show(
+            &ui,
+            &self.scenarios,
+            &self.saves,
+            &self.replays,
+            &mut self.join_address,
+            &self.status,
+        ) {
+            self.menu_action = Some(action);
+        }
 
-        ui.popup_modal(im_str!("modal")).build(|| {
-            ui.text("This is a modal!");
+        if self.message.is_some() {
+            ui.open_popup(im_str!("Message"));
+        }
+        let message = &mut self.message;
+        ui.popup_modal(im_str!("Message")).always_auto_resize(true).build(|| {
+            if let Some(m) = message {
+                ui.text(m);
+            }
             if ui.button(im_str!("OK"), [0.0, 0.0]) {
+                message.take();
                 ui.close_current_popup();
             }
         });
 
-        if self.energy_allocation.is_some() {
-            EnergyAllocationWindow::new(self.energy_allocation.as_ref().unwrap()).show(&ui);
+        // Covers the screen so the last player's plot is out of sight before
+        // the next one sits down.
+        if let Some(player) = self.pass_to.clone() {
+            let mut ready = false;
+            Window::new(im_str!("Pass the device"))
+                .position([0.0, MENU_HEIGHT], Condition::Always)
+                .size([draw_width, draw_height - MENU_HEIGHT], Condition::Always)
+                .title_bar(false)
+                .resizable(false)
+                .movable(false)
+                .build(&ui, || {
+                    ui.text(format!("Pass the device to the {} player.", player));
+                    ui.text("Nobody else should look until their plot is done.");
+                    if ui.button(im_str!("Ready"), [0.0, 0.0]) {
+                        ready = true;
+                    }
+                });
+            if ready {
+                self.pass_to = None;
+            }
+        }
+
+        if let Some((_, ship, alloc)) = self.energy_allocation.as_mut() {
+            match EnergyAllocationWindow::new(ship, alloc).show(&ui) {
+                Some(AllocationAction::Commit) => {
+                    self.committed_allocation = self.energy_allocation.take().map(|(i, _, alloc)| (i, alloc))
+                }
+                Some(AllocationAction::Cancel) => self.energy_allocation = None,
+                None => (),
+            }
         }
 
         let (factory, _, encoder, _, render_target) = graphics::gfx_objects(ctx);
@@ -125,7 +197,49 @@ impl ImGuiWrapper {
         self.mouse_state.pressed = pressed;
     }
 
-    pub fn open_energy_allocation_window(&mut self, ship: &mut Ship) {
-        self.energy_allocation = Some(ship.get_energy_allocation());
+    pub fn show_message(&mut self, message: String) {
+        self.message = Some(message);
+    }
+
+    pub fn open_energy_allocation_window(&mut self, ship_index: usize, ship: &Ship) {
+        self.energy_allocation = Some((ship_index, ship.spec().ship.name.clone(), ship.get_energy_allocation()));
+    }
+
+    pub fn is_allocating(&self) -> bool {
+        self.energy_allocation.is_some()
+    }
+
+    // Hides the map behind a screen asking for the device to be handed over.
+    pub fn show_pass_screen(&mut self, player: &str) {
+        self.pass_to = Some(player.to_string());
+    }
+
+    pub fn is_passing(&self) -> bool {
+        self.pass_to.is_some()
+    }
+
+    // Puts away the pass screen and any allocation in progress.
+    pub fn close_plot_windows(&mut self) {
+        self.pass_to = None;
+        self.energy_allocation = None;
+    }
+
+    pub fn set_status(&mut self, status: String) {
+        self.status = status;
+    }
+
+    // An allocation the player committed, to be written back to its ship.
+    pub fn take_committed_allocation(&mut self) -> Option<(usize, EnergyAllocation)> {
+        self.committed_allocation.take()
+    }
+
+    pub fn take_menu_action(&mut self) -> Option<MenuAction> {
+        self.menu_action.take()
+    }
+
+    // Picks up newly saved games and replays for the File menu.
+    pub fn refresh_files(&mut self) {
+        self.saves = save::list();
+        self.replays = replay::list();
     }
 }
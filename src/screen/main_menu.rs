@@ -1,11 +1,92 @@
 use imgui::*;
+use sfb::ai::Difficulty;
 
-pub fn show<'a>(ui: &Ui<'a>) {
+pub enum MenuAction {
+    // Start the current scenario over.
+    NewGame,
+    OpenScenario(String),
+    Save,
+    Load(String),
+    // Watch a recorded game.
+    Replay(String),
+    // Play the game in progress against someone who joins over the network.
+    Host,
+    // Join a hosted game at the given address.
+    Join(String),
+    LeaveNetworkGame,
+    // Play against the computer at the given difficulty, or hot seat.
+    Opponent(Option<Difficulty>),
+}
+
+// Draws the menu bar, returning whatever was picked from it.
+pub fn show<'a>(
+    ui: &Ui<'a>,
+    scenarios: &[String],
+    saves: &[String],
+    replays: &[String],
+    join_address: &mut ImString,
+    status: &str,
+) -> Option<MenuAction> {
+    let mut action = None;
     if let Some(menu_bar) = ui.begin_main_menu_bar() {
         if let Some(menu) = ui.begin_menu(im_str!("File"), true) {
-            MenuItem::new(im_str!("New Game"))
-                .enabled(false)
-                .build(ui);
+            if MenuItem::new(im_str!("New Game")).build(ui) {
+                action = Some(MenuAction::NewGame);
+            }
+            if let Some(open) = ui.begin_menu(im_str!("Open Scenario"), !scenarios.is_empty()) {
+                for scenario in scenarios {
+                    if MenuItem::new(&ImString::new(scenario.as_str())).build(ui) {
+                        action = Some(MenuAction::OpenScenario(scenario.clone()));
+                    }
+                }
+                open.end(ui);
+            }
+            ui.separator();
+            if MenuItem::new(im_str!("Save")).build(ui) {
+                action = Some(MenuAction::Save);
+            }
+            if let Some(load) = ui.begin_menu(im_str!("Load"), !saves.is_empty()) {
+                for save in saves {
+                    if MenuItem::new(&ImString::new(save.as_str())).build(ui) {
+                        action = Some(MenuAction::Load(save.clone()));
+                    }
+                }
+                load.end(ui);
+            }
+            ui.separator();
+            if let Some(watch) = ui.begin_menu(im_str!("Replays"), !replays.is_empty()) {
+                for replay in replays {
+                    if MenuItem::new(&ImString::new(replay.as_str())).build(ui) {
+                        action = Some(MenuAction::Replay(replay.clone()));
+                    }
+                }
+                watch.end(ui);
+            }
+            ui.separator();
+            if let Some(opponent) = ui.begin_menu(im_str!("Opponent"), true) {
+                if MenuItem::new(im_str!("Hot Seat")).build(ui) {
+                    action = Some(MenuAction::Opponent(None));
+                }
+                for difficulty in &[Difficulty::Easy, Difficulty::Normal, Difficulty::Hard] {
+                    if MenuItem::new(&im_str!("Computer ({})", difficulty)).build(ui) {
+                        action = Some(MenuAction::Opponent(Some(*difficulty)));
+                    }
+                }
+                opponent.end(ui);
+            }
+            if MenuItem::new(im_str!("Host Network Game")).build(ui) {
+                action = Some(MenuAction::Host);
+            }
+            if let Some(join) = ui.begin_menu(im_str!("Join Network Game"), true) {
+                ui.input_text(im_str!("Host address"), join_address).build();
+                if MenuItem::new(im_str!("Join")).build(ui) {
+                    action = Some(MenuAction::Join(join_address.to_str().trim().to_string()));
+                }
+                join.end(ui);
+            }
+            if MenuItem::new(im_str!("Leave Network Game")).build(ui) {
+                action = Some(MenuAction::LeaveNetworkGame);
+            }
             menu.end(ui);
         }
         if let Some(menu) = ui.begin_menu(im_str!("View"), true) {
@@ -14,6 +95,9 @@ pub fn show<'a>(ui: &Ui<'a>) {
                 .build(ui);
             menu.end(ui);
         }
+        ui.separator();
+        ui.text(status);
         menu_bar.end(ui);
     }
+    action
 }
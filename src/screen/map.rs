@@ -1,6 +1,7 @@
 use ggez::graphics::{DrawMode, Mesh, MeshBuilder, WHITE};
 use ggez::nalgebra::{Point2, Vector2};
 use ggez::{Context, GameResult};
+use sfb::hex::{Hex, Map};
 
 pub struct MapState {
     pub origin: Point2<f32>, // The screen coordinate of the map's upper left corner
@@ -9,17 +10,22 @@ pub struct MapState {
     pub start_point: Point2<f32>, // The screen coordinate for the center of hex 0,0
     pub hex_height: f32,
     pub hex_edge: f32,
+    // The columns and rows drawn.
+    pub cols: i16,
+    pub rows: i16,
     vector: Vector2<f32>,
 }
 
-pub fn init(origin: Point2<f32>, height: f32) -> MapState {
-    let hex_height = height / 30.5;
+// Lays the map out as large as it fits in the given space.
+pub fn init(origin: Point2<f32>, max_width: f32, height: f32, map: &Map) -> MapState {
+    let cols = map.cols as f32;
+    let hex_height = (height / (map.rows as f32 + 0.5)).min(max_width / (1.5 * cols + 0.5) * 3_f32.sqrt());
     let hex_edge = hex_height / 3_f32.sqrt();
     let start_point = Point2::new(hex_edge + origin.x, hex_height * 0.5 + origin.y);
     let vector = Vector2::new(hex_edge * 2.0 * 0.75, hex_height * 0.5);
 
     let hex_width = 2.0 * hex_edge;
-    let width = 0.75 * hex_width * 60.0 + (hex_width * 0.25);
+    let width = 0.75 * hex_width * cols + (hex_width * 0.25);
 
     MapState {
         origin,
@@ -28,6 +34,8 @@ pub fn init(origin: Point2<f32>, height: f32) -> MapState {
         start_point,
         hex_height,
         hex_edge,
+        cols: map.cols,
+        rows: map.rows,
         vector,
     }
 }
@@ -35,9 +43,9 @@ pub fn init(origin: Point2<f32>, height: f32) -> MapState {
 pub fn build_mesh(ctx: &mut Context, map_state: &MapState) -> GameResult<Mesh> {
     let builder = &mut MeshBuilder::new();
 
-    for col in 0..60 {
+    for col in 0..map_state.cols {
         let x = map_state.start_point.x + map_state.vector.x * col as f32;
-        for row in 0..30 {
+        for row in 0..map_state.rows {
             let y = if col % 2 == 0 {
                 // even row
                 map_state.start_point.y + map_state.hex_height * row as f32
@@ -54,6 +62,33 @@ pub fn build_mesh(ctx: &mut Context, map_state: &MapState) -> GameResult<Mesh> {
     builder.build(ctx)
 }
 
+pub fn hex_to_screen(hex: &Hex, map_state: &MapState) -> Point2<f32> {
+    let x = map_state.hex_edge * 3.0 / 2.0 * hex.col as f32 + map_state.start_point.x;
+    let y = map_state.hex_height * (hex.row as f32 + 0.5 * (hex.col & 1) as f32) + map_state.start_point.y;
+    Point2::new(x, y)
+}
+
+// adapted from https://web.archive.org/web/20161024224848/http://gdreflections.com/2011/02/hexagonal-grid-math.html
+pub fn hex_from_screen(point: Point2<f32>, map_state: &MapState) -> Option<Hex> {
+    let translated_point = Point2::new(point.x - map_state.origin.x, point.y - map_state.origin.y);
+    let side = map_state.hex_edge * 3.0 / 2.0;
+
+    let ci = (translated_point.x / side).floor() as i16;
+    let cx = translated_point.x - side * ci as f32;
+
+    let ty = translated_point.y - (ci % 2) as f32 * map_state.hex_height / 2.0;
+    let cj = (ty / map_state.hex_height).floor() as i16;
+    let cy = ty - map_state.hex_height * cj as f32;
+
+    let hex = if cx > (map_state.hex_edge / 2.0 - map_state.hex_edge * cy / map_state.hex_height).abs() {
+        Hex::new(ci, cj)
+    } else {
+        let minus = if cy < map_state.hex_height / 2.0 { 1 } else { 0 };
+        Hex::new(ci - 1, cj + (ci % 2) - minus)
+    };
+    Some(hex).filter(|hex| (0..map_state.cols).contains(&hex.col) && (0..map_state.rows).contains(&hex.row))
+}
+
 fn hex_vertex(center: Point2<f32>, size: f32, i: usize) -> Point2<f32> {
     let angle_deg = (60 * i) as f32;
     let angle_rad = std::f32::consts::PI / 180.0 * angle_deg;
@@ -73,3 +108,42 @@ fn hex_points(center: Point2<f32>, size: f32) -> [Point2<f32>; 6] {
         hex_vertex(center, size, 5),
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screen::{MENU_HEIGHT, WINDOW_HEIGHT};
+    use sfb::hex::STANDARD_MAP;
+
+    #[test]
+    fn screen_to_hex() {
+        let map_state = init(Point2::new(0.0, MENU_HEIGHT), f32::INFINITY, WINDOW_HEIGHT - MENU_HEIGHT, &STANDARD_MAP);
+
+        assert_eq!(None, hex_from_screen(Point2::new(0.0, MENU_HEIGHT), &map_state));
+
+        assert_eq!(
+            Some(Hex::new(0, 0)),
+            hex_from_screen(Point2::new(map_state.origin.x + map_state.hex_edge / 2.0, map_state.origin.y + map_state.hex_edge / 2.0), &map_state)
+        );
+
+        for h in STANDARD_MAP.hexes() {
+            assert_eq!(
+                h,
+                hex_from_screen(hex_to_screen(&h, &map_state), &map_state).unwrap()
+            );
+        }
+
+        // Bigger maps fit the same space with smaller hexes.
+        let big = Map {
+            cols: 150,
+            rows: 100,
+            ..STANDARD_MAP
+        };
+        let map_state = init(Point2::new(0.0, MENU_HEIGHT), 1200.0, WINDOW_HEIGHT - MENU_HEIGHT, &big);
+        assert!(map_state.width <= 1200.0);
+        for h in [Hex::new(0, 0), Hex::new(120, 99), Hex::new(149, 50)] {
+            assert_eq!(Some(h), hex_from_screen(hex_to_screen(&h, &map_state), &map_state));
+        }
+        assert_eq!(None, hex_from_screen(hex_to_screen(&Hex::new(150, 50), &map_state), &map_state));
+    }
+}
@@ -0,0 +1,39 @@
+use crate::screen::map::{self, MapState};
+use crate::screen::Actor;
+use ggez::graphics::{self, Color, DrawMode, Mesh};
+use ggez::nalgebra::Point2;
+use ggez::{Context, GameResult};
+use sfb::seeking::{SeekingKind, SeekingWeapon};
+use sfb::ship::Position;
+
+// Seekers are drawn as small arrowheads pointing along their facing. They jump
+// from hex to hex rather than animating, since plasma moves every impulse.
+impl Actor for SeekingWeapon {
+    fn draw(&mut self, ctx: &mut Context, map_state: &MapState) -> GameResult<()> {
+        let size = map_state.hex_height * 0.3;
+        let color = match self.kind {
+            SeekingKind::Drone => Color::new(1.0, 0.8, 0.2, 1.0),
+            SeekingKind::Plasma => Color::new(0.3, 1.0, 0.4, 1.0),
+        };
+        let points = [
+            Point2::new(0.0, -size),
+            Point2::new(size * 0.6, size * 0.6),
+            Point2::new(-size * 0.6, size * 0.6),
+        ];
+        let mesh = Mesh::new_polygon(ctx, DrawMode::fill(), &points, color)?;
+        let draw_param = graphics::DrawParam::new()
+            .dest(map::hex_to_screen(&self.position.hex, map_state))
+            .rotation(self.position.facing.to_angle());
+        graphics::draw(ctx, &mesh, draw_param)
+    }
+
+    fn move_to(&mut self, new_position: Position) {
+        self.position = new_position;
+    }
+
+    fn is_moving(&self) -> bool {
+        false
+    }
+
+    fn invalidate(&mut self) {}
+}
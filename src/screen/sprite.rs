@@ -0,0 +1,151 @@
+use crate::screen::map::{self, MapState};
+use crate::screen::Actor;
+use ggez::graphics;
+use ggez::nalgebra::{Point2, Vector2};
+use ggez::{Context, GameResult};
+use sfb::hex::Shift;
+use sfb::ship::Position;
+use std::path;
+
+const IMAGE_PATH: &str = "/gfx/ships";
+
+// A ship's image on the map. The game moves ships from hex to hex at once;
+// the sprite follows along, turning and then sliding to where the ship is.
+pub struct Sprite {
+    image: graphics::Image,
+    // Where the sprite was last at rest.
+    position: Position,
+    moving_to: Option<Position>,
+
+    scale: Option<f32>,
+    draw_dest: Option<Point2<f32>>,
+    draw_rotation: f32,
+}
+
+impl Sprite {
+    pub fn new(ctx: &mut Context, image_file: &str, position: Position) -> Sprite {
+        let mut path = path::PathBuf::from(IMAGE_PATH);
+        path.push(image_file);
+        let image = graphics::Image::new(ctx, path).unwrap();
+        Sprite {
+            image,
+            position,
+            moving_to: None,
+
+            scale: None,
+            draw_dest: None,
+            draw_rotation: 0.0,
+        }
+    }
+
+    // Jumps along with the ship when a floating map shifts, rather than
+    // sliding across the board.
+    pub fn shift(&mut self, shift: Shift) {
+        self.position.hex = self.position.hex.shifted(shift);
+        if let Some(to) = self.moving_to.as_mut() {
+            to.hex = to.hex.shifted(shift);
+        }
+        self.draw_dest = None;
+    }
+
+    fn done_moving(&mut self) {
+        if self.moving_to.is_some() {
+            println!("Destination reached!");
+            self.position = self.moving_to.take().unwrap();
+        }
+    }
+
+    fn set_next_draw_dest(&mut self, map_state: &MapState) {
+        let started_at = map::hex_to_screen(&self.position.hex, map_state);
+        let moving_to = self.moving_to.as_ref().unwrap();
+
+        let end_dest = map::hex_to_screen(&moving_to.hex, map_state);
+        let dx = (end_dest.x - started_at.x) / 45.0;
+        let dy = (end_dest.y - started_at.y) / 45.0;
+        let v = Vector2::new(dx, dy);
+
+        self.draw_dest.replace(self.draw_dest.unwrap() + v);
+        let new_dest = self.draw_dest.unwrap();
+        // println!("Current: {:?}  Dest: {:?}", new_dest, end_dest);
+        if ulps_eq!(new_dest, end_dest, epsilon = f32::EPSILON, max_ulps = 10_000) {
+            self.done_moving();
+        }
+    }
+
+    fn set_next_draw_rotation(&mut self) {
+        let current_degrees = self.draw_rotation.to_degrees();
+        let dest_degrees = self.moving_to.as_ref().unwrap().facing.to_degrees();
+        // println!("Current facing: {:?} Dest facing: {:?}", current_degrees, dest_degrees);
+
+        // Surely there's a more elegant way to do this, but when looking at the current and
+        // destination facing angles, this mess handles turning the shorter distance when crossing
+        // 0 degrees.
+        if current_degrees == 0.0 && dest_degrees > 180 {
+            self.draw_rotation = 358_f32.to_radians();
+        } else if dest_degrees == 0 && current_degrees > 357.0 {
+            self.draw_rotation = 0_f32.to_radians();
+        } else {
+            let dr: f32 = if dest_degrees == 0 && current_degrees > 180.0 || dest_degrees as f32 > current_degrees
+            {
+                2.0
+            } else {
+                -2.0
+            };
+            self.draw_rotation += dr.to_radians();
+        }
+        if (current_degrees - (dest_degrees as f32)).abs() < 3.0 {
+            self.draw_rotation = (dest_degrees as f32).to_radians();
+            self.position.facing = self.moving_to.as_ref().unwrap().facing;
+        }
+    }
+}
+
+impl Actor for Sprite {
+    fn invalidate(&mut self) {
+      self.scale = None;
+      self.draw_dest = None;
+    }
+
+    fn draw(&mut self, ctx: &mut Context, map_state: &MapState) -> GameResult<()> {
+        if self.scale.is_none() {
+          self.scale = Some((map_state.hex_height - 4.0) / self.image.height() as f32);
+        }
+
+        if self.draw_dest.is_none() {
+            self.draw_dest = Some(map::hex_to_screen(&self.position.hex, map_state));
+            self.draw_rotation = self.position.facing.to_angle();
+        } else if self.moving_to.is_some() {
+            let moving_to = self.moving_to.as_ref().unwrap();
+            if moving_to.facing != self.position.facing {
+                self.set_next_draw_rotation();
+            } else {
+                self.set_next_draw_dest(map_state);
+            }
+        }
+
+        let draw_param = graphics::DrawParam::new()
+            .dest(self.draw_dest.unwrap())
+            .rotation(self.draw_rotation)
+            .offset(Point2::new(0.5, 0.5)) // render from center
+            .scale(Vector2::new(self.scale.unwrap(), self.scale.unwrap()));
+        graphics::draw(ctx, &self.image, draw_param)
+    }
+
+    fn is_moving(&self) -> bool {
+        self.moving_to.is_some()
+    }
+
+    // Heads for the new position. If the ship has already moved on again,
+    // the sprite jumps to where it was going and starts from there.
+    fn move_to(&mut self, new_position: Position) {
+        if self.moving_to.unwrap_or(self.position) == new_position {
+            return;
+        }
+        if self.moving_to.is_some() {
+            self.done_moving();
+            self.draw_dest = None;
+        }
+        println!("Moving to {:?}", new_position);
+        self.moving_to = Some(new_position);
+    }
+}
@@ -0,0 +1,325 @@
+// Seeking weapons: drones and plasma torpedoes that cross the map on the
+// impulse chart, steering toward their target until they reach its hex.
+
+use crate::combat::Target;
+use crate::damage::DamageAllocation;
+use crate::dice::Dice;
+use crate::hex::{Facing, Hex, Map, Shift};
+use crate::impulse::moves_on_impulse;
+use crate::movement::Side;
+use crate::shields::ShieldDamage;
+use crate::ship::Position;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum SeekingKind {
+    Drone,
+    Plasma,
+}
+
+impl SeekingKind {
+    pub fn speed(&self) -> u8 {
+        match self {
+            SeekingKind::Drone => 8,
+            SeekingKind::Plasma => 32,
+        }
+    }
+
+    // Hexes it can travel before it burns out.
+    pub fn range(&self) -> u8 {
+        match self {
+            SeekingKind::Drone => 24,
+            SeekingKind::Plasma => 15,
+        }
+    }
+
+    // Damage on impact after travelling the given number of hexes. Plasma
+    // dissipates as it goes.
+    fn warhead(&self, moved: u8) -> u8 {
+        match self {
+            SeekingKind::Drone => 12,
+            SeekingKind::Plasma => match moved {
+                0..=5 => 20,
+                6..=10 => 15,
+                _ => 10,
+            },
+        }
+    }
+}
+
+impl fmt::Display for SeekingKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SeekingKind::Drone => write!(f, "Drone"),
+            SeekingKind::Plasma => write!(f, "Plasma Torpedo"),
+        }
+    }
+}
+
+// Damage it takes to shoot down a drone.
+const DRONE_HIT_POINTS: u8 = 4;
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum SeekerMove {
+    // Not its turn to move on this impulse.
+    Waiting,
+    Moved(Position),
+    // It reached its target's hex.
+    Hit,
+    BurnedOut,
+    OffMap,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SeekingWeapon {
+    pub name: String,
+    pub kind: SeekingKind,
+    pub position: Position,
+    // Indexes of the ship that launched it and the one it's after.
+    pub launcher: usize,
+    pub target: usize,
+    // The hex it last moved from, which decides the shield it strikes.
+    came_from: Hex,
+    moved: u8,
+    damage_taken: u8,
+}
+
+impl SeekingWeapon {
+    pub fn launch(
+        name: String,
+        kind: SeekingKind,
+        position: Position,
+        launcher: usize,
+        target: usize,
+    ) -> SeekingWeapon {
+        SeekingWeapon {
+            name,
+            kind,
+            position,
+            launcher,
+            target,
+            came_from: position.hex,
+            moved: 0,
+            damage_taken: 0,
+        }
+    }
+
+    pub fn warhead(&self) -> u8 {
+        let warhead = self.kind.warhead(self.moved);
+        match self.kind {
+            SeekingKind::Drone => warhead,
+            // Every two points of damage weaken the plasma by one.
+            SeekingKind::Plasma => warhead.saturating_sub(self.damage_taken / 2),
+        }
+    }
+
+    // The facing one hexside closer to pointing at the target.
+    fn turn_toward(&self, target: &Hex) -> Facing {
+        let facing = self.position.facing;
+        let (a, b) = self.position.hex.bearing_to(target).relative_to(facing).sides();
+        if a == Facing::A || b == Facing::A {
+            return facing;
+        }
+        let side = match a {
+            Facing::B | Facing::C | Facing::D => Side::Right,
+            _ => Side::Left,
+        };
+        side.turn(facing)
+    }
+
+    // Moves on the impulse chart at the weapon's speed, turning before each
+    // hex it moves. Seekers can turn every hex.
+    pub fn on_impulse(&mut self, impulse: u8, target: &Hex, map: &Map) -> SeekerMove {
+        if self.position.hex == *target {
+            return SeekerMove::Hit;
+        }
+        if !moves_on_impulse(self.kind.speed(), impulse) {
+            return SeekerMove::Waiting;
+        }
+        if self.moved >= self.kind.range() {
+            return SeekerMove::BurnedOut;
+        }
+        let facing = self.turn_toward(target);
+        let hex = match map.neighbor(&self.position.hex, facing) {
+            Some(hex) => hex,
+            None => return SeekerMove::OffMap,
+        };
+        self.came_from = self.position.hex;
+        self.position = Position { hex, facing };
+        self.moved += 1;
+        if hex == *target {
+            SeekerMove::Hit
+        } else {
+            SeekerMove::Moved(self.position)
+        }
+    }
+
+    // Moves along with everything else on a floating map.
+    pub fn shift(&mut self, shift: Shift) {
+        self.position.hex = self.position.hex.shifted(shift);
+        self.came_from = self.came_from.shifted(shift);
+    }
+
+    // Strikes the target on the shield facing the hex it came in from.
+    pub fn strike(&self, target: &mut dyn Target, dice: &mut dyn Dice) -> SeekerHit {
+        let shield = target.take_hit(&self.came_from, self.warhead());
+        let internals = target.take_internal_damage(shield.internal, dice);
+        SeekerHit {
+            seeker: self.name.clone(),
+            target: target.name().to_string(),
+            shield,
+            internals,
+            target_destroyed: target.is_destroyed(),
+        }
+    }
+}
+
+impl Target for SeekingWeapon {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn position(&self) -> Position {
+        self.position
+    }
+
+    // Seekers have no shields; everything lands on the weapon itself.
+    fn take_hit(&mut self, _from: &Hex, damage: u8) -> ShieldDamage {
+        self.damage_taken = self.damage_taken.saturating_add(damage);
+        ShieldDamage {
+            shield: 0,
+            damage,
+            reinforcement_absorbed: 0,
+            shield_absorbed: 0,
+            internal: damage,
+        }
+    }
+
+    fn take_internal_damage(&mut self, _hits: u8, _dice: &mut dyn Dice) -> Vec<DamageAllocation> {
+        Vec::new()
+    }
+
+    fn is_destroyed(&self) -> bool {
+        match self.kind {
+            SeekingKind::Drone => self.damage_taken >= DRONE_HIT_POINTS,
+            SeekingKind::Plasma => self.warhead() == 0,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum LaunchError {
+    // No launcher of the kind is loaded or armed and ready to launch.
+    NoLauncher(SeekingKind),
+    // Seeking weapons are only launched at enemy ships.
+    NotAnEnemy(usize),
+}
+
+impl fmt::Display for LaunchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LaunchError::NoLauncher(kind) => write!(f, "No {} launcher is ready", kind),
+            LaunchError::NotAnEnemy(ship) => write!(f, "Ship {} isn't an enemy", ship),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct SeekerHit {
+    pub seeker: String,
+    pub target: String,
+    pub shield: ShieldDamage,
+    pub internals: Vec<DamageAllocation>,
+    pub target_destroyed: bool,
+}
+
+impl fmt::Display for SeekerHit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} hits {}: {}", self.seeker, self.target, self.shield)?;
+        if !self.internals.is_empty() {
+            let internals: Vec<String> = self.internals.iter().map(|i| i.to_string()).collect();
+            writeln!(f, "  Internals: {}", internals.join(", "))?;
+        }
+        if self.target_destroyed {
+            writeln!(f, "  {} is destroyed!", self.target)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex::STANDARD_MAP;
+
+    fn drone(col: i16, row: i16, facing: Facing) -> SeekingWeapon {
+        let position = Position {
+            hex: Hex::new(col, row),
+            facing,
+        };
+        SeekingWeapon::launch(String::from("Drone 1"), SeekingKind::Drone, position, 1, 0)
+    }
+
+    // Runs impulses until the seeker does something other than move or wait.
+    fn run(seeker: &mut SeekingWeapon, target: &Hex) -> (SeekerMove, Vec<Position>) {
+        let mut path = Vec::new();
+        for impulse in (1..=32).cycle().take(320) {
+            match seeker.on_impulse(impulse, target, &STANDARD_MAP) {
+                SeekerMove::Waiting => (),
+                SeekerMove::Moved(p) => path.push(p),
+                other => return (other, path),
+            }
+        }
+        panic!("the seeker never arrived");
+    }
+
+    #[test]
+    fn moves_on_the_impulse_chart() {
+        let mut d = drone(10, 10, Facing::A);
+        let target = Hex::new(10, 0);
+        let moved: Vec<u8> = (1..=32)
+            .filter(|i| d.on_impulse(*i, &target, &STANDARD_MAP) != SeekerMove::Waiting)
+            .collect();
+        assert_eq!(moved, vec![4, 8, 12, 16, 20, 24, 28, 32]);
+        assert_eq!(d.position.hex, Hex::new(10, 2));
+    }
+
+    #[test]
+    fn turns_toward_the_target() {
+        // Launched facing away, it comes around one hexside per hex.
+        let mut d = drone(10, 10, Facing::D);
+        let target = Hex::new(10, 5);
+        let (result, path) = run(&mut d, &target);
+        assert_eq!(result, SeekerMove::Hit);
+        let facings: Vec<Facing> = path.iter().take(3).map(|p| p.facing).collect();
+        assert_eq!(facings, vec![Facing::E, Facing::F, Facing::A]);
+        assert_eq!(d.position.hex, target);
+    }
+
+    #[test]
+    fn burns_out() {
+        let mut d = drone(0, 0, Facing::C);
+        let far = Hex::new(59, 29);
+        let (result, path) = run(&mut d, &far);
+        assert_eq!(result, SeekerMove::BurnedOut);
+        assert_eq!(path.len(), SeekingKind::Drone.range() as usize);
+    }
+
+    #[test]
+    fn phaser_damage() {
+        let mut d = drone(10, 10, Facing::A);
+        d.take_hit(&Hex::new(10, 5), 3);
+        assert!(!d.is_destroyed());
+        d.take_hit(&Hex::new(10, 5), 1);
+        assert!(d.is_destroyed());
+
+        let position = d.position;
+        let mut plasma = SeekingWeapon::launch(String::from("Plasma 1"), SeekingKind::Plasma, position, 1, 0);
+        assert_eq!(plasma.warhead(), 20);
+        plasma.take_hit(&position.hex, 9);
+        assert_eq!(plasma.warhead(), 16);
+        plasma.take_hit(&position.hex, 40);
+        assert!(plasma.is_destroyed());
+    }
+}
@@ -0,0 +1,198 @@
+// Runtime shield state. Shields are numbered 1 through 6 clockwise from the
+// front of the ship, matching the facings: shield #1 covers whatever lies in
+// direction A relative to the ship's facing, #2 direction B, and so on. Here
+// they're indexed 0 through 5.
+
+use crate::hex::Hex;
+use crate::ship::Position;
+use crate::ship_spec::Defenses;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct ShieldDamage {
+    pub shield: usize,
+    pub damage: u8,
+    pub reinforcement_absorbed: u8,
+    pub shield_absorbed: u8,
+    // What got past the shield and reaches the ship's internal systems.
+    pub internal: u8,
+}
+
+impl fmt::Display for ShieldDamage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} on shield #{}: {} stopped by reinforcement, {} by the shield, {} internal",
+            self.damage,
+            self.shield + 1,
+            self.reinforcement_absorbed,
+            self.shield_absorbed,
+            self.internal
+        )
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Shields {
+    boxes: [u8; 6],
+    // General reinforcement protects every shield and is used up as it absorbs damage.
+    general_reinforcement: u8,
+    specific_reinforcement: [u8; 6],
+}
+
+impl Shields {
+    pub fn new(defenses: &Defenses) -> Shields {
+        Shields {
+            boxes: [
+                defenses.shield1,
+                defenses.shield2,
+                defenses.shield3,
+                defenses.shield4,
+                defenses.shield5,
+                defenses.shield6,
+            ],
+            general_reinforcement: 0,
+            specific_reinforcement: [0; 6],
+        }
+    }
+
+    pub fn boxes(&self) -> [u8; 6] {
+        self.boxes
+    }
+
+    // Everything standing between the shield's arc and the ship's hull.
+    pub fn strength(&self, shield: usize) -> u16 {
+        self.boxes[shield] as u16
+            + self.specific_reinforcement[shield] as u16
+            + self.general_reinforcement as u16
+    }
+
+    // Sets this turn's reinforcement, replacing whatever was left from the last.
+    pub fn reinforce(&mut self, general: u8, specific: [u8; 6]) {
+        self.general_reinforcement = general;
+        self.specific_reinforcement = specific;
+    }
+
+    // The shield facing a unit in the given hex. Fire along the boundary
+    // between two shields goes to the stronger of the two, and fire from
+    // within the target's own hex strikes shield #1.
+    pub fn facing_shield(&self, target: &Position, from: &Hex) -> usize {
+        if target.hex == *from {
+            return 0;
+        }
+        let bearing = target.hex.bearing_to(from).relative_to(target.facing);
+        let (a, b) = bearing.sides();
+        let (a, b) = (a as usize, b as usize);
+        if self.strength(b) > self.strength(a) {
+            b
+        } else {
+            a
+        }
+    }
+
+    // Applies damage to one shield: specific reinforcement takes it first,
+    // then general reinforcement, then the shield boxes. Anything left over
+    // is internal damage.
+    pub fn absorb(&mut self, shield: usize, damage: u8) -> ShieldDamage {
+        let mut left = damage;
+
+        let specific = left.min(self.specific_reinforcement[shield]);
+        self.specific_reinforcement[shield] -= specific;
+        left -= specific;
+
+        let general = left.min(self.general_reinforcement);
+        self.general_reinforcement -= general;
+        left -= general;
+
+        let boxes = left.min(self.boxes[shield]);
+        self.boxes[shield] -= boxes;
+        left -= boxes;
+
+        ShieldDamage {
+            shield,
+            damage,
+            reinforcement_absorbed: specific + general,
+            shield_absorbed: boxes,
+            internal: left,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex::Facing;
+
+    fn shields() -> Shields {
+        Shields::new(&Defenses {
+            shield1: 30,
+            shield2: 24,
+            shield3: 20,
+            shield4: 20,
+            shield5: 20,
+            shield6: 24,
+            armor: 0,
+        })
+    }
+
+    fn target(facing: Facing) -> Position {
+        Position {
+            hex: Hex::new(39, 1),
+            facing,
+        }
+    }
+
+    #[test]
+    fn shield_by_bearing() {
+        let s = shields();
+        let ahead = Hex::new(39, 0);
+        assert_eq!(s.facing_shield(&target(Facing::A), &ahead), 0);
+        assert_eq!(s.facing_shield(&target(Facing::B), &ahead), 5);
+        assert_eq!(s.facing_shield(&target(Facing::D), &ahead), 3);
+
+        let lower_left = Hex::new(38, 2);
+        assert_eq!(s.facing_shield(&target(Facing::A), &lower_left), 4);
+        assert_eq!(s.facing_shield(&target(Facing::E), &lower_left), 0);
+    }
+
+    #[test]
+    fn boundary_goes_to_stronger_shield() {
+        let mut s = shields();
+        // On the line between shields #1 and #2.
+        let boundary = Hex::new(40, 0);
+        assert_eq!(s.facing_shield(&target(Facing::A), &boundary), 0);
+        s.absorb(0, 10);
+        assert_eq!(s.facing_shield(&target(Facing::A), &boundary), 1);
+    }
+
+    #[test]
+    fn same_hex_hits_shield_one() {
+        let s = shields();
+        let t = target(Facing::C);
+        assert_eq!(s.facing_shield(&t, &t.hex), 0);
+    }
+
+    #[test]
+    fn absorption_order() {
+        let mut s = shields();
+        s.reinforce(2, [0, 0, 3, 0, 0, 0]);
+        assert_eq!(
+            s.absorb(2, 30),
+            ShieldDamage {
+                shield: 2,
+                damage: 30,
+                reinforcement_absorbed: 5,
+                shield_absorbed: 20,
+                internal: 5,
+            }
+        );
+        assert_eq!(s.boxes()[2], 0);
+        assert_eq!(s.strength(2), 0);
+        // General reinforcement is gone from every shield.
+        assert_eq!(s.strength(3), 20);
+
+        let through = s.absorb(2, 4);
+        assert_eq!(through.internal, 4);
+    }
+}
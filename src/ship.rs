@@ -1,165 +1,340 @@
-use crate::hex::{Facing, Hex};
-use crate::screen::map::MapState;
-//use crate::screen::Actor;
+use crate::combat::{Combatant, Target};
+use crate::damage::{DamageAllocation, System, Systems};
+use crate::dice::Dice;
+use crate::energy::{AllocationError, EnergyAllocation, WeaponEnergy};
+use crate::hex::{Facing, Hex, Map};
+use crate::movement::{HighEnergyTurn, MovementError, MovementState, Side, TurnMode};
+use crate::path::{self, Path};
+use crate::seeking::SeekingKind;
+use crate::shields::{ShieldDamage, Shields};
 use crate::ship_spec::*;
-use ggez::graphics;
-use ggez::nalgebra::{Point2, Vector2};
-use ggez::{Context, GameResult};
-use std::path;
+use crate::weapons::{Mount, PhaserCapacitor, WeaponKind};
+use serde::{Deserialize, Serialize};
 
-const IMAGE_PATH: &str = "/gfx/ships";
-
-#[derive(PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct Position {
     pub hex: Hex,
     pub facing: Facing,
 }
 
-#[derive(Default, Debug)]
-pub struct EnergyAllocation {
-    pub warp_available: u8,
-    pub impulse_available: u8,
-    pub reactor_available: u8,
+impl Position {
+    // The shortest legal path from here to a hex, ending with the given
+    // facing if there is one.
+    pub fn path_to(
+        &self,
+        map: &Map,
+        movement: &MovementState,
+        turn_mode: TurnMode,
+        speed: u8,
+        to: Hex,
+        facing: Option<Facing>,
+    ) -> Option<Path> {
+        path::find(map, *self, movement, turn_mode, speed, to, facing)
+    }
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Ship {
-    pub image: graphics::Image,
     pub position: Position,
-    pub moving_to: Option<Position>,
     pub speed: u8,
 
     spec: ShipSpec,
     energy_alloc: Option<EnergyAllocation>,
-
-    scale: Option<f32>,
-    draw_dest: Option<Point2<f32>>,
-    draw_rotation: f32,
+    battery: u8,
+    movement: MovementState,
+    shields: Shields,
+    systems: Systems,
+    weapons: Vec<Mount>,
+    capacitor: PhaserCapacitor,
 }
 
 impl Ship {
-    pub fn new(ctx: &mut Context, spec_file: &str, position: Position, speed: u8) -> Ship {
-        let spec = ShipSpec::new(spec_file);
-        let mut path = path::PathBuf::from(IMAGE_PATH);
-        path.push(&spec.fx.image);
-        let image = graphics::Image::new(ctx, path).unwrap();
+    pub fn new(spec_file: &str, position: Position, speed: u8) -> Ship {
+        Ship::from_spec(ShipSpec::new(spec_file), position, speed)
+    }
+
+    pub fn from_spec(spec: ShipSpec, position: Position, speed: u8) -> Ship {
+        let battery = spec.power.battery;
+        let shields = Shields::new(&spec.defenses);
+        let systems = Systems::new(&spec);
+        let weapons = spec.weapons.iter().map(Mount::new).collect();
+        let capacitor = PhaserCapacitor::new(&spec.weapons);
         Ship {
-            image,
             position,
-            moving_to: None,
             speed,
 
             spec,
             energy_alloc: None,
+            battery,
+            movement: MovementState::new(),
+            shields,
+            systems,
+            weapons,
+            capacitor,
+        }
+    }
 
-            scale: None,
-            draw_dest: None,
-            draw_rotation: 0.0,
+    pub fn spec(&self) -> &ShipSpec {
+        &self.spec
+    }
+
+    // The allocation committed this turn, or a fresh one to start from.
+    pub fn get_energy_allocation(&self) -> EnergyAllocation {
+        match &self.energy_alloc {
+            Some(e) => e.clone(),
+            None => self.budget(),
         }
     }
 
-    pub fn invalidate(&mut self) {
-      self.scale = None;
-      self.draw_dest = None;
+    // What the ship has to spend this turn, with nothing spent yet.
+    fn budget(&self) -> EnergyAllocation {
+        let mut e = EnergyAllocation::new(&self.spec, &self.systems, self.battery, self.speed);
+        e.heavy_weapons = self.weapon_energy();
+        e.capacitor_charge = self.capacitor.charge();
+        e.capacitor_capacity = self.capacitor.capacity();
+        e
     }
 
-    pub fn draw(&mut self, ctx: &mut Context, map_state: &MapState) -> GameResult<()> {
-        if self.scale.is_none() {
-          self.scale = Some((map_state.hex_height - 4.0) / self.image.height() as f32);
+    // A line for each working heavy weapon, with nothing paid yet.
+    fn weapon_energy(&self) -> Vec<WeaponEnergy> {
+        let mut lines = Vec::new();
+        for (i, mount) in self.weapons.iter().enumerate() {
+            if let (Some(cycle), false) = (mount.arming_cycle(), mount.destroyed) {
+                lines.push(WeaponEnergy {
+                    mount: i,
+                    label: format!("{} {}", mount.weapon.kind, mount.weapon.mount),
+                    energy: 0,
+                    required: cycle.required(mount.carried()),
+                    max: cycle.max_energy(mount.carried()),
+                    proximity: if cycle.proximity_allowed(mount.carried()) {
+                        Some(false)
+                    } else {
+                        None
+                    },
+                });
+            }
         }
+        lines
+    }
+
+    // Commits an allocation for the turn. The ship's speed and battery charge
+    // follow from it, and its limits must be the ship's own. Once committed
+    // it stands until the turn is over.
+    pub fn set_energy_allocation(&mut self, e: EnergyAllocation) -> Result<(), AllocationError> {
+        if self.energy_alloc.is_some() {
+            return Err(AllocationError::AlreadyAllocated);
+        }
+        self.check_mounts(&e)?;
+        e.check_limits(&self.budget())?;
+        e.validate()?;
+        self.speed = e.speed;
+        self.battery = e.battery_after();
+        self.shields
+            .reinforce(e.general_reinforcement, e.specific_reinforcement);
+        self.capacitor.allocate(e.phaser_capacitor);
+        for w in &e.heavy_weapons {
+            self.weapons[w.mount].arm(w.energy, w.proximity.unwrap_or(false));
+        }
+        self.energy_alloc.replace(e);
+        Ok(())
+    }
 
-        if self.draw_dest.is_none() {
-            self.draw_dest = Some(self.position.hex.to_screen(map_state));
-            self.draw_rotation = self.position.facing.to_angle();
-        } else if self.moving_to.is_some() {
-            let moving_to = self.moving_to.as_ref().unwrap();
-            if moving_to.facing != self.position.facing {
-                self.set_next_draw_rotation();
-            } else {
-                self.set_next_draw_dest(map_state);
+    // Every heavy weapon line must name a different working mount that arms.
+    fn check_mounts(&self, e: &EnergyAllocation) -> Result<(), AllocationError> {
+        for (i, w) in e.heavy_weapons.iter().enumerate() {
+            match self.weapons.get(w.mount) {
+                Some(mount) if mount.arming_cycle().is_some() && !mount.destroyed => {}
+                _ => return Err(AllocationError::NotArmable(w.mount)),
+            }
+            if e.heavy_weapons[..i].iter().any(|other| other.mount == w.mount) {
+                return Err(AllocationError::DuplicateWeapon(w.mount));
             }
         }
+        Ok(())
+    }
+
+    // Forgets the committed allocation, though not what it has done to the
+    // ship, for a copy shown to the other player.
+    pub fn conceal_allocation(&mut self) {
+        self.energy_alloc = None;
+    }
+
+    pub fn shields(&self) -> &Shields {
+        &self.shields
+    }
+
+    pub fn capacitor(&self) -> &PhaserCapacitor {
+        &self.capacitor
+    }
+
+    pub fn systems(&self) -> &Systems {
+        &self.systems
+    }
+
+    // The working weapon mounts, by index, that can fire at the given hex.
+    pub fn weapons_bearing_on(&self, target: &Hex) -> Vec<usize> {
+        (0..self.weapons.len())
+            .filter(|i| self.weapons[*i].bears_on(&self.position, target))
+            .collect()
+    }
+
+    pub fn weapon_kind(&self, mount: usize) -> WeaponKind {
+        self.weapons[mount].weapon.kind
+    }
+
+    // The weapon mounts, by index, that can fire on this impulse.
+    pub fn weapons_ready(&self, turn: u16, impulse: u8) -> Vec<usize> {
+        (0..self.weapons.len())
+            .filter(|i| self.weapons[*i].ready(turn, impulse))
+            .collect()
+    }
+
+    // A launcher of the given kind that can launch now, by mount index.
+    pub fn launcher_ready(&self, kind: SeekingKind, turn: u16, impulse: u8) -> Option<usize> {
+        self.weapons_ready(turn, impulse)
+            .into_iter()
+            .find(|i| self.weapons[*i].weapon.kind.launches() == Some(kind))
+    }
+
+    pub fn launch(&mut self, mount: usize, turn: u16, impulse: u8) {
+        self.weapons[mount].launch(turn, impulse);
+    }
+
+    // Closes out the turn: heavy weapons carry their arming into the next
+    // turn, or lose it if they weren't paid for, and next turn starts with a
+    // fresh energy allocation.
+    pub fn end_turn(&mut self) {
+        for mount in self.weapons.iter_mut() {
+            mount.end_turn();
+        }
+        self.capacitor.end_turn();
+        self.energy_alloc = None;
+    }
+
+    // Turns one hexside, if the ship's turn mode allows it at its current speed.
+    pub fn turn(&mut self, side: Side) -> Result<(), MovementError> {
+        let facing = self
+            .movement
+            .turn(self.position.facing, side, self.spec.movement.turn_mode, self.speed)?;
+        self.rotate_to(facing);
+        Ok(())
+    }
 
-        let draw_param = graphics::DrawParam::new()
-            .dest(self.draw_dest.unwrap())
-            .rotation(self.draw_rotation)
-            .offset(Point2::new(0.5, 0.5)) // render from center
-            .scale(Vector2::new(self.scale.unwrap(), self.scale.unwrap()));
-        graphics::draw(ctx, &self.image, draw_param)
+    // Moves one hex straight ahead, counting toward the turn mode.
+    pub fn advance(&mut self, to: Position) {
+        self.movement.record_move();
+        self.move_to(to);
     }
 
-    pub fn get_energy_allocation(&mut self) -> EnergyAllocation {
-        match self.energy_alloc.take() {
-            Some(e) => e,
-            None => EnergyAllocation::default(),
+    // Turns to any facing at the cost of energy and the risk of a breakdown,
+    // which leaves the ship dead in space for the rest of the turn. The energy
+    // comes from the turn's committed allocation.
+    pub fn high_energy_turn(&mut self, facing: Facing, dice: &mut dyn Dice) -> Result<HighEnergyTurn, MovementError> {
+        let energy = match self.energy_alloc.as_mut() {
+            Some(energy) => energy,
+            None => {
+                return Err(MovementError::InsufficientEnergy {
+                    required: self.spec.movement.het_cost,
+                    available: 0,
+                })
+            }
+        };
+        let het = self
+            .movement
+            .high_energy_turn(facing, &self.spec.movement, energy, dice)?;
+        if het.broke_down {
+            self.speed = 0;
         }
+        self.rotate_to(het.facing);
+        Ok(het)
     }
 
-    pub fn set_energy_allocation(&mut self, e: EnergyAllocation) {
-        self.energy_alloc.replace(e);
+    // Pivots one hexside while stopped.
+    pub fn tactical_maneuver(&mut self, side: Side, turn: u16, impulse: u8) -> Result<(), MovementError> {
+        let facing = self
+            .movement
+            .tactical_maneuver(self.position.facing, side, self.speed, turn, impulse)?;
+        self.rotate_to(facing);
+        Ok(())
+    }
+
+    // Slips into the forward-left or forward-right hex without changing facing.
+    pub fn sideslip(&mut self, side: Side, map: &Map) -> Result<(), MovementError> {
+        let to = self.movement.sideslip(self.position, side, map)?;
+        self.move_to(to);
+        Ok(())
     }
 
+    // The shortest path the ship could take to a hex at its current speed,
+    // given how far it has moved since it last turned.
+    pub fn path_to(&self, map: &Map, to: Hex, facing: Option<Facing>) -> Option<Path> {
+        self.position.path_to(
+            map,
+            &self.movement,
+            self.spec.movement.turn_mode,
+            self.speed,
+            to,
+            facing,
+        )
+    }
 
     pub fn rotate_to(&mut self, new_facing: Facing) {
-        println!("Changing to facing {:?}", new_facing);
-        self.moving_to = Some(Position {
-            hex: self.position.hex,
-            facing: new_facing,
-        });
+        self.position.facing = new_facing;
     }
 
     pub fn move_to(&mut self, new_position: Position) {
-        println!("Moving to {:?}", new_position);
-        self.moving_to = Some(new_position);
+        self.position = new_position;
     }
+}
 
-    fn done_moving(&mut self) {
-        if self.moving_to.is_some() {
-            println!("Destination reached!");
-            self.position = self.moving_to.take().unwrap();
-        }
+impl Combatant for Ship {
+    fn weapons(&self) -> &[Mount] {
+        &self.weapons
     }
 
-    fn set_next_draw_dest(&mut self, map_state: &MapState) {
-        let started_at = self.position.hex.to_screen(map_state);
-        let moving_to = self.moving_to.as_ref().unwrap();
+    fn weapons_mut(&mut self) -> &mut [Mount] {
+        &mut self.weapons
+    }
 
-        let end_dest = moving_to.hex.to_screen(map_state);
-        let dx = (end_dest.x - started_at.x) / 45.0;
-        let dy = (end_dest.y - started_at.y) / 45.0;
-        let v = Vector2::new(dx, dy);
+    fn capacitor_mut(&mut self) -> &mut PhaserCapacitor {
+        &mut self.capacitor
+    }
+}
 
-        self.draw_dest.replace(self.draw_dest.unwrap() + v);
-        let new_dest = self.draw_dest.unwrap();
-        // println!("Current: {:?}  Dest: {:?}", new_dest, end_dest);
-        if ulps_eq!(new_dest, end_dest, epsilon = f32::EPSILON, max_ulps = 10_000) {
-            self.done_moving();
-        }
+impl Target for Ship {
+    fn name(&self) -> &str {
+        &self.spec.ship.name
     }
 
-    fn set_next_draw_rotation(&mut self) {
-        let current_degrees = self.draw_rotation.to_degrees();
-        let dest_degrees = self.moving_to.as_ref().unwrap().facing.to_degrees();
-        // println!("Current facing: {:?} Dest facing: {:?}", current_degrees, dest_degrees);
-
-        // Surely there's a more elegant way to do this, but when looking at the current and
-        // destination facing angles, this mess handles turning the shorter distance when crossing
-        // 0 degrees.
-        if current_degrees == 0.0 && dest_degrees > 180 {
-            self.draw_rotation = 358_f32.to_radians();
-        } else if dest_degrees == 0 && current_degrees > 357.0 {
-            self.draw_rotation = 0_f32.to_radians();
-        } else {
-            let dr: f32 = if dest_degrees == 0 && current_degrees > 180.0 || dest_degrees as f32 > current_degrees
+    fn position(&self) -> Position {
+        self.position
+    }
+
+    // Takes a hit from a unit in the given hex on whichever shield faces it.
+    fn take_hit(&mut self, from: &Hex, damage: u8) -> ShieldDamage {
+        let shield = self.shields.facing_shield(&self.position, from);
+        self.shields.absorb(shield, damage)
+    }
+
+    // Resolves the internal damage from one volley on the Damage Allocation
+    // Chart. Lost battery boxes take their stored charge with them, and each
+    // weapon hit knocks out the first working mount of that kind.
+    fn take_internal_damage(&mut self, hits: u8, dice: &mut dyn Dice) -> Vec<DamageAllocation> {
+        let allocations = self.systems.allocate(hits, dice);
+        self.battery = self.battery.min(self.systems.remaining(System::Battery));
+        for system in allocations.iter().filter_map(|a| a.system) {
+            if let Some(mount) = self
+                .weapons
+                .iter_mut()
+                .find(|m| !m.destroyed && m.weapon.kind.system() == system)
             {
-                2.0
-            } else {
-                -2.0
-            };
-            self.draw_rotation += dr.to_radians();
-        }
-        if (current_degrees - (dest_degrees as f32)).abs() < 3.0 {
-            self.draw_rotation = (dest_degrees as f32).to_radians();
-            self.position.facing = self.moving_to.as_ref().unwrap().facing;
+                mount.destroyed = true;
+            }
         }
+        allocations
+    }
+
+    fn is_destroyed(&self) -> bool {
+        self.systems.is_destroyed()
     }
 }
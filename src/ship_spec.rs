@@ -1,24 +1,28 @@
-use serde::Deserialize;
-use std::fs::File;
-use std::io::Read;
-use std::path;
+use crate::movement::TurnMode;
+use crate::resources;
+use crate::weapons::{Arc, WeaponKind};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
 
-const SPECS_PATH: &str = "./resources/ship_specs";
+const SPECS_DIR: &str = "ship_specs";
 
-#[derive(Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ShipSpec {
     pub fx: Fx,
     pub defenses: Defenses,
     pub power: Power,
     pub ship: Ship,
+    pub movement: Movement,
+    pub weapons: Vec<Weapon>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Fx {
     pub image: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Defenses {
     pub shield1: u8,
     pub shield2: u8,
@@ -29,36 +33,113 @@ pub struct Defenses {
     pub armor: u8,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Power {
     pub left_warp: u8,
     pub center_warp: u8,
     pub right_warp: u8,
     pub impulse: u8,
+    pub reactor: u8,
     pub battery: u8,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Ship {
+    pub name: String,
+    pub bridge: u8,
     pub forward_hull: u8,
     pub aft_hull: u8,
+    pub tractor: u8,
+    pub transporter: u8,
+    pub shuttle: u8,
+    pub excess_damage: u8,
+    pub life_support: u8,
+    pub fire_control: u8,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Movement {
+    pub turn_mode: TurnMode,
+    // Energy to move one hex.
+    pub movement_cost: u8,
+    // Energy cost of a high energy turn.
+    pub het_cost: u8,
+    // High energy turns break down on a d6 roll of this or higher.
+    pub breakdown: u8,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Weapon {
+    pub kind: WeaponKind,
+    // The mount's designation on the ship's SSD, e.g. "A" or "3".
+    pub mount: String,
+    pub arc: Arc,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum SpecError {
+    // The file couldn't be read.
+    Io { path: String, error: String },
+    Invalid { spec_file: String, error: String },
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpecError::Io { path, error } => write!(f, "Failed to open {}: {}", path, error),
+            SpecError::Invalid { spec_file, error } => write!(f, "Invalid ship spec '{}': {}", spec_file, error),
+        }
+    }
 }
 
 impl ShipSpec {
+    // Loads a spec that's known to be good, panicking if it isn't.
     pub fn new(spec_file: &str) -> ShipSpec {
-        let mut path = path::PathBuf::from(SPECS_PATH);
+        match ShipSpec::load(spec_file) {
+            Err(err) => panic!("{}", err),
+            Ok(s) => s,
+        }
+    }
+
+    pub fn load(spec_file: &str) -> Result<ShipSpec, SpecError> {
+        let mut path = resources::path(SPECS_DIR);
         path.push(spec_file);
 
-        let mut file = match File::open(&path) {
-            Err(err) => panic!("Failed to open {}: {}", path.display(), err),
-            Ok(file) => file,
-        };
-        let mut s = String::new();
-        file.read_to_string(&mut s).unwrap();
+        let s = fs::read_to_string(&path).map_err(|err| SpecError::Io {
+            path: path.display().to_string(),
+            error: err.to_string(),
+        })?;
+        toml::from_str(&s).map_err(|err| SpecError::Invalid {
+            spec_file: spec_file.to_string(),
+            error: err.to_string(),
+        })
+    }
+}
 
-        match toml::from_str(&s) {
-            Err(err) => panic!("Invalid ship spec '{}': {}", spec_file, err),
-            Ok(s) => s,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ships_have_weapons() {
+        let ca = ShipSpec::new("federation/ca.toml");
+        assert_eq!(ca.weapons.iter().filter(|w| w.kind == WeaponKind::PhotonTorpedo).count(), 4);
+        assert!(ca.weapons.iter().all(|w| w.kind != WeaponKind::Disruptor));
+
+        let d7 = ShipSpec::new("klingon/d7.toml");
+        assert_eq!(d7.weapons.iter().filter(|w| w.kind == WeaponKind::Disruptor).count(), 4);
+        assert!(d7.weapons.iter().any(|w| w.arc == Arc::RA));
+        assert!(d7.weapons.iter().all(|w| w.kind.launches().is_none()));
+
+        let bc = ShipSpec::new("kzinti/bc.toml");
+        assert_eq!(bc.weapons.iter().filter(|w| w.kind == WeaponKind::DroneRack).count(), 2);
+    }
+
+    #[test]
+    fn missing_spec() {
+        match ShipSpec::load("klingon/d8.toml") {
+            Err(SpecError::Io { path, .. }) => assert!(path.ends_with("klingon/d8.toml")),
+            _ => panic!("loaded a spec that doesn't exist"),
         }
     }
 }
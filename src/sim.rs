@@ -0,0 +1,217 @@
+// Headless play: runs whole games between controllers, with no window and no
+// player, and totals up how they went.
+
+use crate::combat::Target;
+use crate::controller::Controller;
+use crate::game::{Game, Order, Outcome};
+use crate::ship::Ship;
+use serde::Serialize;
+use std::fmt;
+
+// How one ship came out of a game.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct ShipResult {
+    pub name: String,
+    pub side: String,
+    pub destroyed: bool,
+    // Shield boxes lost and internal boxes destroyed.
+    pub shield_damage: u16,
+    pub internal_damage: u16,
+}
+
+impl ShipResult {
+    fn new(ship: &Ship, side: &str) -> ShipResult {
+        let spec = &ship.spec().defenses;
+        let shields = [
+            spec.shield1,
+            spec.shield2,
+            spec.shield3,
+            spec.shield4,
+            spec.shield5,
+            spec.shield6,
+        ];
+        let remaining = ship.shields().boxes();
+        ShipResult {
+            name: ship.spec().ship.name.clone(),
+            side: side.to_string(),
+            destroyed: ship.is_destroyed(),
+            shield_damage: shields.iter().zip(remaining.iter()).map(|(s, r)| (s - r) as u16).sum(),
+            internal_damage: ship
+                .systems()
+                .damaged()
+                .iter()
+                .map(|(_, remaining, total)| (total - remaining) as u16)
+                .sum(),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct GameResult {
+    pub seed: u64,
+    // Turns started, counting the one the game ended in.
+    pub turns: u16,
+    // The winning side, or None for a draw.
+    pub winner: Option<String>,
+    pub ships: Vec<ShipResult>,
+}
+
+// Plays a game out to the end under its scenario's rules, which need a turn
+// limit in case nobody wins. Each ship gets its own controller, in the same
+// order as the ships. Orders the rules don't allow are ignored.
+pub fn play(mut game: Game, seed: u64, controllers: &mut [Box<dyn Controller>]) -> GameResult {
+    let in_play = |game: &Game| -> Vec<usize> {
+        (0..game.ships.len())
+            .filter(|i| !game.ships[*i].is_destroyed())
+            .collect()
+    };
+    let outcome = loop {
+        if let Some(outcome) = game.outcome() {
+            break outcome;
+        }
+        if game.between_turns() {
+            for i in in_play(&game) {
+                let e = controllers[i].allocate(&game, i);
+                let _ = game.order(i, &Order::Allocate(e));
+            }
+        }
+        for i in in_play(&game) {
+            for order in controllers[i].orders(&game, i) {
+                let _ = game.order(i, &order);
+            }
+        }
+        if let Some(outcome) = game.outcome() {
+            break outcome;
+        }
+        game.run_impulse();
+    };
+
+    GameResult {
+        seed,
+        turns: game.turn(),
+        winner: match outcome {
+            Outcome::Won(side) => Some(side),
+            Outcome::Draw => None,
+        },
+        ships: (0..game.ships.len())
+            .map(|i| ShipResult::new(&game.ships[i], &game.sides[game.side_of(i)]))
+            .collect(),
+    }
+}
+
+// Totals for one ship across a batch of games.
+#[derive(Clone, Default, PartialEq, Debug, Serialize)]
+pub struct ShipSummary {
+    pub name: String,
+    pub wins: u32,
+    pub losses: u32,
+    pub average_shield_damage: f32,
+    pub average_internal_damage: f32,
+}
+
+#[derive(Clone, Default, PartialEq, Debug, Serialize)]
+pub struct Summary {
+    pub games: u32,
+    pub draws: u32,
+    pub average_turns: f32,
+    pub ships: Vec<ShipSummary>,
+}
+
+impl Summary {
+    // Ships are matched up by their place in the game, not their name, so
+    // mirror matches add up correctly.
+    pub fn new(results: &[GameResult]) -> Summary {
+        let games = results.len() as u32;
+        let average = |total: u32| if games == 0 { 0.0 } else { total as f32 / games as f32 };
+        let ship_count = results.first().map(|r| r.ships.len()).unwrap_or(0);
+        let ships = (0..ship_count)
+            .map(|i| {
+                let name = results[0].ships[i].name.clone();
+                let won = |r: &&GameResult| r.winner.as_ref() == Some(&r.ships[i].side);
+                ShipSummary {
+                    wins: results.iter().filter(won).count() as u32,
+                    losses: results.iter().filter(|r| r.ships[i].destroyed).count() as u32,
+                    average_shield_damage: average(results.iter().map(|r| r.ships[i].shield_damage as u32).sum()),
+                    average_internal_damage: average(results.iter().map(|r| r.ships[i].internal_damage as u32).sum()),
+                    name,
+                }
+            })
+            .collect();
+        Summary {
+            games,
+            draws: results.iter().filter(|r| r.winner.is_none()).count() as u32,
+            average_turns: average(results.iter().map(|r| r.turns as u32).sum()),
+            ships,
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} games, {} drawn, {:.1} turns on average",
+            self.games, self.draws, self.average_turns
+        )?;
+        writeln!(
+            f,
+            "{:<20} {:>6} {:>6} {:>14} {:>16}",
+            "Ship", "Wins", "Losses", "Shield damage", "Internal damage"
+        )?;
+        for ship in &self.ships {
+            writeln!(
+                f,
+                "{:<20} {:>6} {:>6} {:>14.1} {:>16.1}",
+                ship.name, ship.wins, ship.losses, ship.average_shield_damage, ship.average_internal_damage
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::{Brawler, Scripted};
+    use crate::scenario::Scenario;
+
+    fn duel(seed: u64) -> Game {
+        Scenario::load("the_duel.toml").unwrap().start(seed).unwrap()
+    }
+
+    fn brawl(seed: u64) -> GameResult {
+        let mut controllers: Vec<Box<dyn Controller>> =
+            vec![Box::new(Brawler { fire_range: 8 }), Box::new(Brawler { fire_range: 8 })];
+        play(duel(seed), seed, &mut controllers)
+    }
+
+    #[test]
+    fn same_seed_same_game() {
+        assert_eq!(brawl(3), brawl(3));
+    }
+
+    #[test]
+    fn brawlers_do_damage() {
+        let result = brawl(11);
+        assert!(result.ships.iter().any(|s| s.shield_damage > 0));
+        if let Some(winner) = &result.winner {
+            assert!(result.ships.iter().any(|s| s.destroyed && s.side != *winner));
+        }
+    }
+
+    #[test]
+    fn turn_limit() {
+        let mut game = duel(1);
+        game.rules.turn_limit = Some(2);
+        let mut controllers: Vec<Box<dyn Controller>> =
+            vec![Box::new(Scripted::new(0, Vec::new())), Box::new(Scripted::new(0, Vec::new()))];
+        let result = play(game, 1, &mut controllers);
+        assert_eq!(result.turns, 2);
+        assert_eq!(result.winner, None);
+        assert!(result.ships.iter().all(|s| s.shield_damage == 0 && s.internal_damage == 0));
+
+        let summary = Summary::new(&[result]);
+        assert_eq!(summary.draws, 1);
+        assert_eq!(summary.ships[1].name, "Klingon D7");
+    }
+}
@@ -0,0 +1,748 @@
+// Weapon types, firing arcs, arming cycles, and the runtime state of a ship's
+// weapon mounts.
+
+use crate::cube::Cube;
+use crate::damage::System;
+use crate::hex::Hex;
+use crate::impulse::IMPULSES_PER_TURN;
+use crate::seeking::SeekingKind;
+use crate::ship::Position;
+use crate::ship_spec::Weapon;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub enum WeaponKind {
+    #[serde(rename = "phaser-1")]
+    Phaser1,
+    #[serde(rename = "phaser-2")]
+    Phaser2,
+    #[serde(rename = "phaser-3")]
+    Phaser3,
+    #[serde(rename = "disruptor")]
+    Disruptor,
+    #[serde(rename = "photon-torpedo")]
+    PhotonTorpedo,
+    #[serde(rename = "drone-rack")]
+    DroneRack,
+    #[serde(rename = "plasma-torpedo")]
+    PlasmaTorpedo,
+}
+
+impl WeaponKind {
+    // The kind of box this weapon occupies on the Damage Allocation Chart.
+    pub fn system(&self) -> System {
+        use WeaponKind::*;
+        match self {
+            Phaser1 | Phaser2 | Phaser3 => System::Phaser,
+            Disruptor | PhotonTorpedo | PlasmaTorpedo => System::Torpedo,
+            DroneRack => System::Drone,
+        }
+    }
+
+    // The seeking weapon a launcher sends out. Launchers don't fire directly.
+    pub fn launches(&self) -> Option<SeekingKind> {
+        use WeaponKind::*;
+        match self {
+            DroneRack => Some(SeekingKind::Drone),
+            PlasmaTorpedo => Some(SeekingKind::Plasma),
+            Phaser1 | Phaser2 | Phaser3 | Disruptor | PhotonTorpedo => None,
+        }
+    }
+
+    // How many seeking weapons a launcher holds, for those that are loaded
+    // rather than armed with energy.
+    pub fn ammunition(&self) -> Option<u8> {
+        match self {
+            WeaponKind::DroneRack => Some(4),
+            _ => None,
+        }
+    }
+
+    // Energy drawn from the phaser capacitor to fire a phaser. A phaser-3
+    // takes half a point under the full rules; energy here is in whole
+    // points, so it takes one.
+    pub fn phaser_energy(&self) -> Option<u8> {
+        use WeaponKind::*;
+        match self {
+            Phaser1 | Phaser2 | Phaser3 => Some(1),
+            Disruptor | PhotonTorpedo | DroneRack | PlasmaTorpedo => None,
+        }
+    }
+
+    // Heavy weapons have to be armed with energy before they can fire.
+    pub fn arming_cycle(&self) -> Option<ArmingCycle> {
+        use WeaponKind::*;
+        match self {
+            PhotonTorpedo => Some(ArmingCycle {
+                turns: 2,
+                energy: 2,
+                hold: 1,
+                overload: 4,
+                proximity: true,
+            }),
+            Disruptor => Some(ArmingCycle {
+                turns: 1,
+                energy: 2,
+                hold: 1,
+                overload: 2,
+                proximity: false,
+            }),
+            PlasmaTorpedo => Some(ArmingCycle {
+                turns: 3,
+                energy: 2,
+                hold: 2,
+                overload: 0,
+                proximity: false,
+            }),
+            Phaser1 | Phaser2 | Phaser3 | DroneRack => None,
+        }
+    }
+}
+
+impl fmt::Display for WeaponKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use WeaponKind::*;
+        let name = match self {
+            Phaser1 => "Phaser-1",
+            Phaser2 => "Phaser-2",
+            Phaser3 => "Phaser-3",
+            Disruptor => "Disruptor",
+            PhotonTorpedo => "Photon Torpedo",
+            DroneRack => "Drone Rack",
+            PlasmaTorpedo => "Plasma Torpedo",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Firing arcs around the firing ship relative to its facing. Each is a
+// wedge whose edges run along the hex rows, or straight across the ship for
+// the halves, and targets exactly on an edge are in it.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Arc {
+    // Forward
+    FA,
+    // Left front and right front
+    LF,
+    RF,
+    // Forward half and rear half
+    FH,
+    RH,
+    // Rear
+    RA,
+    // Left side and right side
+    LS,
+    RS,
+    #[serde(rename = "360")]
+    All,
+}
+
+// Each twelfth of a circle clockwise from straight up, as a step of whole
+// columns and half rows. The even ones run along the hex rows and the odd
+// ones straight across the hexsides between them. Columns and half rows are
+// both stretched by a positive amount on screen, so which side of one of
+// these a hex falls on comes out the same either way.
+const TWELFTHS: [(i32, i32); 12] = [
+    (0, -2),
+    (1, -3),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (1, 3),
+    (0, 2),
+    (-1, 3),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (-1, -3),
+];
+
+impl Arc {
+    // Where the arc starts, in twelfths of a circle clockwise from dead
+    // ahead, and how many twelfths it spans clockwise from there. Even
+    // numbers are hex rows, so FA runs from the row at -60 degrees to the
+    // row at +60.
+    fn wedge(&self) -> (usize, usize) {
+        match self {
+            Arc::FA => (10, 4),
+            Arc::LF => (8, 4),
+            Arc::RF => (0, 4),
+            Arc::FH => (9, 6),
+            Arc::RH => (3, 6),
+            Arc::RA => (4, 4),
+            Arc::LS => (6, 6),
+            Arc::RS => (0, 6),
+            Arc::All => (0, 12),
+        }
+    }
+
+    // Whether the target hex lies within this arc of a ship at the given
+    // position. A target in the firing ship's own hex is in every arc.
+    pub fn contains(&self, shooter: &Position, target: &Hex) -> bool {
+        let (start, span) = self.wedge();
+        if shooter.hex == *target || span >= 12 {
+            return true;
+        }
+        let d = Cube::from(*target) - Cube::from(shooter.hex);
+        let to = (d.x, 2 * d.z + d.x);
+        let turn = 2 * shooter.facing as usize;
+        let first = TWELFTHS[(start + turn) % 12];
+        let last = TWELFTHS[(start + span + turn) % 12];
+        // Clockwise of the first edge and anticlockwise of the last.
+        let clockwise = |a: (i32, i32), b: (i32, i32)| a.0 * b.1 - a.1 * b.0 >= 0;
+        clockwise(first, to) && clockwise(to, last)
+    }
+}
+
+// A weapon can't fire again until this many impulses after its last shot,
+// even across the end of a turn.
+pub const FIRING_DELAY: u8 = 8;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum ArmingMode {
+    Standard,
+    // Extra energy on the final arming turn for more damage at short range.
+    Overload,
+    // Less damage, but only at long range.
+    Proximity,
+}
+
+// How a heavy weapon arms: for a number of turns at a fixed energy per turn,
+// then held ready at the hold cost each turn after. On the final arming turn
+// it can take up to `overload` extra energy.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct ArmingCycle {
+    pub turns: u8,
+    pub energy: u8,
+    pub hold: u8,
+    pub overload: u8,
+    pub proximity: bool,
+}
+
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Arming {
+    #[default]
+    Unarmed,
+    // Partway through the cycle, with the turns paid for so far.
+    Charging {
+        turns: u8,
+        energy: u8,
+    },
+    Armed {
+        mode: ArmingMode,
+        energy: u8,
+    },
+}
+
+impl ArmingCycle {
+    // Energy in a weapon armed the standard way.
+    pub fn standard_energy(&self) -> u8 {
+        self.turns * self.energy
+    }
+
+    fn final_turn(&self, arming: Arming) -> bool {
+        match arming {
+            Arming::Unarmed => self.turns == 1,
+            Arming::Charging { turns, .. } => turns + 1 == self.turns,
+            Arming::Armed { .. } => false,
+        }
+    }
+
+    // What it takes this turn to keep the weapon arming, or to hold it armed.
+    pub fn required(&self, arming: Arming) -> u8 {
+        match arming {
+            Arming::Armed { .. } => self.hold,
+            _ => self.energy,
+        }
+    }
+
+    // The most energy the weapon can take this turn.
+    pub fn max_energy(&self, arming: Arming) -> u8 {
+        if self.final_turn(arming) {
+            self.energy + self.overload
+        } else {
+            self.required(arming)
+        }
+    }
+
+    // Whether the weapon can be set for a proximity fuse this turn.
+    pub fn proximity_allowed(&self, arming: Arming) -> bool {
+        self.proximity && self.final_turn(arming)
+    }
+
+    // The weapon's state once this turn's energy is paid. Anything short of
+    // the requirement loses the arming and whatever energy went into it.
+    // Energy beyond the requirement on the final turn overloads the weapon.
+    pub fn advance(&self, arming: Arming, energy: u8, proximity: bool) -> Arming {
+        if energy < self.required(arming) {
+            return Arming::Unarmed;
+        }
+        match arming {
+            Arming::Armed { .. } => arming,
+            Arming::Unarmed | Arming::Charging { .. } => {
+                let (turns, stored) = match arming {
+                    Arming::Charging { turns, energy } => (turns, energy),
+                    _ => (0, 0),
+                };
+                let energy = energy.min(self.max_energy(arming));
+                if !self.final_turn(arming) {
+                    return Arming::Charging {
+                        turns: turns + 1,
+                        energy: stored + energy,
+                    };
+                }
+                let mode = if energy > self.energy {
+                    ArmingMode::Overload
+                } else if proximity && self.proximity {
+                    ArmingMode::Proximity
+                } else {
+                    ArmingMode::Standard
+                };
+                Arming::Armed {
+                    mode,
+                    energy: stored + energy,
+                }
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct CapacitorError {
+    pub needed: u8,
+    pub charge: u8,
+}
+
+impl fmt::Display for CapacitorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The phaser capacitor holds {} energy but firing needs {}",
+            self.charge, self.needed
+        )
+    }
+}
+
+// Phasers fire with energy stored in the capacitor, which is big enough to
+// fire every phaser on the ship once. Charge stays in it from turn to turn.
+#[derive(Clone, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct PhaserCapacitor {
+    capacity: u8,
+    charge: u8,
+    // The charge the turn started with, and what's been fired since.
+    carried: u8,
+    spent: u8,
+}
+
+impl PhaserCapacitor {
+    pub fn new(weapons: &[Weapon]) -> PhaserCapacitor {
+        PhaserCapacitor {
+            capacity: weapons.iter().filter_map(|w| w.kind.phaser_energy()).sum(),
+            ..Default::default()
+        }
+    }
+
+    pub fn capacity(&self) -> u8 {
+        self.capacity
+    }
+
+    pub fn charge(&self) -> u8 {
+        self.charge
+    }
+
+    // Charges the capacitor with this turn's allocation, replacing whatever
+    // an earlier allocation this turn put in. Energy already fired this turn
+    // stays spent.
+    pub fn allocate(&mut self, energy: u8) {
+        self.charge = (self.carried + energy).min(self.capacity).saturating_sub(self.spent);
+    }
+
+    pub fn discharge(&mut self, energy: u8) -> Result<(), CapacitorError> {
+        if energy > self.charge {
+            return Err(CapacitorError {
+                needed: energy,
+                charge: self.charge,
+            });
+        }
+        self.charge -= energy;
+        self.spent += energy;
+        Ok(())
+    }
+
+    pub fn end_turn(&mut self) {
+        self.carried = self.charge;
+        self.spent = 0;
+    }
+}
+
+// A weapon as mounted on a particular ship during the game.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Mount {
+    pub weapon: Weapon,
+    pub destroyed: bool,
+    pub arming: Arming,
+    // The arming carried into this turn, before this turn's energy is paid.
+    carried: Arming,
+    paid: bool,
+    fired_this_turn: bool,
+    last_fired: Option<(u16, u8)>,
+    // Seeking weapons left in a launcher that's loaded rather than armed.
+    #[serde(default)]
+    ammo: u8,
+}
+
+impl Mount {
+    pub fn new(weapon: &Weapon) -> Mount {
+        Mount {
+            weapon: weapon.clone(),
+            destroyed: false,
+            arming: Arming::Unarmed,
+            carried: Arming::Unarmed,
+            paid: false,
+            fired_this_turn: false,
+            last_fired: None,
+            ammo: weapon.kind.ammunition().unwrap_or(0),
+        }
+    }
+
+    pub fn bears_on(&self, shooter: &Position, target: &Hex) -> bool {
+        !self.destroyed && self.weapon.arc.contains(shooter, target)
+    }
+
+    pub fn arming_cycle(&self) -> Option<ArmingCycle> {
+        self.weapon.kind.arming_cycle()
+    }
+
+    // The arming this mount starts the turn with.
+    pub fn carried(&self) -> Arming {
+        self.carried
+    }
+
+    // Pays this turn's arming energy. It can be paid again, say when the
+    // allocation is edited, and replaces what was paid before. A weapon that
+    // has already fired this turn stays empty.
+    pub fn arm(&mut self, energy: u8, proximity: bool) {
+        if let Some(cycle) = self.arming_cycle() {
+            if !self.destroyed && !self.fired_this_turn {
+                self.arming = cycle.advance(self.carried, energy, proximity);
+                self.paid = true;
+            }
+        }
+    }
+
+    pub fn ammo(&self) -> u8 {
+        self.ammo
+    }
+
+    // Whether the weapon is armed or loaded (if it needs to be) and has
+    // waited long enough since it last fired.
+    pub fn ready(&self, turn: u16, impulse: u8) -> bool {
+        if self.destroyed || self.fired_this_turn {
+            return false;
+        }
+        if self.weapon.kind.ammunition().is_some() && self.ammo == 0 {
+            return false;
+        }
+        if self.arming_cycle().is_some() && !matches!(self.arming, Arming::Armed { .. }) {
+            return false;
+        }
+        match self.last_fired {
+            // Never ready at a time before the last shot.
+            Some((t, i)) => absolute_impulse(turn, impulse)
+                .checked_sub(absolute_impulse(t, i))
+                .is_some_and(|waited| waited >= FIRING_DELAY as u32),
+            None => true,
+        }
+    }
+
+    // Records a shot. Heavy weapons are emptied by firing.
+    pub fn fire(&mut self, turn: u16, impulse: u8) {
+        self.fired_this_turn = true;
+        self.last_fired = Some((turn, impulse));
+        self.arming = Arming::Unarmed;
+    }
+
+    // Records a launch, which uses up the launcher's arming or one of the
+    // seeking weapons loaded in it.
+    pub fn launch(&mut self, turn: u16, impulse: u8) {
+        self.fire(turn, impulse);
+        self.ammo = self.ammo.saturating_sub(1);
+    }
+
+    // Carries the weapon's arming into the next turn. A heavy weapon that
+    // wasn't paid for this turn loses its arming.
+    pub fn end_turn(&mut self) {
+        if !self.paid || self.destroyed {
+            self.arming = Arming::Unarmed;
+        }
+        self.carried = self.arming;
+        self.paid = false;
+        self.fired_this_turn = false;
+    }
+}
+
+fn absolute_impulse(turn: u16, impulse: u8) -> u32 {
+    turn as u32 * IMPULSES_PER_TURN as u32 + impulse as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex::Facing;
+
+    fn shooter(facing: Facing) -> Position {
+        Position {
+            hex: Hex::new(39, 1),
+            facing,
+        }
+    }
+
+    #[test]
+    fn forward_arc() {
+        let ahead = Hex::new(39, 0);
+        let boundary = Hex::new(40, 0);
+        let right = Hex::new(40, 1);
+        assert!(Arc::FA.contains(&shooter(Facing::A), &ahead));
+        assert!(Arc::FA.contains(&shooter(Facing::A), &boundary));
+        assert!(Arc::FA.contains(&shooter(Facing::B), &right));
+        assert!(!Arc::FA.contains(&shooter(Facing::D), &ahead));
+        // The hex row at 60 degrees is the edge of the arc.
+        assert!(Arc::FA.contains(&shooter(Facing::A), &right));
+        assert!(!Arc::FA.contains(&shooter(Facing::A), &Hex::new(41, 1)));
+        assert!(!Arc::LF.contains(&shooter(Facing::A), &boundary));
+        assert!(Arc::RF.contains(&shooter(Facing::A), &boundary));
+    }
+
+    #[test]
+    fn arcs_end_on_the_hex_rows() {
+        let from = |facing| Position {
+            hex: Hex::new(20, 20),
+            facing,
+        };
+        // Ten hexes out along the rows at -60 and +60 degrees, and the hexes
+        // either side of them.
+        for (on, inside, outside) in &[
+            (Hex::new(10, 15), Hex::new(10, 14), Hex::new(10, 16)),
+            (Hex::new(30, 15), Hex::new(30, 14), Hex::new(30, 16)),
+        ] {
+            assert!(Arc::FA.contains(&from(Facing::A), on));
+            assert!(Arc::FA.contains(&from(Facing::A), inside));
+            assert!(!Arc::FA.contains(&from(Facing::A), outside));
+        }
+        // Nearly abeam, well outside the forward arc.
+        assert!(!Arc::FA.contains(&from(Facing::A), &Hex::new(30, 19)));
+
+        // The row at 120 degrees closes the right front arc.
+        assert!(Arc::RF.contains(&from(Facing::A), &Hex::new(30, 25)));
+        assert!(Arc::RF.contains(&from(Facing::A), &Hex::new(30, 24)));
+        assert!(!Arc::RF.contains(&from(Facing::A), &Hex::new(30, 26)));
+
+        // Facing B, the forward arc runs from straight up to the row at 120.
+        assert!(Arc::FA.contains(&from(Facing::B), &Hex::new(20, 10)));
+        assert!(!Arc::FA.contains(&from(Facing::B), &Hex::new(19, 10)));
+        assert!(Arc::FA.contains(&from(Facing::B), &Hex::new(30, 25)));
+        assert!(!Arc::FA.contains(&from(Facing::B), &Hex::new(30, 26)));
+    }
+
+    #[test]
+    fn side_arcs() {
+        let lower_left = Hex::new(38, 2);
+        let lower_right = Hex::new(40, 2);
+        assert!(Arc::LS.contains(&shooter(Facing::A), &lower_left));
+        assert!(!Arc::LS.contains(&shooter(Facing::A), &lower_right));
+        assert!(Arc::RS.contains(&shooter(Facing::A), &lower_right));
+        assert!(Arc::RH.contains(&shooter(Facing::A), &lower_right));
+        assert!(!Arc::FH.contains(&shooter(Facing::A), &lower_right));
+        // Directly to the side, on the boundary between the halves.
+        let side = Hex::new(41, 1);
+        assert!(Arc::FH.contains(&shooter(Facing::A), &side));
+        assert!(Arc::RH.contains(&shooter(Facing::A), &side));
+        // Dead ahead and dead astern are on the edges of both sides.
+        for target in &[Hex::new(39, 0), Hex::new(39, 2)] {
+            assert!(Arc::LS.contains(&shooter(Facing::A), target));
+            assert!(Arc::RS.contains(&shooter(Facing::A), target));
+        }
+        assert!(Arc::RA.contains(&shooter(Facing::A), &lower_left));
+        assert!(!Arc::RA.contains(&shooter(Facing::A), &side));
+    }
+
+    #[test]
+    fn all_around_and_same_hex() {
+        let s = shooter(Facing::C);
+        for target in &[Hex::new(39, 0), Hex::new(38, 2), s.hex] {
+            assert!(Arc::All.contains(&s, target));
+        }
+        assert!(Arc::RA.contains(&s, &s.hex));
+    }
+
+    #[test]
+    fn destroyed_mounts_dont_bear() {
+        let mut mount = Mount::new(&Weapon {
+            kind: WeaponKind::Phaser1,
+            mount: String::from("1"),
+            arc: Arc::All,
+        });
+        let s = shooter(Facing::A);
+        assert!(mount.bears_on(&s, &Hex::new(39, 0)));
+        mount.destroyed = true;
+        assert!(!mount.bears_on(&s, &Hex::new(39, 0)));
+    }
+
+    fn photon() -> Mount {
+        Mount::new(&Weapon {
+            kind: WeaponKind::PhotonTorpedo,
+            mount: String::from("A"),
+            arc: Arc::FA,
+        })
+    }
+
+    #[test]
+    fn photons_arm_over_two_turns() {
+        let mut m = photon();
+        assert_eq!(m.arming_cycle().unwrap().max_energy(m.carried()), 2);
+        m.arm(2, false);
+        assert_eq!(m.arming, Arming::Charging { turns: 1, energy: 2 });
+        assert!(!m.ready(1, 10));
+        m.end_turn();
+
+        assert_eq!(m.arming_cycle().unwrap().max_energy(m.carried()), 6);
+        m.arm(2, false);
+        assert_eq!(
+            m.arming,
+            Arming::Armed {
+                mode: ArmingMode::Standard,
+                energy: 4
+            }
+        );
+        assert!(m.ready(2, 1));
+        m.end_turn();
+
+        // Held for a turn, then fired.
+        m.arm(1, false);
+        assert!(m.ready(3, 5));
+        m.fire(3, 5);
+        assert!(!m.ready(3, 20));
+        assert_eq!(m.arming, Arming::Unarmed);
+        // Paying again after firing doesn't reload it this turn.
+        m.arm(2, false);
+        assert_eq!(m.arming, Arming::Unarmed);
+    }
+
+    #[test]
+    fn arming_lost_if_unpaid() {
+        let mut m = photon();
+        m.arm(2, false);
+        m.end_turn();
+        m.end_turn();
+        assert_eq!(m.carried(), Arming::Unarmed);
+
+        m.arm(2, false);
+        m.end_turn();
+        m.arm(1, false);
+        assert_eq!(m.arming, Arming::Unarmed);
+    }
+
+    #[test]
+    fn overload_and_proximity() {
+        let cycle = WeaponKind::PhotonTorpedo.arming_cycle().unwrap();
+        let half = Arming::Charging { turns: 1, energy: 2 };
+        assert_eq!(
+            cycle.advance(half, 6, false),
+            Arming::Armed {
+                mode: ArmingMode::Overload,
+                energy: 8
+            }
+        );
+        assert_eq!(
+            cycle.advance(half, 2, true),
+            Arming::Armed {
+                mode: ArmingMode::Proximity,
+                energy: 4
+            }
+        );
+        assert!(cycle.proximity_allowed(half));
+        assert!(!cycle.proximity_allowed(Arming::Unarmed));
+
+        // Disruptors arm in a single turn and have no proximity fuse.
+        let disruptor = WeaponKind::Disruptor.arming_cycle().unwrap();
+        assert_eq!(
+            disruptor.advance(Arming::Unarmed, 2, true),
+            Arming::Armed {
+                mode: ArmingMode::Standard,
+                energy: 2
+            }
+        );
+    }
+
+    #[test]
+    fn firing_delay_spans_turns() {
+        let mut m = Mount::new(&Weapon {
+            kind: WeaponKind::Phaser1,
+            mount: String::from("1"),
+            arc: Arc::All,
+        });
+        assert!(m.ready(1, 1));
+        m.fire(1, 30);
+        m.end_turn();
+        assert!(!m.ready(2, 5));
+        assert!(m.ready(2, 6));
+        assert!(!m.ready(1, 1));
+    }
+
+    #[test]
+    fn launchers() {
+        let mut rack = Mount::new(&Weapon {
+            kind: WeaponKind::DroneRack,
+            mount: String::from("C"),
+            arc: Arc::All,
+        });
+        assert_eq!(rack.ammo(), 4);
+        for turn in 1..=4 {
+            assert!(rack.ready(turn, 1));
+            rack.launch(turn, 1);
+            assert!(!rack.ready(turn, 20));
+            rack.end_turn();
+        }
+        assert_eq!(rack.ammo(), 0);
+        assert!(!rack.ready(5, 1));
+
+        // A plasma torpedo arms like any heavy weapon and is emptied by
+        // launching.
+        let mut plasma = Mount::new(&Weapon {
+            kind: WeaponKind::PlasmaTorpedo,
+            mount: String::from("A"),
+            arc: Arc::FA,
+        });
+        assert!(!plasma.ready(1, 1));
+        for _ in 0..3 {
+            plasma.arm(2, false);
+            plasma.end_turn();
+        }
+        assert!(plasma.ready(4, 1));
+        plasma.launch(4, 1);
+        assert_eq!(plasma.arming, Arming::Unarmed);
+        assert!(!crate::combat::in_range(WeaponKind::PlasmaTorpedo, 1));
+    }
+
+    #[test]
+    fn capacitor() {
+        let spec = crate::ship_spec::ShipSpec::new("federation/ca.toml");
+        let mut c = PhaserCapacitor::new(&spec.weapons);
+        assert_eq!(c.capacity(), 6);
+        c.allocate(4);
+        // Reallocating replaces the earlier charge rather than adding to it.
+        c.allocate(5);
+        assert_eq!(c.charge(), 5);
+        c.discharge(2).unwrap();
+        // Committing the allocation again doesn't give back what was fired.
+        c.allocate(5);
+        assert_eq!(c.charge(), 3);
+        c.end_turn();
+
+        c.allocate(10);
+        assert_eq!(c.charge(), 6);
+        assert_eq!(c.discharge(7), Err(CapacitorError { needed: 7, charge: 6 }));
+        assert_eq!(c.charge(), 6);
+    }
+}